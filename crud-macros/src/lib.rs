@@ -3,8 +3,11 @@ use proc_macro::TokenStream;
 #[allow(unused_imports)]
 use quote::{format_ident, quote};
 use syn::{
+    bracketed,
     parse::{Parse, ParseStream},
-    parse_macro_input, LitStr, Token, Type,
+    parse_macro_input,
+    punctuated::Punctuated,
+    Ident, LitStr, Token, Type,
 };
 
 struct CrudInput {
@@ -12,6 +15,18 @@ struct CrudInput {
     model: Type,
     active_model: Type,
     path: LitStr,
+    read_only: Vec<Ident>,  // Returned but never taken from request bodies
+    hidden: Vec<Ident>,     // Neither returned nor taken from request bodies
+    write_only: Vec<Ident>, // Taken from request bodies but never returned
+    hooks: Option<Type>,    // Lifecycle hooks, auditing only by default
+}
+
+// Parse a field list such as `[id, created]`
+fn parse_fields(input: ParseStream) -> syn::Result<Vec<Ident>> {
+    let content;
+    bracketed!(content in input);
+    let fields = Punctuated::<Ident, Token![,]>::parse_terminated(&content)?;
+    Ok(fields.into_iter().collect())
 }
 
 impl Parse for CrudInput {
@@ -20,6 +35,10 @@ impl Parse for CrudInput {
         let mut model = None;
         let mut active_model = None;
         let mut path = None;
+        let mut read_only = Vec::new();
+        let mut hidden = Vec::new();
+        let mut write_only = Vec::new();
+        let mut hooks = None;
 
         while !input.is_empty() {
             let key: syn::Ident = input.parse()?;
@@ -33,6 +52,14 @@ impl Parse for CrudInput {
                 active_model = Some(input.parse()?);
             } else if key == "path" {
                 path = Some(input.parse()?);
+            } else if key == "read_only" {
+                read_only = parse_fields(input)?;
+            } else if key == "hidden" {
+                hidden = parse_fields(input)?;
+            } else if key == "write_only" {
+                write_only = parse_fields(input)?;
+            } else if key == "hooks" {
+                hooks = Some(input.parse()?);
             } else {
                 return Err(syn::Error::new(key.span(), "Unknown key"));
             }
@@ -47,6 +74,10 @@ impl Parse for CrudInput {
             model: model.ok_or_else(|| input.error("Missing 'model'"))?,
             active_model: active_model.ok_or_else(|| input.error("Missing 'active_model'"))?,
             path: path.ok_or_else(|| input.error("Missing 'path'"))?,
+            read_only,
+            hidden,
+            write_only,
+            hooks,
         })
     }
 }
@@ -60,70 +91,24 @@ pub fn make_crud_routes(input: TokenStream) -> TokenStream {
         model,
         active_model,
         path,
+        read_only,
+        hidden,
+        write_only,
+        hooks,
     } = parse_macro_input!(input as CrudInput);
 
     let path_str = path.value();
 
-    // Conditional logic for password hashing
-    let create_password_logic = if path_str == "/users" {
-        quote! {
-            if let Some(obj) = payload.as_object_mut() {
-                if let Some(password_val) = obj.get("password_hash") {
-                    if let Some(password) = password_val.as_str() {
-                        if !password.is_empty() && !password.starts_with("$argon2") {
-                             match crate::utils::password::hash_password(password) {
-                                 Ok(hashed) => {
-                                     obj.insert("password_hash".to_string(), serde_json::Value::String(hashed));
-                                 },
-                                 Err(e) => return axum::Json(serde_json::json!({ "error": format!("Failed to hash password: {}", e) })).into_response(),
-                             }
-                        }
-                    }
-                }
-            }
-        }
-    } else {
-        quote! {}
-    };
+    // Field names as strings, checked against the model at compile time below
+    let declared_fields: Vec<&Ident> = read_only.iter().chain(&hidden).chain(&write_only).collect();
+    let read_only_names: Vec<String> = read_only.iter().map(Ident::to_string).collect();
+    let hidden_names: Vec<String> = hidden.iter().map(Ident::to_string).collect();
+    let write_only_names: Vec<String> = write_only.iter().map(Ident::to_string).collect();
 
-    let update_password_logic = if path_str == "/users" {
-        quote! {
-            if let Some(obj) = payload.as_object_mut() {
-                let mut should_hash = false;
-                let mut use_existing = false;
-
-                if let Some(password_val) = obj.get("password_hash") {
-                    if let Some(password) = password_val.as_str() {
-                        if password.is_empty() {
-                            use_existing = true;
-                        } else if !password.starts_with("$argon2") {
-                            should_hash = true;
-                        }
-                    }
-                } else {
-                    // If field is missing entirely, we might want to use existing too,
-                    // but set_from_json might complain if we don't put it back.
-                    // However, if it's missing from JSON, set_from_json usually errors for required fields.
-                    // So let's assume we need to put it there.
-                    use_existing = true;
-                }
+    // Audit events name the table acted on, e.g. "users"
+    let target_type = path_str.trim_start_matches('/').to_string();
 
-                if use_existing {
-                     obj.insert("password_hash".to_string(), serde_json::Value::String(model.password_hash.clone()));
-                } else if should_hash {
-                     let password = obj.get("password_hash").unwrap().as_str().unwrap();
-                     match crate::utils::password::hash_password(password) {
-                         Ok(hashed) => {
-                             obj.insert("password_hash".to_string(), serde_json::Value::String(hashed));
-                         },
-                         Err(e) => return axum::Json(serde_json::json!({ "error": format!("Failed to hash password: {}", e) })).into_response(),
-                     }
-                }
-            }
-        }
-    } else {
-        quote! {}
-    };
+    let hooks = hooks.unwrap_or_else(|| syn::parse_quote!(crate::domain::crud::AuditHooks));
 
     let expanded = quote! {
         {
@@ -139,6 +124,21 @@ pub fn make_crud_routes(input: TokenStream) -> TokenStream {
             };
             use std::sync::Arc;
             use serde_json::Value;
+            use crate::domain::crud::{CrudContext, CrudHooks, FieldAccess, ListPage, ListQuery};
+            use crate::domain::error::{AppError, ToResponse};
+
+            // Fields clients can see and change
+            const FIELDS: FieldAccess = FieldAccess {
+                read_only: &[#(#read_only_names),*],
+                hidden: &[#(#hidden_names),*],
+                write_only: &[#(#write_only_names),*],
+            };
+
+            // Fail to compile when a declared field is not on the model
+            #[allow(dead_code)]
+            fn check_declared_fields(model: &#model) {
+                #(let _ = &model.#declared_fields;)*
+            }
 
             // Handlers
 
@@ -146,157 +146,164 @@ pub fn make_crud_routes(input: TokenStream) -> TokenStream {
                 State(state): State<Arc<crate::AppState>>,
                 Query(params): Query<std::collections::HashMap<String, String>>,
             ) -> impl IntoResponse {
-                // Simple pagination
-                let page = params.get("page").and_then(|p| p.parse::<u64>().ok()).unwrap_or(1);
-                let per_page = params.get("per_page").and_then(|p| p.parse::<u64>().ok()).unwrap_or(10);
-
-                let paginator = <#entity>::find()
-                    .paginate(&state.db, per_page);
-
-                let items = paginator.fetch_page(page - 1).await;
-
-                match items {
-                    Ok(items) => {
-                         let total = paginator.num_items().await.unwrap_or(0);
-                         let total_pages = paginator.num_pages().await.unwrap_or(0);
-
-                         axum::Json(serde_json::json!({
-                             "data": items,
-                             "meta": {
-                                 "page": page,
-                                 "per_page": per_page,
-                                 "total": total,
-                                 "total_pages": total_pages
-                             }
-                         })).into_response()
-                    },
-                    Err(e) => {
-                        axum::Json(serde_json::json!({ "error": e.to_string() })).into_response()
-                    }
+                let result: Result<ListPage, AppError> = async {
+                    // Filters, sort order, fields and pagination mode from the query string
+                    let query = ListQuery::<#entity>::parse(&params, FIELDS)?;
+                    query.fetch(&state.db, <#entity>::find()).await
                 }
+                .await;
+
+                let (result, pagination, cursor) = match result {
+                    Ok(page) => (Ok(page.items), page.pagination, page.cursor),
+                    Err(err) => (Err(err), None, None),
+                };
+                result
+                    .to_response("Records retrieved successfully")
+                    .with_pagination(pagination)
+                    .with_cursor(cursor)
             }
 
             async fn get_item(
                 State(state): State<Arc<crate::AppState>>,
                 Path(id): Path<uuid::Uuid>,
             ) -> impl IntoResponse {
-                let item = <#entity>::find_by_id(id)
-                    .one(&state.db)
-                    .await;
-
-                match item {
-                    Ok(Some(item)) => axum::Json(serde_json::json!(item)).into_response(),
-                    Ok(None) => axum::Json(serde_json::json!({ "error": "Not found" })).into_response(),
-                    Err(e) => axum::Json(serde_json::json!({ "error": e.to_string() })).into_response(),
+                let result: Result<Value, AppError> = async {
+                    let model = <#entity>::find_by_id(id)
+                        .one(&state.db)
+                        .await?
+                        .ok_or_else(|| not_found(id))?;
+                    Ok(FIELDS.output(&model))
                 }
+                .await;
+
+                result.to_response("Record retrieved successfully")
             }
 
             async fn create_item(
                 State(state): State<Arc<crate::AppState>>,
-                Json(mut payload): Json<Value>,
+                axum::Extension(actor): axum::Extension<crate::entities::users::Model>,
+                client: crate::utils::client_info::ClientInfo,
+                Json(payload): Json<Value>,
             ) -> impl IntoResponse {
-                // Inject ID and timestamps if missing
-                if let Some(obj) = payload.as_object_mut() {
-                    if !obj.contains_key("id") {
-                        let new_id = uuid::Uuid::now_v7().to_string();
-                        println!("Injecting ID: {}", new_id);
-                        obj.insert("id".to_string(), serde_json::Value::String(new_id));
-                    }
+                let result: Result<Value, AppError> = async {
+                    let mut payload = FIELDS.create_input(payload)?;
+
+                    // Inject ID and timestamps if missing
+                    if let Some(obj) = payload.as_object_mut() {
+                        if !obj.contains_key("id") {
+                            let new_id = uuid::Uuid::now_v7().to_string();
+                            obj.insert("id".to_string(), Value::String(new_id));
+                        }
 
-                    let now = chrono::Utc::now().to_rfc3339();
-                    if !obj.contains_key("created") {
-                        obj.insert("created".to_string(), serde_json::Value::String(now.clone()));
-                    }
-                    if !obj.contains_key("updated") {
-                        obj.insert("updated".to_string(), serde_json::Value::String(now));
+                        let now = chrono::Utc::now().to_rfc3339();
+                        if !obj.contains_key("created") {
+                            obj.insert("created".to_string(), Value::String(now.clone()));
+                        }
+                        if !obj.contains_key("updated") {
+                            obj.insert("updated".to_string(), Value::String(now));
+                        }
                     }
-                }
 
-                #create_password_logic
+                    let ctx = CrudContext::begin(&state, &actor, &client, #target_type).await?;
+                    let hooks = <#hooks>::default();
 
-                println!("Payload before from_json: {:?}", payload);
+                    <#hooks as CrudHooks<#entity>>::before_create(&hooks, &ctx, &mut payload).await?;
 
-                // Use from_json to create ActiveModel
-                let mut active_model = <#active_model>::from_json(payload);
+                    // The payload may not fit the model, e.g. an unknown enum value
+                    let active_model = <#active_model>::from_json(payload)
+                        .map_err(|e| AppError::Validation(e.to_string()))?;
+                    let model = active_model.insert(&ctx.txn).await?;
 
-                match active_model {
-                    Ok(mut am) => {
-                        let res = am.insert(&state.db).await;
-                        match res {
-                            Ok(model) => {
-                                axum::Json(serde_json::json!(model)).into_response()
-                            },
-                            Err(e) => axum::Json(serde_json::json!({ "error": e.to_string() })).into_response(),
-                        }
-                    },
-                    Err(e) => axum::Json(serde_json::json!({ "error": e.to_string() })).into_response(),
+                    <#hooks as CrudHooks<#entity>>::after_create(&hooks, &ctx, &model).await?;
+                    ctx.commit().await?;
+
+                    Ok(FIELDS.output(&model))
                 }
+                .await;
+
+                result.to_response_created("Record created successfully")
             }
 
             async fn update_item(
                 State(state): State<Arc<crate::AppState>>,
+                axum::Extension(actor): axum::Extension<crate::entities::users::Model>,
+                client: crate::utils::client_info::ClientInfo,
                 Path(id): Path<uuid::Uuid>,
-                Json(mut payload): Json<Value>,
+                Json(payload): Json<Value>,
             ) -> impl IntoResponse {
-                // Inject updated timestamp
-                if let Some(obj) = payload.as_object_mut() {
-                     let now = chrono::Utc::now().to_rfc3339();
-                     obj.insert("updated".to_string(), serde_json::Value::String(now));
-                }
+                let result: Result<Value, AppError> = async {
+                    // Lock the row, so the hooks and the audit diff see what is overwritten
+                    let ctx = CrudContext::begin(&state, &actor, &client, #target_type).await?;
+                    let model = <#entity>::find_by_id(id)
+                        .lock_exclusive()
+                        .one(&ctx.txn)
+                        .await?
+                        .ok_or_else(|| not_found(id))?;
+
+                    // Fields missing from the payload keep their current value
+                    let mut payload = FIELDS.update_input(serde_json::json!(model), payload)?;
+
+                    // Inject updated timestamp
+                    if let Some(obj) = payload.as_object_mut() {
+                        let now = chrono::Utc::now().to_rfc3339();
+                        obj.insert("updated".to_string(), Value::String(now));
+                    }
 
-                // First find the item
-                let item = <#entity>::find_by_id(id)
-                    .one(&state.db)
-                    .await;
-
-                match item {
-                    Ok(Some(model)) => {
-                        // Inject password logic here, where we have access to `model`
-                        #update_password_logic
-
-                        let mut active_model: #active_model = model.into();
-
-                        // Update from JSON
-                        // set_from_json takes &mut self and json
-                        match active_model.set_from_json(payload) {
-                            Ok(_) => {
-                                let res = active_model.save(&state.db).await;
-                                match res {
-                                    Ok(updated_am) => {
-                                        match updated_am.try_into_model() {
-                                            Ok(m) => axum::Json(serde_json::json!(m)).into_response(),
-                                            Err(_) => axum::Json(serde_json::json!({ "error": "Failed to convert to model" })).into_response(),
-                                        }
-                                    },
-                                    Err(e) => axum::Json(serde_json::json!({ "error": e.to_string() })).into_response(),
-                                }
-                            },
-                            Err(e) => axum::Json(serde_json::json!({ "error": e.to_string() })).into_response(),
-                        }
-                    },
-                    Ok(None) => axum::Json(serde_json::json!({ "error": "Not found" })).into_response(),
-                    Err(e) => axum::Json(serde_json::json!({ "error": e.to_string() })).into_response(),
+                    let hooks = <#hooks>::default();
+
+                    <#hooks as CrudHooks<#entity>>::before_update(&hooks, &ctx, &model, &mut payload).await?;
+
+                    let mut active_model: #active_model = model.clone().into();
+                    active_model
+                        .set_from_json(payload)
+                        .map_err(|e| AppError::Validation(e.to_string()))?;
+                    let updated = active_model.update(&ctx.txn).await?;
+
+                    <#hooks as CrudHooks<#entity>>::after_update(&hooks, &ctx, &model, &updated).await?;
+                    ctx.commit().await?;
+
+                    Ok(FIELDS.output(&updated))
                 }
+                .await;
+
+                result.to_response("Record updated successfully")
             }
 
             async fn delete_item(
                 State(state): State<Arc<crate::AppState>>,
+                axum::Extension(actor): axum::Extension<crate::entities::users::Model>,
+                client: crate::utils::client_info::ClientInfo,
                 Path(id): Path<uuid::Uuid>,
             ) -> impl IntoResponse {
-                let res = <#entity>::delete_by_id(id)
-                    .exec(&state.db)
-                    .await;
-
-                match res {
-                    Ok(res) => {
-                        if res.rows_affected == 0 {
-                             axum::Json(serde_json::json!({ "error": "Not found" })).into_response()
-                        } else {
-                             axum::Json(serde_json::json!({ "message": "Deleted successfully" })).into_response()
-                        }
-                    },
-                    Err(e) => axum::Json(serde_json::json!({ "error": e.to_string() })).into_response(),
+                let result: Result<(), AppError> = async {
+                    // Keep the deleted row for the hooks
+                    let ctx = CrudContext::begin(&state, &actor, &client, #target_type).await?;
+                    let model = <#entity>::find_by_id(id)
+                        .lock_exclusive()
+                        .one(&ctx.txn)
+                        .await?
+                        .ok_or_else(|| not_found(id))?;
+                    let hooks = <#hooks>::default();
+
+                    <#hooks as CrudHooks<#entity>>::before_delete(&hooks, &ctx, &model).await?;
+
+                    let res = <#entity>::delete_by_id(id).exec(&ctx.txn).await?;
+                    if res.rows_affected == 0 {
+                        return Err(not_found(id));
+                    }
+
+                    <#hooks as CrudHooks<#entity>>::after_delete(&hooks, &ctx, &model).await?;
+                    ctx.commit().await?;
+
+                    Ok(())
                 }
+                .await;
+
+                result.to_response_no_content("Record deleted successfully")
+            }
+
+            fn not_found(id: uuid::Uuid) -> AppError {
+                AppError::NotFound(format!("Record with id {} not found in {}", id, #target_type))
             }
 
             Router::new()
@@ -0,0 +1,6 @@
+-- reverse: create index "idx_refresh_tokens_user_id" to table: "refresh_tokens"
+DROP INDEX "idx_refresh_tokens_user_id";
+-- reverse: create index "idx_refresh_tokens_family_id" to table: "refresh_tokens"
+DROP INDEX "idx_refresh_tokens_family_id";
+-- reverse: create "refresh_tokens" table
+DROP TABLE "refresh_tokens";
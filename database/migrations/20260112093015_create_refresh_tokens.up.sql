@@ -0,0 +1,20 @@
+-- create "refresh_tokens" table
+CREATE TABLE "refresh_tokens" (
+  "id" uuid NOT NULL DEFAULT gen_random_uuid(),
+  "user_id" uuid NOT NULL,
+  "family_id" uuid NOT NULL,
+  "jti_hash" text NOT NULL,
+  "user_agent" text NULL,
+  "ip_address" character varying(64) NULL,
+  "expires_at" timestamptz NOT NULL,
+  "used_at" timestamptz NULL,
+  "revoked_at" timestamptz NULL,
+  "created" timestamptz NOT NULL DEFAULT now(),
+  PRIMARY KEY ("id"),
+  CONSTRAINT "refresh_tokens_jti_hash_key" UNIQUE ("jti_hash"),
+  CONSTRAINT "fk_refresh_tokens_user_id" FOREIGN KEY ("user_id") REFERENCES "users" ("id") ON UPDATE NO ACTION ON DELETE CASCADE
+);
+-- create index "idx_refresh_tokens_family_id" to table: "refresh_tokens"
+CREATE INDEX "idx_refresh_tokens_family_id" ON "refresh_tokens" ("family_id");
+-- create index "idx_refresh_tokens_user_id" to table: "refresh_tokens"
+CREATE INDEX "idx_refresh_tokens_user_id" ON "refresh_tokens" ("user_id");
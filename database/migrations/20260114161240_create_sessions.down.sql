@@ -0,0 +1,12 @@
+-- reverse: create index "idx_refresh_tokens_session_id" to table: "refresh_tokens"
+DROP INDEX "idx_refresh_tokens_session_id";
+-- reverse: modify "refresh_tokens" table
+ALTER TABLE "refresh_tokens" DROP CONSTRAINT "fk_refresh_tokens_session_id";
+-- reverse: rename a column from "family_id" to "session_id"
+ALTER TABLE "refresh_tokens" RENAME COLUMN "session_id" TO "family_id";
+-- reverse: drop index "idx_refresh_tokens_family_id" from table: "refresh_tokens"
+CREATE INDEX "idx_refresh_tokens_family_id" ON "refresh_tokens" ("family_id");
+-- reverse: create index "idx_sessions_user_id" to table: "sessions"
+DROP INDEX "idx_sessions_user_id";
+-- reverse: create "sessions" table
+DROP TABLE "sessions";
@@ -0,0 +1,34 @@
+-- create "sessions" table
+CREATE TABLE "sessions" (
+  "id" uuid NOT NULL DEFAULT gen_random_uuid(),
+  "user_id" uuid NOT NULL,
+  "user_agent" text NULL,
+  "ip_address" character varying(64) NULL,
+  "last_used" timestamptz NOT NULL DEFAULT now(),
+  "revoked_at" timestamptz NULL,
+  "created" timestamptz NOT NULL DEFAULT now(),
+  PRIMARY KEY ("id"),
+  CONSTRAINT "fk_sessions_user_id" FOREIGN KEY ("user_id") REFERENCES "users" ("id") ON UPDATE NO ACTION ON DELETE CASCADE
+);
+-- create index "idx_sessions_user_id" to table: "sessions"
+CREATE INDEX "idx_sessions_user_id" ON "sessions" ("user_id");
+-- backfill "sessions" from existing refresh token families
+INSERT INTO "sessions" ("id", "user_id", "user_agent", "ip_address", "last_used", "revoked_at", "created")
+SELECT
+  "family_id",
+  "user_id",
+  (array_agg("user_agent" ORDER BY "created" DESC))[1],
+  (array_agg("ip_address" ORDER BY "created" DESC))[1],
+  max("created"),
+  CASE WHEN bool_and("revoked_at" IS NOT NULL) THEN max("revoked_at") END,
+  min("created")
+FROM "refresh_tokens"
+GROUP BY "family_id", "user_id";
+-- drop index "idx_refresh_tokens_family_id" from table: "refresh_tokens"
+DROP INDEX "idx_refresh_tokens_family_id";
+-- rename a column from "family_id" to "session_id"
+ALTER TABLE "refresh_tokens" RENAME COLUMN "family_id" TO "session_id";
+-- modify "refresh_tokens" table
+ALTER TABLE "refresh_tokens" ADD CONSTRAINT "fk_refresh_tokens_session_id" FOREIGN KEY ("session_id") REFERENCES "sessions" ("id") ON UPDATE NO ACTION ON DELETE CASCADE;
+-- create index "idx_refresh_tokens_session_id" to table: "refresh_tokens"
+CREATE INDEX "idx_refresh_tokens_session_id" ON "refresh_tokens" ("session_id");
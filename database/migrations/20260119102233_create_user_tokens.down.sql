@@ -0,0 +1,4 @@
+-- reverse: create index "idx_user_tokens_user_id" to table: "user_tokens"
+DROP INDEX "idx_user_tokens_user_id";
+-- reverse: create "user_tokens" table
+DROP TABLE "user_tokens";
@@ -0,0 +1,15 @@
+-- create "user_tokens" table
+CREATE TABLE "user_tokens" (
+  "id" uuid NOT NULL DEFAULT gen_random_uuid(),
+  "user_id" uuid NOT NULL,
+  "purpose" text NOT NULL,
+  "token_hash" text NOT NULL,
+  "expires_at" timestamptz NOT NULL,
+  "used_at" timestamptz NULL,
+  "created" timestamptz NOT NULL DEFAULT now(),
+  PRIMARY KEY ("id"),
+  CONSTRAINT "user_tokens_token_hash_key" UNIQUE ("token_hash"),
+  CONSTRAINT "fk_user_tokens_user_id" FOREIGN KEY ("user_id") REFERENCES "users" ("id") ON UPDATE NO ACTION ON DELETE CASCADE
+);
+-- create index "idx_user_tokens_user_id" to table: "user_tokens"
+CREATE INDEX "idx_user_tokens_user_id" ON "user_tokens" ("user_id");
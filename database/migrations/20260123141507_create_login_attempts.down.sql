@@ -0,0 +1,4 @@
+-- reverse: create index "idx_login_attempts_locked_until" to table: "login_attempts"
+DROP INDEX "idx_login_attempts_locked_until";
+-- reverse: create "login_attempts" table
+DROP TABLE "login_attempts";
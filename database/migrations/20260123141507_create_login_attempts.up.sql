@@ -0,0 +1,10 @@
+-- create "login_attempts" table
+CREATE TABLE "login_attempts" (
+  "key" text NOT NULL,
+  "failures" integer NOT NULL DEFAULT 0,
+  "last_failure" timestamptz NOT NULL DEFAULT now(),
+  "locked_until" timestamptz NULL,
+  PRIMARY KEY ("key")
+);
+-- create index "idx_login_attempts_locked_until" to table: "login_attempts"
+CREATE INDEX "idx_login_attempts_locked_until" ON "login_attempts" ("locked_until");
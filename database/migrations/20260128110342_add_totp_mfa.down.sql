@@ -0,0 +1,10 @@
+-- reverse: create "role_mfa_policies" table
+DROP TABLE "role_mfa_policies";
+-- reverse: create index "idx_recovery_codes_user_id" to table: "recovery_codes"
+DROP INDEX "idx_recovery_codes_user_id";
+-- reverse: create "recovery_codes" table
+DROP TABLE "recovery_codes";
+-- reverse: create "user_totp" table
+DROP TABLE "user_totp";
+-- reverse: modify "sessions" table
+ALTER TABLE "sessions" DROP COLUMN "mfa_verified_at";
@@ -0,0 +1,33 @@
+-- modify "sessions" table
+ALTER TABLE "sessions" ADD COLUMN "mfa_verified_at" timestamptz NULL;
+-- create "user_totp" table
+CREATE TABLE "user_totp" (
+  "user_id" uuid NOT NULL,
+  "secret" text NOT NULL,
+  "confirmed_at" timestamptz NULL,
+  "last_used_step" bigint NULL,
+  "created" timestamptz NOT NULL DEFAULT now(),
+  "updated" timestamptz NOT NULL DEFAULT now(),
+  PRIMARY KEY ("user_id"),
+  CONSTRAINT "fk_user_totp_user_id" FOREIGN KEY ("user_id") REFERENCES "users" ("id") ON UPDATE NO ACTION ON DELETE CASCADE
+);
+-- create "recovery_codes" table
+CREATE TABLE "recovery_codes" (
+  "id" uuid NOT NULL DEFAULT gen_random_uuid(),
+  "user_id" uuid NOT NULL,
+  "code_hash" text NOT NULL,
+  "used_at" timestamptz NULL,
+  "created" timestamptz NOT NULL DEFAULT now(),
+  PRIMARY KEY ("id"),
+  CONSTRAINT "recovery_codes_code_hash_key" UNIQUE ("code_hash"),
+  CONSTRAINT "fk_recovery_codes_user_id" FOREIGN KEY ("user_id") REFERENCES "users" ("id") ON UPDATE NO ACTION ON DELETE CASCADE
+);
+-- create index "idx_recovery_codes_user_id" to table: "recovery_codes"
+CREATE INDEX "idx_recovery_codes_user_id" ON "recovery_codes" ("user_id");
+-- create "role_mfa_policies" table
+CREATE TABLE "role_mfa_policies" (
+  "role" text NOT NULL,
+  "required" boolean NOT NULL DEFAULT false,
+  "updated" timestamptz NOT NULL DEFAULT now(),
+  PRIMARY KEY ("role")
+);
@@ -0,0 +1,6 @@
+-- reverse: create "oidc_login_states" table
+DROP TABLE "oidc_login_states";
+-- reverse: create index "idx_identities_user_id" to table: "identities"
+DROP INDEX "idx_identities_user_id";
+-- reverse: create "identities" table
+DROP TABLE "identities";
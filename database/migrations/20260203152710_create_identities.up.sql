@@ -0,0 +1,25 @@
+-- create "identities" table
+CREATE TABLE "identities" (
+  "id" uuid NOT NULL DEFAULT gen_random_uuid(),
+  "user_id" uuid NOT NULL,
+  "provider" text NOT NULL,
+  "subject" text NOT NULL,
+  "email" character varying(255) NULL,
+  "last_login" timestamptz NULL,
+  "created" timestamptz NOT NULL DEFAULT now(),
+  PRIMARY KEY ("id"),
+  CONSTRAINT "uq_identities_provider_subject" UNIQUE ("provider", "subject"),
+  CONSTRAINT "fk_identities_user_id" FOREIGN KEY ("user_id") REFERENCES "users" ("id") ON UPDATE NO ACTION ON DELETE CASCADE
+);
+-- create index "idx_identities_user_id" to table: "identities"
+CREATE INDEX "idx_identities_user_id" ON "identities" ("user_id");
+-- create "oidc_login_states" table
+CREATE TABLE "oidc_login_states" (
+  "state_hash" text NOT NULL,
+  "provider" text NOT NULL,
+  "code_verifier" text NOT NULL,
+  "nonce" text NOT NULL,
+  "expires_at" timestamptz NOT NULL,
+  "created" timestamptz NOT NULL DEFAULT now(),
+  PRIMARY KEY ("state_hash")
+);
@@ -0,0 +1,4 @@
+-- reverse: create index "idx_api_keys_user_id" to table: "api_keys"
+DROP INDEX "idx_api_keys_user_id";
+-- reverse: create "api_keys" table
+DROP TABLE "api_keys";
@@ -0,0 +1,8 @@
+-- reverse: modify "role_mfa_policies" table
+ALTER TABLE "role_mfa_policies" ALTER COLUMN "role" TYPE text USING "role"::text;
+-- reverse: modify "users" table
+ALTER TABLE "users" ALTER COLUMN "role" DROP DEFAULT, ALTER COLUMN "role" TYPE text USING "role"::text, ALTER COLUMN "status" DROP DEFAULT, ALTER COLUMN "status" TYPE text USING "status"::text, ALTER COLUMN "status" SET DEFAULT 'Pending';
+-- reverse: create enum type "user_status"
+DROP TYPE "user_status";
+-- reverse: create enum type "user_role"
+DROP TYPE "user_role";
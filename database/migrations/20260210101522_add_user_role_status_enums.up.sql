@@ -0,0 +1,14 @@
+-- create enum type "user_role"
+CREATE TYPE "user_role" AS ENUM ('student', 'teacher', 'admin');
+-- create enum type "user_status"
+CREATE TYPE "user_status" AS ENUM ('pending', 'active', 'suspended');
+-- normalize "role" values of table "users"; unknown roles get the least privileged one
+UPDATE "users" SET "role" = CASE WHEN lower(trim("role")) IN ('student', 'teacher', 'admin') THEN lower(trim("role")) ELSE 'student' END;
+-- normalize "status" values of table "users"; accounts in an unknown state are suspended
+UPDATE "users" SET "status" = CASE WHEN lower(trim("status")) IN ('pending', 'active', 'suspended') THEN lower(trim("status")) ELSE 'suspended' END;
+-- modify "users" table
+ALTER TABLE "users" ALTER COLUMN "role" TYPE "user_role" USING "role"::"user_role", ALTER COLUMN "role" SET DEFAULT 'student', ALTER COLUMN "status" DROP DEFAULT, ALTER COLUMN "status" TYPE "user_status" USING "status"::"user_status", ALTER COLUMN "status" SET DEFAULT 'pending';
+-- remove policies of unknown roles from table "role_mfa_policies"
+DELETE FROM "role_mfa_policies" WHERE "role" NOT IN ('student', 'teacher', 'admin');
+-- modify "role_mfa_policies" table
+ALTER TABLE "role_mfa_policies" ALTER COLUMN "role" TYPE "user_role" USING "role"::"user_role";
@@ -0,0 +1,6 @@
+-- reverse: create index "idx_admin_actions_user_id" to table: "admin_actions"
+DROP INDEX "idx_admin_actions_user_id";
+-- reverse: create "admin_actions" table
+DROP TABLE "admin_actions";
+-- reverse: modify "sessions" table
+ALTER TABLE "sessions" DROP CONSTRAINT "fk_sessions_impersonated_by", DROP COLUMN "impersonated_by";
@@ -0,0 +1,16 @@
+-- modify "sessions" table
+ALTER TABLE "sessions" ADD COLUMN "impersonated_by" uuid NULL, ADD CONSTRAINT "fk_sessions_impersonated_by" FOREIGN KEY ("impersonated_by") REFERENCES "users" ("id") ON UPDATE NO ACTION ON DELETE CASCADE;
+-- create "admin_actions" table
+CREATE TABLE "admin_actions" (
+  "id" uuid NOT NULL DEFAULT gen_random_uuid(),
+  "user_id" uuid NOT NULL,
+  "admin_id" uuid NULL,
+  "action" character varying(50) NOT NULL,
+  "reason" text NOT NULL,
+  "created" timestamptz NOT NULL DEFAULT now(),
+  PRIMARY KEY ("id"),
+  CONSTRAINT "fk_admin_actions_admin_id" FOREIGN KEY ("admin_id") REFERENCES "users" ("id") ON UPDATE NO ACTION ON DELETE SET NULL,
+  CONSTRAINT "fk_admin_actions_user_id" FOREIGN KEY ("user_id") REFERENCES "users" ("id") ON UPDATE NO ACTION ON DELETE CASCADE
+);
+-- create index "idx_admin_actions_user_id" to table: "admin_actions"
+CREATE INDEX "idx_admin_actions_user_id" ON "admin_actions" ("user_id");
@@ -0,0 +1,23 @@
+-- reverse: drop "admin_actions" table
+CREATE TABLE "admin_actions" (
+  "id" uuid NOT NULL DEFAULT gen_random_uuid(),
+  "user_id" uuid NOT NULL,
+  "admin_id" uuid NULL,
+  "action" character varying(50) NOT NULL,
+  "reason" text NOT NULL,
+  "created" timestamptz NOT NULL DEFAULT now(),
+  PRIMARY KEY ("id"),
+  CONSTRAINT "fk_admin_actions_admin_id" FOREIGN KEY ("admin_id") REFERENCES "users" ("id") ON UPDATE NO ACTION ON DELETE SET NULL,
+  CONSTRAINT "fk_admin_actions_user_id" FOREIGN KEY ("user_id") REFERENCES "users" ("id") ON UPDATE NO ACTION ON DELETE CASCADE
+);
+CREATE INDEX "idx_admin_actions_user_id" ON "admin_actions" ("user_id");
+-- reverse: copy the rows of table "admin_actions" into "audit_events"
+INSERT INTO "admin_actions" ("id", "user_id", "admin_id", "action", "reason", "created") SELECT "e"."id", "u"."id", "e"."actor_id", "e"."action", "e"."reason", "e"."created" FROM "audit_events" "e" JOIN "users" "u" ON "u"."id"::text = "e"."target_id" WHERE "e"."target_type" = 'users' AND "e"."reason" IS NOT NULL;
+-- reverse: create index "idx_audit_events_target" to table: "audit_events"
+DROP INDEX "idx_audit_events_target";
+-- reverse: create index "idx_audit_events_created" to table: "audit_events"
+DROP INDEX "idx_audit_events_created";
+-- reverse: create index "idx_audit_events_actor_id" to table: "audit_events"
+DROP INDEX "idx_audit_events_actor_id";
+-- reverse: create "audit_events" table
+DROP TABLE "audit_events";
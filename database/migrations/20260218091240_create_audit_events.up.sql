@@ -0,0 +1,25 @@
+-- create "audit_events" table
+CREATE TABLE "audit_events" (
+  "id" uuid NOT NULL DEFAULT gen_random_uuid(),
+  "actor_id" uuid NULL,
+  "action" character varying(50) NOT NULL,
+  "target_type" character varying(50) NOT NULL,
+  "target_id" text NULL,
+  "ip_address" character varying(64) NULL,
+  "user_agent" text NULL,
+  "changes" jsonb NULL,
+  "reason" text NULL,
+  "created" timestamptz NOT NULL DEFAULT now(),
+  PRIMARY KEY ("id"),
+  CONSTRAINT "fk_audit_events_actor_id" FOREIGN KEY ("actor_id") REFERENCES "users" ("id") ON UPDATE NO ACTION ON DELETE SET NULL
+);
+-- create index "idx_audit_events_actor_id" to table: "audit_events"
+CREATE INDEX "idx_audit_events_actor_id" ON "audit_events" ("actor_id");
+-- create index "idx_audit_events_created" to table: "audit_events"
+CREATE INDEX "idx_audit_events_created" ON "audit_events" ("created");
+-- create index "idx_audit_events_target" to table: "audit_events"
+CREATE INDEX "idx_audit_events_target" ON "audit_events" ("target_type", "target_id");
+-- copy the rows of table "admin_actions" into "audit_events"
+INSERT INTO "audit_events" ("id", "actor_id", "action", "target_type", "target_id", "reason", "created") SELECT "id", "admin_id", "action", 'users', "user_id"::text, "reason", "created" FROM "admin_actions";
+-- drop "admin_actions" table
+DROP TABLE "admin_actions";
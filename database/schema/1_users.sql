@@ -1,12 +1,20 @@
+-- User roles, from least to most privileged.
+CREATE TYPE user_role AS ENUM ('student', 'teacher', 'admin');
+
+-- User account states: pending until the email address is verified; suspended accounts cannot log in.
+CREATE TYPE user_status AS ENUM ('pending', 'active', 'suspended');
+
 -- Users table: Stores user authentication and profile information.
+-- `pending_email` holds a new address of an active user until its verification link is followed.
 CREATE TABLE users (
     id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
     username VARCHAR(255) UNIQUE,
     email VARCHAR(255) UNIQUE,
+    pending_email VARCHAR(255),
     display_name VARCHAR(255),
     password_hash TEXT NOT NULL,
-    role TEXT NOT NULL,
-    status TEXT NOT NULL DEFAULT 'Pending',
+    role user_role NOT NULL DEFAULT 'student',
+    status user_status NOT NULL DEFAULT 'pending',
     created TIMESTAMPTZ NOT NULL DEFAULT NOW(),
     updated TIMESTAMPTZ NOT NULL DEFAULT NOW()
 );
@@ -29,3 +37,171 @@ CREATE INDEX idx_notes_user_id ON notes(user_id);
 CREATE INDEX idx_notes_created ON notes(created);
 
 
+
+
+
+-- Sessions table: One row per login (device). Access and refresh tokens carry the session id,
+-- so revoking a session logs that device out. `impersonated_by` is set on sessions an admin
+-- opened to act as the user.
+CREATE TABLE sessions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL,
+    user_agent TEXT,
+    ip_address VARCHAR(64),
+    last_used TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    revoked_at TIMESTAMPTZ,
+    mfa_verified_at TIMESTAMPTZ,
+    impersonated_by UUID,
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT fk_sessions_user_id FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    CONSTRAINT fk_sessions_impersonated_by FOREIGN KEY (impersonated_by) REFERENCES users(id) ON DELETE CASCADE
+);
+CREATE INDEX idx_sessions_user_id ON sessions(user_id);
+
+
+
+-- Refresh tokens table: Server-side record of issued refresh tokens.
+-- Tokens are rotated on every refresh; all tokens issued for a session form one token family.
+-- Presenting an already-used token revokes the whole session.
+CREATE TABLE refresh_tokens (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL,
+    session_id UUID NOT NULL,
+    jti_hash TEXT NOT NULL UNIQUE,
+    user_agent TEXT,
+    ip_address VARCHAR(64),
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ,
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT fk_refresh_tokens_user_id FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    CONSTRAINT fk_refresh_tokens_session_id FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+);
+CREATE INDEX idx_refresh_tokens_user_id ON refresh_tokens(user_id);
+CREATE INDEX idx_refresh_tokens_session_id ON refresh_tokens(session_id);
+
+
+
+-- User tokens table: Short-lived, single-use tokens emailed to users (e.g. password reset links).
+-- Only the SHA-256 hash of the token is stored.
+CREATE TABLE user_tokens (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL,
+    purpose TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ,
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT fk_user_tokens_user_id FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+CREATE INDEX idx_user_tokens_user_id ON user_tokens(user_id);
+
+
+
+-- Login attempts table: Failed login counters used to lock out brute-force attempts.
+-- Keys are "account:<user id>" or "ip:<address>".
+CREATE TABLE login_attempts (
+    key TEXT PRIMARY KEY,
+    failures INTEGER NOT NULL DEFAULT 0,
+    last_failure TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    locked_until TIMESTAMPTZ
+);
+CREATE INDEX idx_login_attempts_locked_until ON login_attempts(locked_until);
+
+
+
+-- User TOTP table: Authenticator app secret of a user. The secret is only used for login
+-- once enrollment has been confirmed with a valid code.
+CREATE TABLE user_totp (
+    user_id UUID PRIMARY KEY,
+    secret TEXT NOT NULL,
+    confirmed_at TIMESTAMPTZ,
+    last_used_step BIGINT,
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT fk_user_totp_user_id FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+-- Recovery codes table: One-time codes for logging in without the authenticator app.
+-- Only the SHA-256 hash of each code is stored.
+CREATE TABLE recovery_codes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL,
+    code_hash TEXT NOT NULL UNIQUE,
+    used_at TIMESTAMPTZ,
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT fk_recovery_codes_user_id FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+CREATE INDEX idx_recovery_codes_user_id ON recovery_codes(user_id);
+
+-- Role MFA policies table: Roles whose members must use two-factor authentication.
+CREATE TABLE role_mfa_policies (
+    role user_role PRIMARY KEY,
+    required BOOLEAN NOT NULL DEFAULT false,
+    updated TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Identities table: Accounts at external OpenID Connect providers linked to users.
+-- `subject` is the provider's stable user id (the `sub` claim of its ID tokens).
+CREATE TABLE identities (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL,
+    provider TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    email VARCHAR(255),
+    last_login TIMESTAMPTZ,
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT uq_identities_provider_subject UNIQUE (provider, subject),
+    CONSTRAINT fk_identities_user_id FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+CREATE INDEX idx_identities_user_id ON identities(user_id);
+
+-- OIDC login states table: Authorization requests sent to a provider and not yet completed.
+-- Holds the PKCE code verifier and nonce; only the SHA-256 hash of the state is stored.
+CREATE TABLE oidc_login_states (
+    state_hash TEXT PRIMARY KEY,
+    provider TEXT NOT NULL,
+    code_verifier TEXT NOT NULL,
+    nonce TEXT NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- API keys table: Personal keys that let scripts and integrations act as their owner.
+-- Only the SHA-256 hash of a key is stored; `prefix` keeps its first characters so owners can tell keys apart.
+-- `scopes` is a space-separated list such as "read:lessons write:words".
+CREATE TABLE api_keys (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL,
+    name VARCHAR(100) NOT NULL,
+    prefix VARCHAR(16) NOT NULL,
+    key_hash TEXT NOT NULL UNIQUE,
+    scopes TEXT NOT NULL,
+    last_used TIMESTAMPTZ,
+    expires_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ,
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT fk_api_keys_user_id FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+CREATE INDEX idx_api_keys_user_id ON api_keys(user_id);
+
+-- Audit events table: Record of security-relevant and admin actions (logins, account changes,
+-- admin edits). `target_type` names the table acted on and `changes` holds the changed fields as
+-- {"field": {"before": ..., "after": ...}}, with secrets redacted. `reason` is given for admin actions.
+-- `actor_id` is cleared if the actor's account is deleted.
+CREATE TABLE audit_events (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    actor_id UUID,
+    action VARCHAR(50) NOT NULL,
+    target_type VARCHAR(50) NOT NULL,
+    target_id TEXT,
+    ip_address VARCHAR(64),
+    user_agent TEXT,
+    changes JSONB,
+    reason TEXT,
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT fk_audit_events_actor_id FOREIGN KEY (actor_id) REFERENCES users(id) ON DELETE SET NULL
+);
+CREATE INDEX idx_audit_events_actor_id ON audit_events(actor_id);
+CREATE INDEX idx_audit_events_created ON audit_events(created);
+CREATE INDEX idx_audit_events_target ON audit_events(target_type, target_id);
@@ -0,0 +1,46 @@
+//! Minimal OpenID Connect provider for exercising social login offline.
+//!
+//! Start it with `cargo run --example mock_oidc`, then run the backend with:
+//!
+//! ```sh
+//! APP__OIDC__PROVIDERS__MOCK__ISSUER=http://127.0.0.1:4010
+//! APP__OIDC__PROVIDERS__MOCK__CLIENT_ID=mock-client
+//! APP__OIDC__PROVIDERS__MOCK__CLIENT_SECRET=mock-secret
+//! APP__OIDC__PROVIDERS__MOCK__REDIRECT_URI=http://localhost:5173/oidc/callback
+//! ```
+//!
+//! Open the `authorization_url` from `GET /auth/oidc/mock/authorize` (for example with
+//! `curl -i`), take `code` and `state` from the `Location` header and post them to
+//! `POST /auth/oidc/mock/callback`.
+//!
+//! Environment: `MOCK_OIDC_PORT` (4010), `MOCK_OIDC_CLIENT_ID` (mock-client),
+//! `MOCK_OIDC_CLIENT_SECRET` (mock-secret), `MOCK_OIDC_SUBJECT` (mock-user-1),
+//! `MOCK_OIDC_EMAIL` (student@example.com), `MOCK_OIDC_EMAIL_VERIFIED` (true),
+//! `MOCK_OIDC_NAME` (Mock Student).
+
+mod provider;
+
+use provider::ProviderConfig;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let port: u16 = env_or("MOCK_OIDC_PORT", "4010").parse()?;
+    let config = ProviderConfig {
+        client_id: env_or("MOCK_OIDC_CLIENT_ID", "mock-client"),
+        client_secret: env_or("MOCK_OIDC_CLIENT_SECRET", "mock-secret"),
+        subject: env_or("MOCK_OIDC_SUBJECT", "mock-user-1"),
+        email: env_or("MOCK_OIDC_EMAIL", "student@example.com"),
+        email_verified: env_or("MOCK_OIDC_EMAIL_VERIFIED", "true").parse()?,
+        name: env_or("MOCK_OIDC_NAME", "Mock Student"),
+    };
+
+    let (issuer, server) = provider::start(port, config).await?;
+    println!("Mock OIDC provider listening on {}", issuer);
+    server.await??;
+
+    Ok(())
+}
+
+fn env_or(name: &str, default: &str) -> String {
+    std::env::var(name).unwrap_or_else(|_| default.to_string())
+}
@@ -0,0 +1,212 @@
+//! Mock OpenID Connect provider, shared by the `mock_oidc` example and the OIDC tests.
+//!
+//! Every authorization request is approved immediately for one configured user, and
+//! ID tokens are signed with the client secret (HS256).
+
+use axum::extract::{Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Form, Json, Router};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use jsonwebtoken::{encode, EncodingKey, Header};
+use rand::distr::{Alphanumeric, SampleString};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::task::JoinHandle;
+
+const ID_TOKEN_LIFETIME_SECONDS: i64 = 300;
+
+/// Client the provider accepts and the user it signs everyone in as
+#[derive(Debug, Clone)]
+pub struct ProviderConfig {
+    pub client_id: String,
+    pub client_secret: String,
+    pub subject: String,
+    pub email: String,
+    pub email_verified: bool,
+    pub name: String,
+}
+
+struct Provider {
+    issuer: String,
+    config: ProviderConfig,
+    codes: Mutex<HashMap<String, PendingCode>>,
+}
+
+// Authorization request waiting to be exchanged at the token endpoint
+struct PendingCode {
+    redirect_uri: String,
+    nonce: Option<String>,
+    code_challenge: String,
+}
+
+#[derive(Deserialize)]
+struct AuthorizeParams {
+    response_type: String,
+    client_id: String,
+    redirect_uri: String,
+    state: Option<String>,
+    nonce: Option<String>,
+    code_challenge: Option<String>,
+    code_challenge_method: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TokenParams {
+    grant_type: String,
+    code: String,
+    redirect_uri: String,
+    client_id: String,
+    client_secret: String,
+    code_verifier: String,
+}
+
+#[derive(Serialize)]
+struct IdTokenClaims<'a> {
+    iss: &'a str,
+    sub: &'a str,
+    aud: &'a str,
+    exp: i64,
+    iat: i64,
+    email: &'a str,
+    email_verified: bool,
+    name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nonce: Option<String>,
+}
+
+/// Start the provider on a local port (0 picks a free one).
+/// Returns its issuer URL and the server task.
+pub async fn start(
+    port: u16,
+    config: ProviderConfig,
+) -> anyhow::Result<(String, JoinHandle<std::io::Result<()>>)> {
+    let listener = tokio::net::TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], port))).await?;
+    let issuer = format!("http://{}", listener.local_addr()?);
+
+    let provider = Arc::new(Provider {
+        issuer: issuer.clone(),
+        config,
+        codes: Mutex::new(HashMap::new()),
+    });
+
+    let app = Router::new()
+        .route("/.well-known/openid-configuration", get(discovery))
+        .route("/authorize", get(authorize))
+        .route("/token", post(token))
+        .route("/jwks", get(jwks))
+        .with_state(provider);
+
+    let server = tokio::spawn(async move { axum::serve(listener, app).await });
+
+    Ok((issuer, server))
+}
+
+async fn discovery(State(provider): State<Arc<Provider>>) -> impl IntoResponse {
+    Json(json!({
+        "issuer": provider.issuer,
+        "authorization_endpoint": format!("{}/authorize", provider.issuer),
+        "token_endpoint": format!("{}/token", provider.issuer),
+        "jwks_uri": format!("{}/jwks", provider.issuer),
+        "response_types_supported": ["code"],
+        "subject_types_supported": ["public"],
+        "id_token_signing_alg_values_supported": ["HS256"],
+        "code_challenge_methods_supported": ["S256"],
+    }))
+}
+
+/// Approve the request straight away and redirect back with a code
+async fn authorize(
+    State(provider): State<Arc<Provider>>,
+    Query(params): Query<AuthorizeParams>,
+) -> Response {
+    if params.response_type != "code" || params.client_id != provider.config.client_id {
+        return error(StatusCode::BAD_REQUEST, "unauthorized_client");
+    }
+    let (Some(code_challenge), Some("S256")) = (
+        params.code_challenge,
+        params.code_challenge_method.as_deref(),
+    ) else {
+        return error(StatusCode::BAD_REQUEST, "invalid_request");
+    };
+
+    let code = Alphanumeric.sample_string(&mut rand::rng(), 32);
+    provider.codes.lock().unwrap().insert(
+        code.clone(),
+        PendingCode {
+            redirect_uri: params.redirect_uri.clone(),
+            nonce: params.nonce,
+            code_challenge,
+        },
+    );
+
+    let mut location = format!("{}?code={}", params.redirect_uri, code);
+    if let Some(state) = params.state {
+        location.push_str(&format!("&state={}", state));
+    }
+
+    (StatusCode::FOUND, [(header::LOCATION, location)]).into_response()
+}
+
+/// Exchange a code for an ID token after checking the client and the PKCE verifier
+async fn token(State(provider): State<Arc<Provider>>, Form(params): Form<TokenParams>) -> Response {
+    let config = &provider.config;
+    if params.grant_type != "authorization_code" {
+        return error(StatusCode::BAD_REQUEST, "unsupported_grant_type");
+    }
+    if params.client_id != config.client_id || params.client_secret != config.client_secret {
+        return error(StatusCode::UNAUTHORIZED, "invalid_client");
+    }
+
+    let Some(pending) = provider.codes.lock().unwrap().remove(&params.code) else {
+        return error(StatusCode::BAD_REQUEST, "invalid_grant");
+    };
+    let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(params.code_verifier.as_bytes()));
+    if pending.redirect_uri != params.redirect_uri || pending.code_challenge != challenge {
+        return error(StatusCode::BAD_REQUEST, "invalid_grant");
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let claims = IdTokenClaims {
+        iss: &provider.issuer,
+        sub: &config.subject,
+        aud: &config.client_id,
+        exp: now + ID_TOKEN_LIFETIME_SECONDS,
+        iat: now,
+        email: &config.email,
+        email_verified: config.email_verified,
+        name: &config.name,
+        nonce: pending.nonce,
+    };
+    let id_token = match encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(config.client_secret.as_bytes()),
+    ) {
+        Ok(id_token) => id_token,
+        Err(_) => return error(StatusCode::INTERNAL_SERVER_ERROR, "server_error"),
+    };
+
+    Json(json!({
+        "access_token": Alphanumeric.sample_string(&mut rand::rng(), 32),
+        "token_type": "Bearer",
+        "expires_in": ID_TOKEN_LIFETIME_SECONDS,
+        "id_token": id_token,
+    }))
+    .into_response()
+}
+
+/// No public keys: ID tokens are signed with the client secret
+async fn jwks() -> impl IntoResponse {
+    Json(json!({ "keys": [] }))
+}
+
+fn error(status: StatusCode, code: &str) -> Response {
+    (status, Json(json!({ "error": code }))).into_response()
+}
@@ -1,8 +1,15 @@
 use crate::AppState;
-use axum::Router;
+use axum::{middleware, Router};
 use std::sync::Arc;
 
+use crate::domain::audit::admin_router as audit_admin_router;
+use crate::domain::mfa::admin_router as mfa_admin_router;
+use crate::domain::user::admin_router as user_admin_router;
+use crate::domain::user::hooks::UserHooks;
+use crate::domain::user::model::Role;
 use crate::entities::{lessons, notes, sentences, users, word_sentences, words};
+use crate::middleware::{rate_limit, require_mfa, require_role};
+use crate::utils::rate_limit::RateLimitScope;
 use crud_macros::make_crud_routes;
 
 // Combine all admin routes
@@ -11,51 +18,77 @@ pub fn router() -> Router<Arc<AppState>> {
         entity: users::Entity,
         model: users::Model,
         active_model: users::ActiveModel,
-        path: "/users"
+        path: "/users",
+        // Roles and statuses change through the admin actions, which ask for a reason
+        read_only: [id, pending_email, role, status, created, updated],
+        write_only: [password_hash],
+        hooks: UserHooks
     );
 
     let lesson_routes = make_crud_routes!(
         entity: lessons::Entity,
         model: lessons::Model,
         active_model: lessons::ActiveModel,
-        path: "/lessons"
+        path: "/lessons",
+        read_only: [id, created, updated]
     );
 
     let note_routes = make_crud_routes!(
         entity: notes::Entity,
         model: notes::Model,
         active_model: notes::ActiveModel,
-        path: "/notes"
+        path: "/notes",
+        read_only: [id, created, updated]
     );
 
     let sentence_routes = make_crud_routes!(
         entity: sentences::Entity,
         model: sentences::Model,
         active_model: sentences::ActiveModel,
-        path: "/sentences"
+        path: "/sentences",
+        read_only: [id, created, updated]
     );
 
     let word_routes = make_crud_routes!(
         entity: words::Entity,
         model: words::Model,
         active_model: words::ActiveModel,
-        path: "/words"
+        path: "/words",
+        read_only: [id, created, updated]
     );
 
     let word_sentence_routes = make_crud_routes!(
         entity: word_sentences::Entity,
         model: word_sentences::Model,
         active_model: word_sentences::ActiveModel,
-        path: "/word_sentences"
+        path: "/word_sentences",
+        read_only: [id, created]
     );
 
-    Router::new().nest(
-        "/admin",
-        user_routes
-            .merge(lesson_routes)
-            .merge(note_routes)
-            .merge(sentence_routes)
-            .merge(word_routes)
-            .merge(word_sentence_routes),
-    )
+    // User management and learning content have separate rate limit budgets
+    let user_management_routes = user_routes
+        .merge(user_admin_router())
+        .merge(mfa_admin_router())
+        .merge(audit_admin_router())
+        .route_layer(middleware::from_fn_with_state(
+            RateLimitScope::Admin,
+            rate_limit,
+        ));
+
+    let learning_routes = lesson_routes
+        .merge(note_routes)
+        .merge(sentence_routes)
+        .merge(word_routes)
+        .merge(word_sentence_routes)
+        .route_layer(middleware::from_fn_with_state(
+            RateLimitScope::Learning,
+            rate_limit,
+        ));
+
+    // Admin routes are restricted to admins (authentication is applied by the caller)
+    // who completed two-factor authentication when their role requires it
+    Router::new()
+        .nest("/admin", user_management_routes.merge(learning_routes))
+        .route_layer(middleware::from_fn(require_mfa))
+        .route_layer(middleware::from_fn_with_state(Role::Admin, require_role))
 }
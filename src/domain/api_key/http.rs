@@ -0,0 +1,74 @@
+use axum::{
+    extract::{Path, State},
+    middleware,
+    response::IntoResponse,
+    routing::{delete, get},
+    Extension, Json, Router,
+};
+use std::sync::Arc;
+
+use super::model::CreateApiKeyRequest;
+use crate::domain::error::ToResponse;
+use crate::entities::users::Model as User;
+use crate::middleware::{forbid_impersonation, rate_limit, require_mfa};
+use crate::state::AppState;
+use crate::utils::client_info::ClientInfo;
+use crate::utils::rate_limit::RateLimitScope;
+
+/// List the API keys of the current user
+pub async fn list_api_keys(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+) -> impl IntoResponse {
+    state
+        .api_key_service
+        .list(user.id)
+        .await
+        .to_response("API keys retrieved successfully")
+}
+
+/// Create an API key for the current user
+pub async fn create_api_key(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    client: ClientInfo,
+    Json(req): Json<CreateApiKeyRequest>,
+) -> impl IntoResponse {
+    state
+        .api_key_service
+        .create(user.id, req, &client)
+        .await
+        .to_response_created(
+            "API key created successfully; store it now, it will not be shown again",
+        )
+}
+
+/// Revoke one API key of the current user
+pub async fn revoke_api_key(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Path(id): Path<uuid::Uuid>,
+    client: ClientInfo,
+) -> impl IntoResponse {
+    state
+        .api_key_service
+        .revoke(user.id, id, &client)
+        .await
+        .to_response_no_content("API key revoked successfully")
+}
+
+/// API key Router (routes requiring authentication, nested under `/auth/api-keys` by the caller)
+///
+/// Keys can only be managed from sessions that completed two-factor authentication when
+/// the role requires it, and never with an API key.
+pub fn protected_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(list_api_keys).post(create_api_key))
+        .route("/{id}", delete(revoke_api_key))
+        .route_layer(middleware::from_fn(require_mfa))
+        .route_layer(middleware::from_fn(forbid_impersonation))
+        .route_layer(middleware::from_fn_with_state(
+            RateLimitScope::Auth,
+            rate_limit,
+        ))
+}
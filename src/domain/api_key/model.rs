@@ -0,0 +1,107 @@
+use axum::http::Method;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::entities::api_keys::Model as ApiKeyModel;
+use crate::entities::users::Model as UserModel;
+
+/// Header carrying an API key, kept apart from `Authorization` so keys are never mistaken for tokens
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Learning content API keys can be scoped to, by its path under `/admin`.
+/// Each resource has a `read:<resource>` and a `write:<resource>` scope.
+pub const API_KEY_RESOURCES: [&str; 5] =
+    ["lessons", "notes", "sentences", "words", "word_sentences"];
+
+/// Whether a scope names a known resource, e.g. `read:lessons` or `write:words`
+pub fn is_valid_scope(scope: &str) -> bool {
+    match scope.split_once(':') {
+        Some(("read" | "write", resource)) => API_KEY_RESOURCES.contains(&resource),
+        _ => false,
+    }
+}
+
+/// Scope an API key needs for a request: `read:<resource>` for safe methods and
+/// `write:<resource>` otherwise. None for endpoints that cannot be used with API keys.
+pub fn required_scope(method: &Method, path: &str) -> Option<String> {
+    let resource = path.strip_prefix("/admin/")?.split('/').next()?;
+    if !API_KEY_RESOURCES.contains(&resource) {
+        return None;
+    }
+
+    let access = if method.is_safe() { "read" } else { "write" };
+    Some(format!("{}:{}", access, resource))
+}
+
+/// API key of the authenticated request, added to request extensions by `auth_middleware`
+#[derive(Debug, Clone)]
+pub struct ApiKeyAuth {
+    pub key_id: uuid::Uuid,
+    pub scopes: Vec<String>,
+}
+
+/// User and key authenticated from an API key
+#[derive(Debug, Clone)]
+pub struct ApiKeyIdentity {
+    pub user: UserModel,
+    pub key: ApiKeyAuth,
+    pub mfa_pending: bool,
+}
+
+// ============= API Key Request DTOs =============
+
+/// Request body for creating an API key
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateApiKeyRequest {
+    #[validate(length(min = 1, max = 100, message = "Name must be 1 to 100 characters"))]
+    pub name: String,
+
+    /// Scopes such as `read:lessons` or `write:words`
+    #[validate(length(min = 1, message = "At least one scope is required"))]
+    pub scopes: Vec<String>,
+
+    /// Days until the key expires; keys without one never expire
+    #[validate(range(min = 1, max = 3650, message = "Expiry must be 1 to 3650 days"))]
+    pub expires_in_days: Option<i64>,
+}
+
+// ============= API Key Response DTOs =============
+
+/// API key information returned by the key listing; never includes the key itself
+#[derive(Debug, Serialize)]
+pub struct ApiKeyInfo {
+    pub id: String,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Vec<String>,
+    pub last_used: Option<chrono::DateTime<chrono::FixedOffset>>,
+    pub expires_at: Option<chrono::DateTime<chrono::FixedOffset>>,
+    pub created: chrono::DateTime<chrono::FixedOffset>,
+}
+
+impl From<ApiKeyModel> for ApiKeyInfo {
+    fn from(api_key: ApiKeyModel) -> Self {
+        Self {
+            id: api_key.id.to_string(),
+            scopes: parse_scopes(&api_key.scopes),
+            name: api_key.name,
+            prefix: api_key.prefix,
+            last_used: api_key.last_used,
+            expires_at: api_key.expires_at,
+            created: api_key.created,
+        }
+    }
+}
+
+/// Newly created API key, the only time the key is shown
+#[derive(Debug, Serialize)]
+pub struct CreatedApiKeyResponse {
+    pub key: String,
+    #[serde(flatten)]
+    pub api_key: ApiKeyInfo,
+}
+
+/// Split the space-separated `api_keys.scopes` column
+pub fn parse_scopes(scopes: &str) -> Vec<String> {
+    scopes.split_whitespace().map(str::to_string).collect()
+}
@@ -0,0 +1,270 @@
+// Import Domain
+use crate::entities::api_keys::{self, Entity as ApiKeys};
+use crate::entities::users::Entity as Users;
+use sea_orm::sea_query::Expr;
+use sea_orm::*;
+
+// Import Dtos
+use super::model::{
+    is_valid_scope, parse_scopes, ApiKeyAuth, ApiKeyIdentity, ApiKeyInfo, CreateApiKeyRequest,
+    CreatedApiKeyResponse,
+};
+
+// Import Utils
+use crate::domain::audit::model::{AuditAction, AuditEvent};
+use crate::domain::audit::service::AuditService;
+use crate::domain::error::AppError;
+use crate::domain::mfa::service::MfaService;
+use crate::domain::user::service::ensure_active;
+use crate::utils::client_info::ClientInfo;
+use crate::utils::token::{generate_token, hash_token};
+use validator::Validate;
+
+// Keys start with this marker so they are easy to spot, e.g. by secret scanners
+const API_KEY_MARKER: &str = "chl_";
+
+// Number of random characters after the marker
+const API_KEY_LENGTH: usize = 40;
+
+// Characters of the key kept in clear so owners can tell their keys apart
+const API_KEY_PREFIX_LENGTH: usize = 12;
+
+// Active keys a user can hold at once
+const MAX_API_KEYS_PER_USER: u64 = 20;
+
+// How often the last-used time of a key is refreshed by requests
+const LAST_USED_TOUCH_INTERVAL_MINUTES: i64 = 5;
+
+/// ApiKeyService manages personal API keys and authenticates requests made with them
+#[derive(Clone)]
+pub struct ApiKeyService {
+    db: DatabaseConnection,
+    mfa_service: MfaService,
+    audit_service: AuditService,
+}
+
+impl ApiKeyService {
+    /// Create a new ApiKeyService instance
+    pub fn new(
+        db: DatabaseConnection,
+        mfa_service: MfaService,
+        audit_service: AuditService,
+    ) -> Self {
+        Self {
+            db,
+            mfa_service,
+            audit_service,
+        }
+    }
+
+    /// List the active API keys of a user
+    pub async fn list(&self, user_id: uuid::Uuid) -> Result<Vec<ApiKeyInfo>, AppError> {
+        let api_keys = ApiKeys::find()
+            .filter(api_keys::Column::UserId.eq(user_id))
+            .filter(api_keys::Column::RevokedAt.is_null())
+            .order_by_desc(api_keys::Column::Created)
+            .all(&self.db)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Ok(api_keys.into_iter().map(ApiKeyInfo::from).collect())
+    }
+
+    /// Create an API key for a user. The key is returned only this once.
+    pub async fn create(
+        &self,
+        user_id: uuid::Uuid,
+        create_req: CreateApiKeyRequest,
+        client: &ClientInfo,
+    ) -> Result<CreatedApiKeyResponse, AppError> {
+        // Validate input
+        create_req.validate().map_err(AppError::from)?;
+
+        if let Some(scope) = create_req
+            .scopes
+            .iter()
+            .find(|scope| !is_valid_scope(scope))
+        {
+            return Err(AppError::Validation(format!("Unknown scope '{}'", scope)));
+        }
+
+        let mut scopes = create_req.scopes;
+        scopes.sort();
+        scopes.dedup();
+
+        let active_keys = ApiKeys::find()
+            .filter(api_keys::Column::UserId.eq(user_id))
+            .filter(api_keys::Column::RevokedAt.is_null())
+            .count(&self.db)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        if active_keys >= MAX_API_KEYS_PER_USER {
+            return Err(AppError::Conflict(format!(
+                "A user can have at most {} API keys; revoke one first",
+                MAX_API_KEYS_PER_USER
+            )));
+        }
+
+        let key = format!("{}{}", API_KEY_MARKER, generate_token(API_KEY_LENGTH));
+        let now = now();
+
+        let txn = self
+            .db
+            .begin()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let api_key = api_keys::ActiveModel {
+            id: Set(uuid::Uuid::now_v7()),
+            user_id: Set(user_id),
+            name: Set(create_req.name),
+            prefix: Set(key[..API_KEY_PREFIX_LENGTH].to_string()),
+            key_hash: Set(hash_token(&key)),
+            scopes: Set(scopes.join(" ")),
+            last_used: Set(None),
+            expires_at: Set(create_req
+                .expires_in_days
+                .map(|days| now + chrono::Duration::days(days))),
+            revoked_at: Set(None),
+            created: Set(now),
+        }
+        .insert(&txn)
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let api_key = ApiKeyInfo::from(api_key);
+        self.audit_service
+            .record(
+                &txn,
+                AuditEvent::new(
+                    AuditAction::ApiKeyCreate,
+                    "api_keys",
+                    Some(api_key.id.clone()),
+                )
+                .actor(user_id)
+                .client(client)
+                .changes(None, Some(&serde_json::json!(api_key))),
+            )
+            .await?;
+
+        txn.commit()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Ok(CreatedApiKeyResponse { key, api_key })
+    }
+
+    /// Revoke one API key of a user
+    pub async fn revoke(
+        &self,
+        user_id: uuid::Uuid,
+        key_id: uuid::Uuid,
+        client: &ClientInfo,
+    ) -> Result<(), AppError> {
+        let txn = self
+            .db
+            .begin()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let result = ApiKeys::update_many()
+            .col_expr(api_keys::Column::RevokedAt, Expr::value(now()))
+            .filter(api_keys::Column::Id.eq(key_id))
+            .filter(api_keys::Column::UserId.eq(user_id))
+            .filter(api_keys::Column::RevokedAt.is_null())
+            .exec(&txn)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        if result.rows_affected == 0 {
+            return Err(AppError::NotFound(format!(
+                "API key with id {} not found",
+                key_id
+            )));
+        }
+
+        self.audit_service
+            .record(
+                &txn,
+                AuditEvent::new(
+                    AuditAction::ApiKeyRevoke,
+                    "api_keys",
+                    Some(key_id.to_string()),
+                )
+                .actor(user_id)
+                .client(client),
+            )
+            .await?;
+
+        txn.commit()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Authenticate a request made with an API key
+    pub async fn authenticate(&self, key: &str) -> Result<ApiKeyIdentity, AppError> {
+        let invalid_key = || AppError::Unauthorized("Invalid API key".to_string());
+
+        if !key.starts_with(API_KEY_MARKER) {
+            return Err(invalid_key());
+        }
+
+        let (api_key, user_model) = ApiKeys::find()
+            .filter(api_keys::Column::KeyHash.eq(hash_token(key)))
+            .find_also_related(Users)
+            .one(&self.db)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .ok_or_else(invalid_key)?;
+        let user_model = user_model.ok_or_else(invalid_key)?;
+
+        let now = now();
+        if api_key.revoked_at.is_some() {
+            return Err(AppError::Unauthorized(
+                "API key has been revoked".to_string(),
+            ));
+        }
+        if api_key
+            .expires_at
+            .is_some_and(|expires_at| expires_at <= now)
+        {
+            return Err(AppError::Unauthorized("API key has expired".to_string()));
+        }
+
+        ensure_active(&user_model)?;
+
+        // Keys have no session to complete a second factor on, so their owner must have
+        // set one up whenever their role requires it
+        let mfa_pending = self.mfa_service.is_required(user_model.role).await?
+            && !self.mfa_service.is_enrolled(user_model.id).await?;
+
+        // Refresh the last-used time, at most once per interval to avoid a write per request
+        if api_key.last_used.is_none_or(|last_used| {
+            now - last_used > chrono::Duration::minutes(LAST_USED_TOUCH_INTERVAL_MINUTES)
+        }) {
+            ApiKeys::update_many()
+                .col_expr(api_keys::Column::LastUsed, Expr::value(now))
+                .filter(api_keys::Column::Id.eq(api_key.id))
+                .exec(&self.db)
+                .await
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+        }
+
+        Ok(ApiKeyIdentity {
+            user: user_model,
+            key: ApiKeyAuth {
+                key_id: api_key.id,
+                scopes: parse_scopes(&api_key.scopes),
+            },
+            mfa_pending,
+        })
+    }
+}
+
+// Current time in the timezone used by the entity timestamps
+fn now() -> chrono::DateTime<chrono::FixedOffset> {
+    chrono::Utc::now().with_timezone(&chrono::FixedOffset::east_opt(0).unwrap())
+}
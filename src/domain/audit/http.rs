@@ -0,0 +1,30 @@
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+use std::sync::Arc;
+
+use super::model::AuditQuery;
+use crate::domain::error::ToResponse;
+use crate::state::AppState;
+
+/// List audit events, filtered by actor, action, target and time range (admin only)
+pub async fn list_audit_events(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<AuditQuery>,
+) -> impl IntoResponse {
+    let result = state.audit_service.list(query).await;
+    let pagination = result.as_ref().ok().map(|(_, pagination)| pagination.clone());
+
+    result
+        .map(|(events, _)| events)
+        .to_response("Audit events retrieved successfully")
+        .with_pagination(pagination)
+}
+
+/// Audit Router (admin routes, nested under `/admin` by the caller)
+pub fn admin_router() -> Router<Arc<AppState>> {
+    Router::new().route("/audit", get(list_audit_events))
+}
@@ -0,0 +1,5 @@
+pub mod http;
+pub mod model;
+pub mod service;
+
+pub use http::admin_router;
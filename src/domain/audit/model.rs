@@ -0,0 +1,288 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use validator::Validate;
+
+use crate::entities::audit_events::Model as AuditEventModel;
+use crate::utils::client_info::ClientInfo;
+
+/// Fields whose values are never written to the audit log
+pub const REDACTED_FIELDS: [&str; 1] = ["password_hash"];
+
+// Placeholder recorded instead of a redacted value
+const REDACTED: &str = "[redacted]";
+
+// Bookkeeping fields that change with every write, left out of diffs
+const IGNORED_FIELDS: [&str; 1] = ["updated"];
+
+/// Action recorded in the audit log
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AuditAction {
+    // Authentication
+    Register,
+    Login,
+    LoginFailed,
+    Logout,
+    LogoutAll,
+
+    // Account changes made by the user
+    ProfileUpdate,
+    PasswordChange,
+    PasswordReset,
+    MfaEnable,
+    MfaDisable,
+    RecoveryCodesRegenerate,
+    ApiKeyCreate,
+    ApiKeyRevoke,
+
+    // Account actions taken by admins
+    Suspend,
+    Reactivate,
+    ForcePasswordReset,
+    ChangeRole,
+    Impersonate,
+    Unlock,
+    MfaPolicyChange,
+
+    // Admin CRUD edits
+    Create,
+    Update,
+    Delete,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::Register => "register",
+            AuditAction::Login => "login",
+            AuditAction::LoginFailed => "login_failed",
+            AuditAction::Logout => "logout",
+            AuditAction::LogoutAll => "logout_all",
+            AuditAction::ProfileUpdate => "profile_update",
+            AuditAction::PasswordChange => "password_change",
+            AuditAction::PasswordReset => "password_reset",
+            AuditAction::MfaEnable => "mfa_enable",
+            AuditAction::MfaDisable => "mfa_disable",
+            AuditAction::RecoveryCodesRegenerate => "recovery_codes_regenerate",
+            AuditAction::ApiKeyCreate => "api_key_create",
+            AuditAction::ApiKeyRevoke => "api_key_revoke",
+            AuditAction::Suspend => "suspend",
+            AuditAction::Reactivate => "reactivate",
+            AuditAction::ForcePasswordReset => "force_password_reset",
+            AuditAction::ChangeRole => "change_role",
+            AuditAction::Impersonate => "impersonate",
+            AuditAction::Unlock => "unlock",
+            AuditAction::MfaPolicyChange => "mfa_policy_change",
+            AuditAction::Create => "create",
+            AuditAction::Update => "update",
+            AuditAction::Delete => "delete",
+        }
+    }
+}
+
+/// Event to record in the audit log
+#[derive(Debug, Clone)]
+pub struct AuditEvent {
+    pub actor_id: Option<uuid::Uuid>,
+    pub action: AuditAction,
+    pub target_type: String, // Table acted on, e.g. "users"
+    pub target_id: Option<String>,
+    pub client: ClientInfo,
+    pub changes: Option<Value>,
+    pub reason: Option<String>,
+}
+
+impl AuditEvent {
+    pub fn new(action: AuditAction, target_type: &str, target_id: Option<String>) -> Self {
+        Self {
+            actor_id: None,
+            action,
+            target_type: target_type.to_string(),
+            target_id,
+            client: ClientInfo::default(),
+            changes: None,
+            reason: None,
+        }
+    }
+
+    /// Event about a user account
+    pub fn user(action: AuditAction, user_id: uuid::Uuid) -> Self {
+        Self::new(action, "users", Some(user_id.to_string()))
+    }
+
+    pub fn actor(mut self, actor_id: uuid::Uuid) -> Self {
+        self.actor_id = Some(actor_id);
+        self
+    }
+
+    pub fn client(mut self, client: &ClientInfo) -> Self {
+        self.client = client.clone();
+        self
+    }
+
+    /// Record the fields that differ between two JSON objects
+    pub fn changes(mut self, before: Option<&Value>, after: Option<&Value>) -> Self {
+        self.changes = diff(before, after);
+        self
+    }
+
+    pub fn reason(mut self, reason: &str) -> Self {
+        self.reason = Some(reason.to_string());
+        self
+    }
+}
+
+/// Changed fields of two JSON objects, as `{"field": {"before": ..., "after": ...}}`.
+/// Either side may be missing (creations and deletions). None when nothing changed.
+pub fn diff(before: Option<&Value>, after: Option<&Value>) -> Option<Value> {
+    let empty = Map::new();
+    let before = before.and_then(Value::as_object).unwrap_or(&empty);
+    let after = after.and_then(Value::as_object).unwrap_or(&empty);
+
+    let mut fields: Vec<&String> = before.keys().chain(after.keys()).collect();
+    fields.sort();
+    fields.dedup();
+
+    let changes: Map<String, Value> = fields
+        .into_iter()
+        .filter(|field| !IGNORED_FIELDS.contains(&field.as_str()))
+        .filter(|field| before.get(*field) != after.get(*field))
+        .map(|field| {
+            let redact = |value: Option<&Value>| match value {
+                Some(_) if REDACTED_FIELDS.contains(&field.as_str()) => {
+                    Value::String(REDACTED.to_string())
+                }
+                Some(value) => value.clone(),
+                None => Value::Null,
+            };
+            let mut change = Map::new();
+            change.insert("before".to_string(), redact(before.get(field)));
+            change.insert("after".to_string(), redact(after.get(field)));
+            (field.clone(), Value::Object(change))
+        })
+        .collect();
+
+    (!changes.is_empty()).then_some(Value::Object(changes))
+}
+
+// ============= Audit Request DTOs =============
+
+/// Query parameters for listing audit events; all filters are optional
+#[derive(Debug, Deserialize, Validate)]
+pub struct AuditQuery {
+    pub actor_id: Option<uuid::Uuid>,
+    pub action: Option<String>,
+    pub target_type: Option<String>,
+    pub target_id: Option<String>,
+    pub from: Option<chrono::DateTime<chrono::FixedOffset>>, // Inclusive
+    pub to: Option<chrono::DateTime<chrono::FixedOffset>>,   // Exclusive
+
+    #[validate(range(min = 1, message = "Page must be at least 1"))]
+    pub page: Option<u64>,
+
+    #[validate(range(min = 1, max = 100, message = "Per page must be 1 to 100"))]
+    pub per_page: Option<u64>,
+}
+
+// ============= Audit Response DTOs =============
+
+/// Audit event returned by the audit log listing
+#[derive(Debug, Serialize)]
+pub struct AuditEventInfo {
+    pub id: String,
+    pub actor_id: Option<String>,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: Option<String>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub changes: Option<Value>,
+    pub reason: Option<String>,
+    pub created: chrono::DateTime<chrono::FixedOffset>,
+}
+
+impl From<AuditEventModel> for AuditEventInfo {
+    fn from(event: AuditEventModel) -> Self {
+        Self {
+            id: event.id.to_string(),
+            actor_id: event.actor_id.map(|id| id.to_string()),
+            action: event.action,
+            target_type: event.target_type,
+            target_id: event.target_id,
+            ip_address: event.ip_address,
+            user_agent: event.user_agent,
+            changes: event.changes,
+            reason: event.reason,
+            created: event.created,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn records_only_changed_fields() {
+        let before = json!({ "email": "a@example.com", "role": "student", "updated": "1" });
+        let after = json!({ "email": "b@example.com", "role": "student", "updated": "2" });
+        assert_eq!(
+            diff(Some(&before), Some(&after)),
+            Some(json!({
+                "email": { "before": "a@example.com", "after": "b@example.com" }
+            }))
+        );
+    }
+
+    #[test]
+    fn records_creations_and_deletions() {
+        let row = json!({ "word": "cat" });
+        assert_eq!(
+            diff(None, Some(&row)),
+            Some(json!({ "word": { "before": null, "after": "cat" } }))
+        );
+        assert_eq!(
+            diff(Some(&row), None),
+            Some(json!({ "word": { "before": "cat", "after": null } }))
+        );
+    }
+
+    #[test]
+    fn is_none_when_nothing_changed() {
+        let row = json!({ "word": "cat", "updated": "1" });
+        let touched = json!({ "word": "cat", "updated": "2" });
+        assert_eq!(diff(Some(&row), Some(&touched)), None);
+        assert_eq!(diff(None, None), None);
+    }
+
+    #[test]
+    fn redacts_password_hashes() {
+        let before = json!({ "password_hash": "$argon2id$old" });
+        let after = json!({ "password_hash": "$argon2id$new" });
+        assert_eq!(
+            diff(Some(&before), Some(&after)),
+            Some(json!({
+                "password_hash": { "before": "[redacted]", "after": "[redacted]" }
+            }))
+        );
+
+        // A hash appearing or disappearing is recorded without its value
+        assert_eq!(
+            diff(None, Some(&after)),
+            Some(json!({ "password_hash": { "before": null, "after": "[redacted]" } }))
+        );
+        assert!(!diff(Some(&before), None)
+            .unwrap()
+            .to_string()
+            .contains("argon2id"));
+    }
+
+    #[test]
+    fn unchanged_password_hashes_are_left_out() {
+        let before = json!({ "password_hash": "$argon2id$same", "display_name": "Ann" });
+        let after = json!({ "password_hash": "$argon2id$same", "display_name": "Bob" });
+        let changes = diff(Some(&before), Some(&after)).unwrap();
+        assert!(changes.get("password_hash").is_none());
+        assert!(changes.get("display_name").is_some());
+    }
+}
@@ -0,0 +1,123 @@
+// Import Domain
+use crate::entities::audit_events::{self, Entity as AuditEvents};
+use sea_orm::*;
+
+// Import Dtos
+use super::model::{AuditEvent, AuditEventInfo, AuditQuery};
+
+// Import Utils
+use crate::domain::error::AppError;
+use crate::domain::response::Pagination;
+use validator::Validate;
+
+// Audit events listed per page unless the request asks for another page size
+const DEFAULT_PER_PAGE: u64 = 20;
+
+/// AuditService records security-relevant and admin actions and lists them for admins
+#[derive(Clone)]
+pub struct AuditService {
+    db: DatabaseConnection,
+}
+
+impl AuditService {
+    /// Create a new AuditService instance
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    /// Record an event, as part of the caller's transaction when given one
+    pub async fn record<C: ConnectionTrait>(
+        &self,
+        conn: &C,
+        event: AuditEvent,
+    ) -> Result<(), AppError> {
+        audit_events::ActiveModel {
+            id: Set(uuid::Uuid::now_v7()),
+            actor_id: Set(event.actor_id),
+            action: Set(event.action.as_str().to_string()),
+            target_type: Set(event.target_type),
+            target_id: Set(event.target_id),
+            ip_address: Set(event.client.ip_address),
+            user_agent: Set(event.client.user_agent),
+            changes: Set(event.changes),
+            reason: Set(event.reason),
+            created: Set(now()),
+        }
+        .insert(conn)
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Record an event for an action that has already happened.
+    /// Failures are only logged, so they do not turn the action into an error.
+    pub async fn record_or_warn(&self, event: AuditEvent) {
+        let action = event.action;
+        if let Err(e) = self.record(&self.db, event).await {
+            tracing::warn!("Failed to record {} audit event: {}", action.as_str(), e);
+        }
+    }
+
+    /// List audit events matching the query, newest first
+    pub async fn list(
+        &self,
+        query: AuditQuery,
+    ) -> Result<(Vec<AuditEventInfo>, Pagination), AppError> {
+        // Validate input
+        query.validate().map_err(AppError::from)?;
+
+        let mut select = AuditEvents::find();
+        if let Some(actor_id) = query.actor_id {
+            select = select.filter(audit_events::Column::ActorId.eq(actor_id));
+        }
+        if let Some(action) = &query.action {
+            select = select.filter(audit_events::Column::Action.eq(action));
+        }
+        if let Some(target_type) = &query.target_type {
+            select = select.filter(audit_events::Column::TargetType.eq(target_type));
+        }
+        if let Some(target_id) = &query.target_id {
+            select = select.filter(audit_events::Column::TargetId.eq(target_id));
+        }
+        if let Some(from) = query.from {
+            select = select.filter(audit_events::Column::Created.gte(from));
+        }
+        if let Some(to) = query.to {
+            select = select.filter(audit_events::Column::Created.lt(to));
+        }
+
+        let page = query.page.unwrap_or(1);
+        let per_page = query.per_page.unwrap_or(DEFAULT_PER_PAGE);
+        let paginator = select
+            .order_by_desc(audit_events::Column::Created)
+            .order_by_desc(audit_events::Column::Id)
+            .paginate(&self.db, per_page);
+
+        let counts = paginator
+            .num_items_and_pages()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        let events = paginator
+            .fetch_page(page - 1)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let pagination = Pagination {
+            page: page as u32,
+            limit: per_page as u32,
+            total: counts.number_of_items,
+            pages: counts.number_of_pages as u32,
+        };
+
+        Ok((
+            events.into_iter().map(AuditEventInfo::from).collect(),
+            pagination,
+        ))
+    }
+}
+
+// Current time in the timezone used by the entity timestamps
+fn now() -> chrono::DateTime<chrono::FixedOffset> {
+    chrono::Utc::now().with_timezone(&chrono::FixedOffset::east_opt(0).unwrap())
+}
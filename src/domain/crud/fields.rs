@@ -0,0 +1,174 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::domain::error::AppError;
+
+/// Which fields of an entity clients can see and change, declared with the `read_only`,
+/// `hidden` and `write_only` options of `make_crud_routes!`:
+///
+/// - read-only fields are returned but never taken from request bodies
+/// - hidden fields are neither returned nor taken from request bodies
+/// - write-only fields are taken from request bodies but never returned
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FieldAccess {
+    pub read_only: &'static [&'static str],
+    pub hidden: &'static [&'static str],
+    pub write_only: &'static [&'static str],
+}
+
+impl FieldAccess {
+    /// Whether a field is returned, and so can be filtered, sorted on and selected
+    pub fn is_readable(&self, field: &str) -> bool {
+        !self.hidden.contains(&field) && !self.write_only.contains(&field)
+    }
+
+    /// Whether a field is taken from request bodies
+    pub fn is_writable(&self, field: &str) -> bool {
+        !self.read_only.contains(&field) && !self.hidden.contains(&field)
+    }
+
+    /// Serialize a row without the fields clients cannot see
+    pub fn output<T: Serialize>(&self, row: &T) -> Value {
+        match serde_json::json!(row) {
+            Value::Object(mut object) => {
+                object.retain(|field, _| self.is_readable(field));
+                Value::Object(object)
+            }
+            row => row,
+        }
+    }
+
+    /// Request body for creating a row, without the fields clients cannot set
+    pub fn create_input(&self, payload: Value) -> Result<Value, AppError> {
+        let mut object = expect_object(payload)?;
+        object.retain(|field, _| self.is_writable(field));
+        Ok(Value::Object(object))
+    }
+
+    /// Request body for updating a row: its current values, overlaid with the fields of
+    /// the request clients can set. Write-only fields left empty keep their current value,
+    /// since clients never see it to send it back.
+    pub fn update_input(&self, current: Value, payload: Value) -> Result<Value, AppError> {
+        let mut object = match current {
+            Value::Object(object) => object,
+            _ => Map::new(),
+        };
+
+        for (field, value) in expect_object(payload)? {
+            let unset = value.is_null() || value.as_str().is_some_and(str::is_empty);
+            if !self.is_writable(&field) || (unset && self.write_only.contains(&field.as_str())) {
+                continue;
+            }
+            object.insert(field, value);
+        }
+
+        Ok(Value::Object(object))
+    }
+}
+
+fn expect_object(payload: Value) -> Result<Map<String, Value>, AppError> {
+    match payload {
+        Value::Object(object) => Ok(object),
+        _ => Err(AppError::validation("Request body must be a JSON object")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const ACCESS: FieldAccess = FieldAccess {
+        read_only: &["id", "created"],
+        hidden: &["secret"],
+        write_only: &["password_hash"],
+    };
+
+    #[test]
+    fn output_drops_hidden_and_write_only_fields() {
+        let row = json!({
+            "id": 1,
+            "name": "Ann",
+            "created": "today",
+            "secret": "s",
+            "password_hash": "h",
+        });
+        assert_eq!(
+            ACCESS.output(&row),
+            json!({ "id": 1, "name": "Ann", "created": "today" })
+        );
+    }
+
+    #[test]
+    fn create_input_drops_read_only_and_hidden_fields() {
+        let payload = json!({
+            "id": 1,
+            "name": "Ann",
+            "created": "today",
+            "secret": "s",
+            "password_hash": "h",
+        });
+        assert_eq!(
+            ACCESS.create_input(payload).unwrap(),
+            json!({ "name": "Ann", "password_hash": "h" })
+        );
+    }
+
+    #[test]
+    fn update_input_overlays_writable_fields_on_current_values() {
+        let current = json!({
+            "id": 1,
+            "name": "Ann",
+            "created": "today",
+            "secret": "s",
+            "password_hash": "h",
+        });
+        let payload = json!({
+            "id": 2,
+            "name": "Bob",
+            "created": "tomorrow",
+            "secret": "t",
+        });
+        assert_eq!(
+            ACCESS.update_input(current, payload).unwrap(),
+            json!({
+                "id": 1,
+                "name": "Bob",
+                "created": "today",
+                "secret": "s",
+                "password_hash": "h",
+            })
+        );
+    }
+
+    #[test]
+    fn update_input_keeps_write_only_fields_left_empty() {
+        let current = json!({ "name": "Ann", "password_hash": "h" });
+
+        for unset in [json!(null), json!("")] {
+            let payload = json!({ "name": "", "password_hash": unset });
+            assert_eq!(
+                ACCESS.update_input(current.clone(), payload).unwrap(),
+                json!({ "name": "", "password_hash": "h" })
+            );
+        }
+
+        let payload = json!({ "password_hash": "new" });
+        assert_eq!(
+            ACCESS.update_input(current, payload).unwrap(),
+            json!({ "name": "Ann", "password_hash": "new" })
+        );
+    }
+
+    #[test]
+    fn rejects_bodies_that_are_not_objects() {
+        assert!(matches!(
+            ACCESS.create_input(json!([1, 2])),
+            Err(AppError::Validation(_))
+        ));
+        assert!(matches!(
+            ACCESS.update_input(json!({}), json!("name")),
+            Err(AppError::Validation(_))
+        ));
+    }
+}
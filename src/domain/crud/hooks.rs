@@ -0,0 +1,173 @@
+use async_trait::async_trait;
+use sea_orm::{DatabaseTransaction, EntityTrait, TransactionTrait};
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Mutex;
+
+use crate::domain::audit::model::{AuditAction, AuditEvent};
+use crate::domain::error::AppError;
+use crate::entities::users::Model as UserModel;
+use crate::state::AppState;
+use crate::utils::client_info::ClientInfo;
+
+// Action run once the write of a request is committed
+type AfterCommit = Box<dyn FnOnce(&AppState) + Send>;
+
+/// Request a CRUD hook is called for
+pub struct CrudContext<'a> {
+    pub state: &'a AppState,
+    pub txn: DatabaseTransaction, // Transaction of the write; an error rolls it back
+    pub actor: &'a UserModel,
+    pub client: &'a ClientInfo,
+    pub target_type: &'a str, // Table acted on, e.g. "users"
+    after_commit: Mutex<Vec<AfterCommit>>,
+}
+
+impl<'a> CrudContext<'a> {
+    /// Start the transaction of a write
+    pub async fn begin(
+        state: &'a AppState,
+        actor: &'a UserModel,
+        client: &'a ClientInfo,
+        target_type: &'a str,
+    ) -> Result<Self, AppError> {
+        Ok(Self {
+            state,
+            txn: state.db.begin().await?,
+            actor,
+            client,
+            target_type,
+            after_commit: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Run an action once the write is committed, such as dropping cached copies of the
+    /// row, which a concurrent request could otherwise refill from the old row. Nothing
+    /// runs when the write is rolled back.
+    pub fn after_commit(&self, action: impl FnOnce(&AppState) + Send + 'static) {
+        self.after_commit.lock().unwrap().push(Box::new(action));
+    }
+
+    /// Commit the write, then run the actions queued by the hooks
+    pub async fn commit(self) -> Result<(), AppError> {
+        self.txn.commit().await?;
+        for action in self.after_commit.into_inner().unwrap() {
+            action(self.state);
+        }
+        Ok(())
+    }
+}
+
+/// Lifecycle hooks of the routes generated by `make_crud_routes!`, declared with its
+/// `hooks` option. Every hook runs inside the transaction of the write, so returning an
+/// error rejects the request and rolls the write back. Side effects outside the database
+/// belong in `CrudContext::after_commit`. Before hooks get the request body as it will be
+/// applied and may change it.
+#[async_trait]
+pub trait CrudHooks<E>: Default + Send + Sync
+where
+    E: EntityTrait,
+    E::Model: Sync,
+{
+    async fn before_create(
+        &self,
+        _ctx: &CrudContext<'_>,
+        _payload: &mut Value,
+    ) -> Result<(), AppError> {
+        Ok(())
+    }
+
+    async fn after_create(
+        &self,
+        _ctx: &CrudContext<'_>,
+        _model: &E::Model,
+    ) -> Result<(), AppError> {
+        Ok(())
+    }
+
+    async fn before_update(
+        &self,
+        _ctx: &CrudContext<'_>,
+        _current: &E::Model,
+        _payload: &mut Value,
+    ) -> Result<(), AppError> {
+        Ok(())
+    }
+
+    async fn after_update(
+        &self,
+        _ctx: &CrudContext<'_>,
+        _before: &E::Model,
+        _after: &E::Model,
+    ) -> Result<(), AppError> {
+        Ok(())
+    }
+
+    async fn before_delete(
+        &self,
+        _ctx: &CrudContext<'_>,
+        _model: &E::Model,
+    ) -> Result<(), AppError> {
+        Ok(())
+    }
+
+    async fn after_delete(
+        &self,
+        _ctx: &CrudContext<'_>,
+        _model: &E::Model,
+    ) -> Result<(), AppError> {
+        Ok(())
+    }
+}
+
+/// Records creations, updates and deletions in the audit log.
+/// Used by `make_crud_routes!` when no hooks are declared.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AuditHooks;
+
+#[async_trait]
+impl<E> CrudHooks<E> for AuditHooks
+where
+    E: EntityTrait,
+    E::Model: Serialize + Sync,
+{
+    async fn after_create(&self, ctx: &CrudContext<'_>, model: &E::Model) -> Result<(), AppError> {
+        let after = serde_json::json!(model);
+        record(ctx, AuditAction::Create, None, Some(&after)).await
+    }
+
+    async fn after_update(
+        &self,
+        ctx: &CrudContext<'_>,
+        before: &E::Model,
+        after: &E::Model,
+    ) -> Result<(), AppError> {
+        let before = serde_json::json!(before);
+        let after = serde_json::json!(after);
+        record(ctx, AuditAction::Update, Some(&before), Some(&after)).await
+    }
+
+    async fn after_delete(&self, ctx: &CrudContext<'_>, model: &E::Model) -> Result<(), AppError> {
+        let before = serde_json::json!(model);
+        record(ctx, AuditAction::Delete, Some(&before), None).await
+    }
+}
+
+// Record a CRUD edit of the row serialized in `before` and/or `after`
+async fn record(
+    ctx: &CrudContext<'_>,
+    action: AuditAction,
+    before: Option<&Value>,
+    after: Option<&Value>,
+) -> Result<(), AppError> {
+    let id = after
+        .or(before)
+        .and_then(|row| row.get("id"))
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let event = AuditEvent::new(action, ctx.target_type, id)
+        .actor(ctx.actor.id)
+        .client(ctx.client)
+        .changes(before, after);
+    ctx.state.audit_service.record(&ctx.txn, event).await
+}
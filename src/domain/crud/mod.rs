@@ -0,0 +1,7 @@
+pub mod fields;
+pub mod hooks;
+pub mod query;
+
+pub use fields::FieldAccess;
+pub use hooks::{AuditHooks, CrudContext, CrudHooks};
+pub use query::{ListPage, ListQuery};
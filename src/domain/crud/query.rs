@@ -0,0 +1,737 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use sea_orm::sea_query::extension::postgres::PgExpr;
+use sea_orm::sea_query::{ColumnType, Expr, SimpleExpr};
+use sea_orm::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+
+use super::fields::FieldAccess;
+use crate::domain::error::AppError;
+use crate::domain::response::{CursorPagination, Pagination};
+
+// Page size used when the request does not ask for one
+const DEFAULT_PER_PAGE: u64 = 10;
+
+// Largest page size a request can ask for
+const MAX_PER_PAGE: u64 = 100;
+
+// Parameters other than filters
+const PARAMS: [&str; 6] = ["page", "per_page", "sort", "fields", "cursor", "total"];
+
+/// Comparison applied by a `filter[column][op]=value` parameter; `eq` when no op is given
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FilterOp {
+    Eq,
+    Ne,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    Ilike,
+}
+
+impl FilterOp {
+    fn parse(op: &str) -> Option<Self> {
+        match op {
+            "eq" => Some(FilterOp::Eq),
+            "ne" => Some(FilterOp::Ne),
+            "gt" => Some(FilterOp::Gt),
+            "gte" => Some(FilterOp::Gte),
+            "lt" => Some(FilterOp::Lt),
+            "lte" => Some(FilterOp::Lte),
+            "ilike" => Some(FilterOp::Ilike),
+            _ => None,
+        }
+    }
+}
+
+/// Query parameters of a generated list endpoint, checked against the columns of the entity:
+///
+/// - `page` and `per_page` (1 to 100)
+/// - `filter[column]=value` or `filter[column][op]=value`, op being one of
+///   `eq`, `ne`, `gt`, `gte`, `lt`, `lte` or `ilike` (text columns only)
+/// - `sort=-created,word`, descending when the column starts with `-`
+/// - `fields=id,word` to return only some fields of each row
+///
+/// Passing `cursor` (empty for the first page) switches to keyset pagination, which stays
+/// fast on large tables. Rows are then sorted by `id` or `created` only, `page` is not
+/// allowed, and the total is counted only with `total=true`.
+#[derive(Debug, Clone)]
+pub struct ListQuery<E: EntityTrait> {
+    page: u64,
+    per_page: u64,
+    condition: Condition,
+    sort: Vec<(E::Column, Order)>,
+    keyset: Option<Keyset<E>>,
+    fields: Option<Vec<String>>,
+    access: FieldAccess,
+}
+
+/// Rows of a list page, with the pagination details for the response
+#[derive(Debug, Clone)]
+pub struct ListPage {
+    pub items: Vec<JsonValue>,
+    pub pagination: Option<Pagination>,
+    pub cursor: Option<CursorPagination>,
+}
+
+/// Keyset pagination of a list request
+#[derive(Debug, Clone)]
+struct Keyset<E: EntityTrait> {
+    sort: String,
+    created: Option<E::Column>, // Set when sorting by created, with id breaking ties
+    id: E::Column,
+    descending: bool,
+    position: Option<Cursor>, // None for the first page
+    with_total: bool,
+}
+
+/// Position in a keyset-paginated list, handed to clients as an opaque token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Cursor {
+    sort: String, // Sort order the cursor was issued for
+    id: uuid::Uuid,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    created: Option<chrono::DateTime<chrono::FixedOffset>>,
+    #[serde(default)]
+    before: bool, // Whether the page ends just before this row rather than starting after it
+}
+
+impl Cursor {
+    fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    fn decode(token: &str) -> Option<Self> {
+        let json = URL_SAFE_NO_PAD.decode(token).ok()?;
+        serde_json::from_slice(&json).ok()
+    }
+}
+
+impl<E: EntityTrait> ListQuery<E>
+where
+    E::Model: Serialize + Sync,
+{
+    /// Parse the query string parameters, rejecting unknown parameters, unknown or
+    /// unreadable columns and malformed values
+    pub fn parse(params: &HashMap<String, String>, access: FieldAccess) -> Result<Self, AppError> {
+        let page = match params.get("page") {
+            Some(page) => page
+                .parse::<u64>()
+                .ok()
+                .filter(|page| *page >= 1)
+                .ok_or_else(|| AppError::validation("Page must be a number of at least 1"))?,
+            None => 1,
+        };
+        let per_page = match params.get("per_page") {
+            Some(per_page) => per_page
+                .parse::<u64>()
+                .ok()
+                .filter(|per_page| (1..=MAX_PER_PAGE).contains(per_page))
+                .ok_or_else(|| {
+                    AppError::Validation(format!("Per page must be 1 to {}", MAX_PER_PAGE))
+                })?,
+            None => DEFAULT_PER_PAGE,
+        };
+
+        // An ignored parameter would return more rows than asked for, so none is ignored
+        let mut condition = Condition::all();
+        for (key, value) in params {
+            if !key.starts_with("filter") {
+                if !PARAMS.contains(&key.as_str()) {
+                    return Err(AppError::Validation(format!("Unknown parameter '{}'", key)));
+                }
+                continue;
+            }
+            let (name, op) = parse_filter_key(key).ok_or_else(|| {
+                AppError::Validation(format!(
+                    "Invalid filter '{}', expected filter[column] or filter[column][op]",
+                    key
+                ))
+            })?;
+            let op = FilterOp::parse(op)
+                .ok_or_else(|| AppError::Validation(format!("Unknown filter operator '{}'", op)))?;
+            condition = condition.add(filter_expr(find_column::<E>(name, &access)?, op, value)?);
+        }
+
+        let keyset = match params.get("cursor") {
+            Some(cursor) => {
+                if params.contains_key("page") {
+                    return Err(AppError::validation(
+                        "Page cannot be combined with cursor pagination",
+                    ));
+                }
+                Some(Keyset::parse(cursor, params, &access)?)
+            }
+            None => None,
+        };
+
+        let sort = match params.get("sort") {
+            Some(sort) if keyset.is_none() => split_list(sort)
+                .map(|name| match name.strip_prefix('-') {
+                    Some(name) => Ok((find_column::<E>(name, &access)?, Order::Desc)),
+                    None => Ok((find_column::<E>(name, &access)?, Order::Asc)),
+                })
+                .collect::<Result<_, AppError>>()?,
+            _ => Vec::new(),
+        };
+
+        let fields = match params.get("fields") {
+            Some(fields) => Some(
+                split_list(fields)
+                    .map(|name| find_column::<E>(name, &access).map(|_| name.to_string()))
+                    .collect::<Result<_, AppError>>()?,
+            ),
+            None => None,
+        };
+
+        Ok(Self {
+            page,
+            per_page,
+            condition,
+            sort,
+            keyset,
+            fields,
+            access,
+        })
+    }
+
+    /// Fetch the requested page of rows matching the filters
+    pub async fn fetch(
+        &self,
+        db: &DatabaseConnection,
+        select: Select<E>,
+    ) -> Result<ListPage, AppError> {
+        let select = select.filter(self.condition.clone());
+        match &self.keyset {
+            Some(keyset) => self.fetch_keyset(db, select, keyset).await,
+            None => self.fetch_offset(db, select).await,
+        }
+    }
+
+    async fn fetch_offset(
+        &self,
+        db: &DatabaseConnection,
+        mut select: Select<E>,
+    ) -> Result<ListPage, AppError> {
+        // Rows are ordered by primary key last, so pages stay stable when sorting
+        // by a column with duplicates
+        for (column, order) in &self.sort {
+            select = select.order_by(*column, order.clone());
+        }
+        for key in E::PrimaryKey::iter() {
+            let column = key.into_column();
+            if !self
+                .sort
+                .iter()
+                .any(|(sorted, _)| sorted.as_str() == column.as_str())
+            {
+                select = select.order_by_asc(column);
+            }
+        }
+
+        let paginator = select.paginate(db, self.per_page);
+        let counts = paginator.num_items_and_pages().await?;
+        let rows = paginator.fetch_page(self.page - 1).await?;
+
+        Ok(ListPage {
+            items: rows.iter().map(|row| self.select_fields(row)).collect(),
+            pagination: Some(Pagination {
+                page: self.page as u32,
+                limit: self.per_page as u32,
+                total: counts.number_of_items,
+                pages: counts.number_of_pages as u32,
+            }),
+            cursor: None,
+        })
+    }
+
+    async fn fetch_keyset(
+        &self,
+        db: &DatabaseConnection,
+        select: Select<E>,
+        keyset: &Keyset<E>,
+    ) -> Result<ListPage, AppError> {
+        let total = if keyset.with_total {
+            Some(select.clone().count(db).await?)
+        } else {
+            None
+        };
+
+        // Pages before a cursor are read backwards from it, then put back in order
+        let before = keyset.position.as_ref().is_some_and(|cursor| cursor.before);
+        let mut select = select;
+        if let Some(cursor) = &keyset.position {
+            select = select.filter(keyset.after(cursor));
+        }
+        let order = if keyset.descending != before {
+            Order::Desc
+        } else {
+            Order::Asc
+        };
+        if let Some(created) = keyset.created {
+            select = select.order_by(created, order.clone());
+        }
+
+        // One row more than the page tells whether another page follows
+        let mut rows = select
+            .order_by(keyset.id, order)
+            .limit(self.per_page + 1)
+            .all(db)
+            .await?;
+        let has_more = rows.len() as u64 > self.per_page;
+        rows.truncate(self.per_page as usize);
+        if before {
+            rows.reverse();
+        }
+
+        let rows: Vec<JsonValue> = rows.iter().map(|row| serde_json::json!(row)).collect();
+        let started_at_cursor = keyset.position.is_some();
+        let next_cursor = if (!before && has_more) || (before && started_at_cursor) {
+            rows.last().and_then(|row| keyset.cursor(row, false))
+        } else {
+            None
+        };
+        let prev_cursor = if (before && has_more) || (!before && started_at_cursor) {
+            rows.first().and_then(|row| keyset.cursor(row, true))
+        } else {
+            None
+        };
+
+        Ok(ListPage {
+            items: rows.iter().map(|row| self.select_fields(row)).collect(),
+            pagination: None,
+            cursor: Some(CursorPagination {
+                limit: self.per_page as u32,
+                next_cursor,
+                prev_cursor,
+                total,
+            }),
+        })
+    }
+
+    /// Serialize a row, keeping only the requested fields clients can see
+    fn select_fields<T: Serialize>(&self, row: &T) -> JsonValue {
+        match (&self.fields, self.access.output(row)) {
+            (Some(fields), JsonValue::Object(mut object)) => {
+                object.retain(|field, _| fields.contains(field));
+                JsonValue::Object(object)
+            }
+            (_, item) => item,
+        }
+    }
+}
+
+impl<E: EntityTrait> Keyset<E> {
+    fn parse(
+        cursor: &str,
+        params: &HashMap<String, String>,
+        access: &FieldAccess,
+    ) -> Result<Self, AppError> {
+        let sort = params.get("sort").map(|sort| sort.trim()).unwrap_or("id");
+        let (created, descending) = match sort {
+            "id" => (None, false),
+            "-id" => (None, true),
+            "created" => (Some(find_column::<E>("created", access)?), false),
+            "-created" => (Some(find_column::<E>("created", access)?), true),
+            _ => {
+                return Err(AppError::validation(
+                    "Cursor pagination can only sort by id or created",
+                ))
+            }
+        };
+
+        let position = if cursor.is_empty() {
+            None
+        } else {
+            let position = Cursor::decode(cursor)
+                .filter(|position| position.sort == sort)
+                .filter(|position| created.is_none() || position.created.is_some())
+                .ok_or_else(|| {
+                    AppError::validation("Invalid cursor, or cursor issued for another sort")
+                })?;
+            Some(position)
+        };
+
+        let with_total = match params.get("total").map(String::as_str) {
+            Some("true") => true,
+            Some("false") | None => false,
+            Some(_) => return Err(AppError::validation("Total must be true or false")),
+        };
+
+        Ok(Self {
+            sort: sort.to_string(),
+            created,
+            id: find_column::<E>("id", access)?,
+            descending,
+            position,
+            with_total,
+        })
+    }
+
+    /// Rows on the far side of the cursor, in the direction the page is read
+    fn after(&self, cursor: &Cursor) -> Condition {
+        let forward = self.descending == cursor.before;
+        let beyond = |column: E::Column, value: Value| {
+            if forward {
+                column.gt(value)
+            } else {
+                column.lt(value)
+            }
+        };
+
+        match (self.created, cursor.created) {
+            (Some(created), Some(created_at)) => Condition::any()
+                .add(beyond(created, created_at.into()))
+                .add(
+                    Condition::all()
+                        .add(created.eq(created_at))
+                        .add(beyond(self.id, cursor.id.into())),
+                ),
+            _ => Condition::all().add(beyond(self.id, cursor.id.into())),
+        }
+    }
+
+    /// Cursor pointing at a serialized row
+    fn cursor(&self, row: &JsonValue, before: bool) -> Option<String> {
+        let id = row.get("id")?.as_str()?.parse().ok()?;
+        let created = match self.created {
+            Some(_) => {
+                Some(chrono::DateTime::parse_from_rfc3339(row.get("created")?.as_str()?).ok()?)
+            }
+            None => None,
+        };
+
+        Some(
+            Cursor {
+                sort: self.sort.clone(),
+                id,
+                created,
+                before,
+            }
+            .encode(),
+        )
+    }
+}
+
+/// Column of the entity with the given name, if clients can see it
+fn find_column<E: EntityTrait>(name: &str, access: &FieldAccess) -> Result<E::Column, AppError> {
+    E::Column::iter()
+        .filter(|column| access.is_readable(column.as_str()))
+        .find(|column| column.as_str() == name)
+        .ok_or_else(|| AppError::Validation(format!("Unknown column '{}'", name)))
+}
+
+// Column and operator of a `filter[column]` or `filter[column][op]` key, `eq` when no op
+// is given; None when the key has another shape
+fn parse_filter_key(key: &str) -> Option<(&str, &str)> {
+    let filter = key.strip_prefix("filter[")?.strip_suffix(']')?;
+    let (name, op) = filter.split_once("][").unwrap_or((filter, "eq"));
+    let valid = |part: &str| !part.is_empty() && !part.contains(['[', ']']);
+    (valid(name) && valid(op)).then_some((name, op))
+}
+
+// Names of a comma-separated parameter, ignoring empty entries
+fn split_list(list: &str) -> impl Iterator<Item = &str> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+}
+
+/// Condition comparing a column with a value from the query string
+fn filter_expr<C: ColumnTrait>(column: C, op: FilterOp, raw: &str) -> Result<SimpleExpr, AppError> {
+    let column_type = column.def().get_column_type().clone();
+
+    if op == FilterOp::Ilike {
+        if !matches!(
+            column_type,
+            ColumnType::String(_) | ColumnType::Text | ColumnType::Char(_)
+        ) {
+            return Err(AppError::Validation(format!(
+                "Column '{}' is not text and cannot be filtered with ilike",
+                column.as_str()
+            )));
+        }
+        return Ok(Expr::col((column.entity_name(), column)).ilike(raw));
+    }
+
+    let value = parse_value(&column_type, raw).ok_or_else(|| {
+        AppError::Validation(format!(
+            "Invalid value '{}' for column '{}'",
+            raw,
+            column.as_str()
+        ))
+    })?;
+
+    Ok(match op {
+        FilterOp::Eq => column.eq(value),
+        FilterOp::Ne => column.ne(value),
+        FilterOp::Gt => column.gt(value),
+        FilterOp::Gte => column.gte(value),
+        FilterOp::Lt => column.lt(value),
+        FilterOp::Lte => column.lte(value),
+        FilterOp::Ilike => unreachable!("ilike filters are handled above"),
+    })
+}
+
+/// Value of the column's type parsed from the query string; None when it does not parse
+/// or the column type cannot be filtered on (such as JSON and arrays)
+fn parse_value(column_type: &ColumnType, raw: &str) -> Option<Value> {
+    Some(match column_type {
+        ColumnType::Uuid => raw.parse::<uuid::Uuid>().ok()?.into(),
+        ColumnType::TimestampWithTimeZone => chrono::DateTime::parse_from_rfc3339(raw).ok()?.into(),
+        ColumnType::Timestamp | ColumnType::DateTime => {
+            raw.parse::<chrono::NaiveDateTime>().ok()?.into()
+        }
+        ColumnType::Date => raw.parse::<chrono::NaiveDate>().ok()?.into(),
+        ColumnType::SmallInteger => raw.parse::<i16>().ok()?.into(),
+        ColumnType::Integer => raw.parse::<i32>().ok()?.into(),
+        ColumnType::BigInteger => raw.parse::<i64>().ok()?.into(),
+        ColumnType::Float => raw.parse::<f32>().ok()?.into(),
+        ColumnType::Double => raw.parse::<f64>().ok()?.into(),
+        ColumnType::Boolean => raw.parse::<bool>().ok()?.into(),
+        ColumnType::String(_) | ColumnType::Text | ColumnType::Char(_) => raw.to_string().into(),
+        // Enums are serialized capitalized but stored lowercase, so either spelling matches.
+        // The column casts the value to the Postgres enum type.
+        ColumnType::Enum { variants, .. } => variants
+            .iter()
+            .map(|variant| variant.to_string())
+            .find(|variant| variant.eq_ignore_ascii_case(raw))?
+            .into(),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::users;
+
+    const ACCESS: FieldAccess = FieldAccess {
+        read_only: &["id", "created", "updated"],
+        hidden: &[],
+        write_only: &["password_hash"],
+    };
+
+    fn parse(params: &[(&str, &str)]) -> Result<ListQuery<users::Entity>, AppError> {
+        let params = params
+            .iter()
+            .map(|(key, value)| (String::from(*key), String::from(*value)))
+            .collect();
+        ListQuery::parse(&params, ACCESS)
+    }
+
+    fn validation_error(params: &[(&str, &str)]) -> String {
+        match parse(params) {
+            Err(AppError::Validation(message)) => message,
+            other => panic!("expected a validation error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    fn where_sql(query: &ListQuery<users::Entity>) -> String {
+        users::Entity::find()
+            .filter(query.condition.clone())
+            .build(DbBackend::Postgres)
+            .to_string()
+    }
+
+    #[test]
+    fn defaults_to_first_page_of_ten() {
+        let query = parse(&[]).unwrap();
+        assert_eq!((query.page, query.per_page), (1, DEFAULT_PER_PAGE));
+        assert!(query.sort.is_empty() && query.fields.is_none() && query.keyset.is_none());
+    }
+
+    #[test]
+    fn validates_page_and_per_page() {
+        let query = parse(&[("page", "3"), ("per_page", "100")]).unwrap();
+        assert_eq!((query.page, query.per_page), (3, 100));
+
+        for params in [
+            [("page", "0")],
+            [("page", "two")],
+            [("per_page", "0")],
+            [("per_page", "101")],
+        ] {
+            validation_error(&params);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_parameters() {
+        assert_eq!(
+            validation_error(&[("limit", "5")]),
+            "Unknown parameter 'limit'"
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_filter_keys() {
+        for key in [
+            "filter",
+            "filterx",
+            "filter[]",
+            "filter[email",
+            "filter[email][]",
+            "filter[][eq]",
+            "filter[email][eq][x]",
+        ] {
+            assert!(
+                validation_error(&[(key, "x")]).starts_with("Invalid filter"),
+                "{key} was accepted"
+            );
+        }
+    }
+
+    #[test]
+    fn parses_filter_keys() {
+        assert_eq!(parse_filter_key("filter[email]"), Some(("email", "eq")));
+        assert_eq!(
+            parse_filter_key("filter[created][gte]"),
+            Some(("created", "gte"))
+        );
+        assert_eq!(parse_filter_key("filters[email]"), None);
+    }
+
+    #[test]
+    fn builds_filter_conditions() {
+        let query = parse(&[
+            ("filter[email][ilike]", "%@example.com"),
+            ("filter[created][gte]", "2024-01-01T00:00:00Z"),
+        ])
+        .unwrap();
+        let sql = where_sql(&query);
+        assert!(
+            sql.contains(r#""users"."email" ILIKE '%@example.com'"#),
+            "{sql}"
+        );
+        assert!(
+            sql.contains(r#""users"."created" >= '2024-01-01 00:00:00.000000 +00:00'"#),
+            "{sql}"
+        );
+    }
+
+    #[test]
+    fn matches_enum_filters_in_either_case() {
+        let sql = where_sql(&parse(&[("filter[role]", "Admin")]).unwrap());
+        assert!(sql.contains("'admin'"), "{sql}");
+
+        validation_error(&[("filter[role]", "superuser")]);
+    }
+
+    #[test]
+    fn rejects_bad_filter_operators_and_values() {
+        assert_eq!(
+            validation_error(&[("filter[email][like]", "x")]),
+            "Unknown filter operator 'like'"
+        );
+        assert_eq!(
+            validation_error(&[("filter[created][gt]", "yesterday")]),
+            "Invalid value 'yesterday' for column 'created'"
+        );
+        assert!(validation_error(&[("filter[created][ilike]", "2024%")]).contains("not text"));
+    }
+
+    #[test]
+    fn hides_unreadable_columns() {
+        for params in [
+            [("filter[password_hash]", "x")],
+            [("sort", "password_hash")],
+            [("fields", "id,password_hash")],
+        ] {
+            assert_eq!(validation_error(&params), "Unknown column 'password_hash'");
+        }
+    }
+
+    #[test]
+    fn parses_sort_and_fields() {
+        let query = parse(&[("sort", "-created, email,"), ("fields", "id,email")]).unwrap();
+        let sort: Vec<_> = query
+            .sort
+            .iter()
+            .map(|(column, order)| (column.as_str(), matches!(order, Order::Desc)))
+            .collect();
+        assert_eq!(sort, vec![("created", true), ("email", false)]);
+        assert_eq!(
+            query.fields,
+            Some(vec!["id".to_string(), "email".to_string()])
+        );
+    }
+
+    #[test]
+    fn empty_cursor_starts_keyset_pagination() {
+        let query = parse(&[("cursor", ""), ("sort", "-created"), ("total", "true")]).unwrap();
+        let keyset = query.keyset.unwrap();
+        assert_eq!(keyset.sort, "-created");
+        assert!(keyset.created.is_some() && keyset.descending && keyset.with_total);
+        assert!(keyset.position.is_none());
+        // The keyset orders the rows itself
+        assert!(query.sort.is_empty());
+    }
+
+    #[test]
+    fn validates_keyset_parameters() {
+        validation_error(&[("cursor", ""), ("page", "2")]);
+        validation_error(&[("cursor", ""), ("sort", "email")]);
+        validation_error(&[("cursor", ""), ("total", "yes")]);
+        validation_error(&[("cursor", "not a cursor")]);
+    }
+
+    #[test]
+    fn cursor_round_trips() {
+        let cursor = Cursor {
+            sort: "-created".to_string(),
+            id: uuid::Uuid::now_v7(),
+            created: Some(
+                chrono::DateTime::parse_from_rfc3339("2024-05-01T10:00:00+07:00").unwrap(),
+            ),
+            before: true,
+        };
+        let decoded = Cursor::decode(&cursor.encode()).unwrap();
+        assert_eq!(decoded.sort, cursor.sort);
+        assert_eq!(decoded.id, cursor.id);
+        assert_eq!(decoded.created, cursor.created);
+        assert!(decoded.before);
+
+        assert!(Cursor::decode("not a cursor").is_none());
+        assert!(Cursor::decode(&URL_SAFE_NO_PAD.encode(b"{}")).is_none());
+    }
+
+    #[test]
+    fn accepts_cursors_only_for_their_sort() {
+        let cursor = Cursor {
+            sort: "id".to_string(),
+            id: uuid::Uuid::now_v7(),
+            created: None,
+            before: false,
+        }
+        .encode();
+
+        let query = parse(&[("cursor", &cursor)]).unwrap();
+        assert!(query.keyset.unwrap().position.is_some());
+
+        validation_error(&[("cursor", &cursor), ("sort", "-id")]);
+        // Sorting by created needs the created value of the row
+        validation_error(&[("cursor", &cursor), ("sort", "created")]);
+    }
+
+    #[test]
+    fn issues_cursors_for_serialized_rows() {
+        let keyset = parse(&[("cursor", ""), ("sort", "created")])
+            .unwrap()
+            .keyset
+            .unwrap();
+        let id = uuid::Uuid::now_v7();
+        let row = serde_json::json!({ "id": id, "created": "2024-05-01T10:00:00+07:00" });
+
+        let cursor = Cursor::decode(&keyset.cursor(&row, false).unwrap()).unwrap();
+        assert_eq!((cursor.sort.as_str(), cursor.id), ("created", id));
+        assert!(cursor.created.is_some() && !cursor.before);
+
+        assert!(keyset
+            .cursor(&serde_json::json!({ "id": id }), false)
+            .is_none());
+    }
+}
@@ -1,4 +1,5 @@
 use crate::domain::response::{ErrorType, Response};
+use crate::utils::password_policy::PasswordPolicyViolation;
 use anyhow::Error as AnyhowError;
 use serde::{Deserialize, Serialize};
 use std::fmt;
@@ -13,6 +14,7 @@ pub enum AppError {
     InvalidPassword(String),
     MissingField(String),
     UserValidationError(String),
+    PasswordPolicy(Vec<PasswordPolicyViolation>),
 
     // Business logic errors
     NotFound(String),
@@ -23,6 +25,7 @@ pub enum AppError {
     // Authentication/Authorization errors
     Unauthorized(String),
     Forbidden(String),
+    TooManyLoginAttempts(String),
 
     // Database errors
     DatabaseError(String),
@@ -44,12 +47,18 @@ impl fmt::Display for AppError {
             AppError::InvalidPassword(msg) => write!(f, "Invalid password: {}", msg),
             AppError::MissingField(msg) => write!(f, "Missing field: {}", msg),
             AppError::UserValidationError(msg) => write!(f, "User validation error: {}", msg),
+            AppError::PasswordPolicy(violations) => write!(
+                f,
+                "Password policy violated: {}",
+                violation_messages(violations)
+            ),
             AppError::NotFound(msg) => write!(f, "Not found: {}", msg),
             AppError::AlreadyExists(msg) => write!(f, "Already exists: {}", msg),
             AppError::UsernameAlreadyExists(msg) => write!(f, "Username already exists: {}", msg),
             AppError::Conflict(msg) => write!(f, "Conflict: {}", msg),
             AppError::Unauthorized(msg) => write!(f, "Unauthorized: {}", msg),
             AppError::Forbidden(msg) => write!(f, "Forbidden: {}", msg),
+            AppError::TooManyLoginAttempts(msg) => write!(f, "Too many login attempts: {}", msg),
             AppError::DatabaseError(msg) => write!(f, "Database error: {}", msg),
             AppError::ConnectionError(msg) => write!(f, "Connection error: {}", msg),
             AppError::ExternalServiceError(msg) => write!(f, "External service error: {}", msg),
@@ -102,6 +111,39 @@ impl From<sqlx::Error> for AppError {
     }
 }
 
+// Implement the From trait for sea_orm::DbErr
+impl From<sea_orm::DbErr> for AppError {
+    fn from(err: sea_orm::DbErr) -> Self {
+        // Check for constraint violations first, whatever the query that hit them. The
+        // database message names tables and columns, so it is only logged.
+        match err.sql_err() {
+            Some(sea_orm::SqlErr::UniqueConstraintViolation(msg)) => {
+                tracing::warn!("Unique constraint violation: {}", msg);
+                return if msg.contains("username") {
+                    AppError::UsernameAlreadyExists("Username already exists".to_string())
+                } else {
+                    AppError::AlreadyExists("Resource already exists".to_string())
+                };
+            }
+            Some(sea_orm::SqlErr::ForeignKeyConstraintViolation(msg)) => {
+                tracing::warn!("Foreign key constraint violation: {}", msg);
+                return AppError::Conflict("Related record is missing or still in use".to_string());
+            }
+            _ => {}
+        }
+
+        match err {
+            sea_orm::DbErr::RecordNotFound(msg) => AppError::NotFound(msg),
+            sea_orm::DbErr::RecordNotUpdated => {
+                AppError::NotFound("Record to update not found".to_string())
+            }
+            sea_orm::DbErr::Json(msg) | sea_orm::DbErr::Type(msg) => AppError::Validation(msg),
+            sea_orm::DbErr::ConnectionAcquire(err) => AppError::ConnectionError(err.to_string()),
+            _ => AppError::DatabaseError(err.to_string()),
+        }
+    }
+}
+
 // Implement the From trait for uuid::Error
 impl From<uuid::Error> for AppError {
     fn from(_: uuid::Error) -> Self {
@@ -143,141 +185,62 @@ impl<T> ToResponse<T> for Result<T, AppError> {
     fn to_response(self, success_message: &str) -> Response<T> {
         match self {
             Ok(data) => Response::success_ok(data, success_message),
-            Err(err) => {
-                let (error_type, message, error_detail) = match err {
-                    AppError::Validation(msg) => {
-                        (ErrorType::Validation, "Validation failed", Some(msg))
-                    }
-                    AppError::InvalidEmail(msg) => {
-                        (ErrorType::Validation, "Invalid email format", Some(msg))
-                    }
-                    AppError::InvalidPassword(msg) => {
-                        (ErrorType::Validation, "Invalid password", Some(msg))
-                    }
-                    AppError::MissingField(msg) => {
-                        (ErrorType::Validation, "Missing required field", Some(msg))
-                    }
-                    AppError::UserValidationError(msg) => {
-                        (ErrorType::Validation, "User validation failed", Some(msg))
-                    }
-                    AppError::NotFound(msg) => {
-                        (ErrorType::NotFound, "Resource not found", Some(msg))
-                    }
-                    AppError::AlreadyExists(msg) => {
-                        (ErrorType::Conflict, "Resource already exists", Some(msg))
-                    }
-                    AppError::UsernameAlreadyExists(msg) => {
-                        (ErrorType::Conflict, "Username already exists", Some(msg))
-                    }
-                    AppError::Conflict(msg) => {
-                        (ErrorType::Conflict, "Resource conflict", Some(msg))
-                    }
-                    AppError::Unauthorized(msg) => {
-                        (ErrorType::Unauthorized, "Unauthorized access", Some(msg))
-                    }
-                    AppError::Forbidden(msg) => {
-                        (ErrorType::Forbidden, "Access forbidden", Some(msg))
-                    }
-                    AppError::DatabaseError(msg) => {
-                        (ErrorType::Internal, "Database operation failed", Some(msg))
-                    }
-                    AppError::ConnectionError(msg) => {
-                        (ErrorType::Internal, "Connection failed", Some(msg))
-                    }
-                    AppError::ExternalServiceError(msg) => {
-                        (ErrorType::Internal, "External service error", Some(msg))
-                    }
-                    AppError::Internal(msg) => {
-                        (ErrorType::Internal, "Internal server error", Some(msg))
-                    }
-                };
-
-                Response::failure(message, error_type, error_detail)
-            }
+            Err(err) => err.into_failure(),
         }
     }
 
     fn to_response_created(self, success_message: &str) -> Response<T> {
         match self {
             Ok(data) => Response::success_created(data, success_message),
-            Err(err) => {
-                let (error_type, message, error_detail) = match err {
-                    AppError::Validation(msg) => {
-                        (ErrorType::Validation, "Validation failed", Some(msg))
-                    }
-                    AppError::InvalidEmail(msg) => {
-                        (ErrorType::Validation, "Invalid email format", Some(msg))
-                    }
-                    AppError::InvalidPassword(msg) => {
-                        (ErrorType::Validation, "Invalid password", Some(msg))
-                    }
-                    AppError::MissingField(msg) => {
-                        (ErrorType::Validation, "Missing required field", Some(msg))
-                    }
-                    AppError::UserValidationError(msg) => {
-                        (ErrorType::Validation, "User validation failed", Some(msg))
-                    }
-                    AppError::NotFound(msg) => {
-                        (ErrorType::NotFound, "Resource not found", Some(msg))
-                    }
-                    AppError::AlreadyExists(msg) => {
-                        (ErrorType::Conflict, "Resource already exists", Some(msg))
-                    }
-                    AppError::UsernameAlreadyExists(msg) => {
-                        (ErrorType::Conflict, "Username already exists", Some(msg))
-                    }
-                    AppError::Conflict(msg) => {
-                        (ErrorType::Conflict, "Resource conflict", Some(msg))
-                    }
-                    AppError::Unauthorized(msg) => {
-                        (ErrorType::Unauthorized, "Unauthorized access", Some(msg))
-                    }
-                    AppError::Forbidden(msg) => {
-                        (ErrorType::Forbidden, "Access forbidden", Some(msg))
-                    }
-                    AppError::DatabaseError(msg) => {
-                        (ErrorType::Internal, "Database operation failed", Some(msg))
-                    }
-                    AppError::ConnectionError(msg) => {
-                        (ErrorType::Internal, "Connection failed", Some(msg))
-                    }
-                    AppError::ExternalServiceError(msg) => {
-                        (ErrorType::Internal, "External service error", Some(msg))
-                    }
-                    AppError::Internal(msg) => {
-                        (ErrorType::Internal, "Internal server error", Some(msg))
-                    }
-                };
-
-                Response::failure(message, error_type, error_detail)
-            }
+            Err(err) => err.into_failure(),
         }
     }
 
     fn to_response_no_content(self, success_message: &str) -> Response<serde_json::Value> {
         match self {
             Ok(_) => Response::success_no_content(success_message),
-            Err(err) => {
-                let (error_type, message, error_detail) = match err {
-                    AppError::NotFound(msg) => {
-                        (ErrorType::NotFound, "Resource not found", Some(msg))
-                    }
-                    AppError::DatabaseError(msg) => {
-                        (ErrorType::Internal, "Database operation failed", Some(msg))
-                    }
-                    AppError::ConnectionError(msg) => {
-                        (ErrorType::Internal, "Connection failed", Some(msg))
-                    }
-                    _ => (
-                        ErrorType::Internal,
-                        "Operation failed",
-                        Some(err.to_string()),
-                    ),
-                };
+            Err(err) => err.into_failure(),
+        }
+    }
+}
 
-                Response::failure(message, error_type, error_detail)
+impl AppError {
+    /// Failure response for the error, with the error type deciding the status code
+    pub fn into_failure<T>(self) -> Response<T> {
+        let details = self.details();
+        let (error_type, message, error_detail) = match self {
+            AppError::Validation(msg) => (ErrorType::Validation, "Validation failed", msg),
+            AppError::InvalidEmail(msg) => (ErrorType::Validation, "Invalid email format", msg),
+            AppError::InvalidPassword(msg) => (ErrorType::Validation, "Invalid password", msg),
+            AppError::MissingField(msg) => (ErrorType::Validation, "Missing required field", msg),
+            AppError::UserValidationError(msg) => {
+                (ErrorType::Validation, "User validation failed", msg)
             }
-        }
+            AppError::PasswordPolicy(violations) => (
+                ErrorType::Validation,
+                "Password does not meet the policy",
+                violation_messages(&violations),
+            ),
+            AppError::NotFound(msg) => (ErrorType::NotFound, "Resource not found", msg),
+            AppError::AlreadyExists(msg) => (ErrorType::Conflict, "Resource already exists", msg),
+            AppError::UsernameAlreadyExists(msg) => {
+                (ErrorType::Conflict, "Username already exists", msg)
+            }
+            AppError::Conflict(msg) => (ErrorType::Conflict, "Resource conflict", msg),
+            AppError::Unauthorized(msg) => (ErrorType::Unauthorized, "Unauthorized access", msg),
+            AppError::Forbidden(msg) => (ErrorType::Forbidden, "Access forbidden", msg),
+            AppError::TooManyLoginAttempts(msg) => {
+                (ErrorType::TooManyRequests, "Too many login attempts", msg)
+            }
+            AppError::DatabaseError(msg) => (ErrorType::Internal, "Database operation failed", msg),
+            AppError::ConnectionError(msg) => (ErrorType::Internal, "Connection failed", msg),
+            AppError::ExternalServiceError(msg) => {
+                (ErrorType::Internal, "External service error", msg)
+            }
+            AppError::Internal(msg) => (ErrorType::Internal, "Internal server error", msg),
+        };
+
+        Response::failure(message, error_type, Some(error_detail)).with_details(details)
     }
 }
 
@@ -319,4 +282,22 @@ impl AppError {
     pub fn validation(message: &str) -> Self {
         AppError::Validation(message.to_string())
     }
+
+    /// Structured information about the error, for clients to act on
+    pub fn details(&self) -> Option<serde_json::Value> {
+        match self {
+            AppError::PasswordPolicy(violations) => {
+                Some(serde_json::json!({ "violations": violations }))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn violation_messages(violations: &[PasswordPolicyViolation]) -> String {
+    violations
+        .iter()
+        .map(|violation| violation.message.as_str())
+        .collect::<Vec<_>>()
+        .join("; ")
 }
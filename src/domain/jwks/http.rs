@@ -0,0 +1,20 @@
+use crate::AppState;
+use axum::extract::State;
+use axum::http::header;
+use axum::response::IntoResponse;
+use axum::{routing::get, Json, Router};
+use std::sync::Arc;
+
+/// Publish the public signing keys so other services can verify our access tokens.
+/// Served as a bare JWK set (not wrapped in the response envelope) for JWT libraries.
+pub async fn jwks(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    (
+        [(header::CACHE_CONTROL, "public, max-age=300")],
+        Json(state.jwt_util.jwks()),
+    )
+}
+
+/// JWKS Router
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new().route("/jwks.json", get(jwks))
+}
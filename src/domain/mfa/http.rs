@@ -0,0 +1,131 @@
+use axum::{
+    extract::{Path, State},
+    middleware,
+    response::IntoResponse,
+    routing::{get, post, put},
+    Extension, Json, Router,
+};
+use std::sync::Arc;
+
+use super::model::{DisableTotpRequest, TotpCodeRequest, UpdateMfaPolicyRequest};
+use crate::domain::error::ToResponse;
+use crate::domain::user::model::CurrentSession;
+use crate::entities::users::Model as User;
+use crate::middleware::{forbid_impersonation, rate_limit};
+use crate::state::AppState;
+use crate::utils::client_info::ClientInfo;
+use crate::utils::rate_limit::RateLimitScope;
+
+/// Get the two-factor authentication state of the current user
+pub async fn status(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+) -> impl IntoResponse {
+    state
+        .mfa_service
+        .status(&user)
+        .await
+        .to_response("Two-factor authentication status retrieved successfully")
+}
+
+/// Start TOTP enrollment
+pub async fn enroll_totp(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+) -> impl IntoResponse {
+    state
+        .mfa_service
+        .enroll_totp(&user)
+        .await
+        .to_response_created("TOTP enrollment started")
+}
+
+/// Confirm TOTP enrollment with a code
+pub async fn confirm_totp(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Extension(CurrentSession(session_id)): Extension<CurrentSession>,
+    client: ClientInfo,
+    Json(req): Json<TotpCodeRequest>,
+) -> impl IntoResponse {
+    state
+        .mfa_service
+        .confirm_totp(&user, session_id, req, &client)
+        .await
+        .to_response("Two-factor authentication enabled")
+}
+
+/// Turn off TOTP
+pub async fn disable_totp(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Extension(CurrentSession(session_id)): Extension<CurrentSession>,
+    client: ClientInfo,
+    Json(req): Json<DisableTotpRequest>,
+) -> impl IntoResponse {
+    state
+        .mfa_service
+        .disable_totp(&user, session_id, req, &client)
+        .await
+        .to_response_no_content("Two-factor authentication disabled")
+}
+
+/// Replace the recovery codes of the current user
+pub async fn regenerate_recovery_codes(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    client: ClientInfo,
+    Json(req): Json<TotpCodeRequest>,
+) -> impl IntoResponse {
+    state
+        .mfa_service
+        .regenerate_recovery_codes(&user, req, &client)
+        .await
+        .to_response("Recovery codes regenerated successfully")
+}
+
+/// List which roles must use two-factor authentication (admin only)
+pub async fn list_policies(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    state
+        .mfa_service
+        .list_policies()
+        .await
+        .to_response("MFA policies retrieved successfully")
+}
+
+/// Require or stop requiring two-factor authentication for a role (admin only)
+pub async fn update_policy(
+    State(state): State<Arc<AppState>>,
+    Extension(admin): Extension<User>,
+    Path(role): Path<String>,
+    client: ClientInfo,
+    Json(req): Json<UpdateMfaPolicyRequest>,
+) -> impl IntoResponse {
+    state
+        .mfa_service
+        .set_policy(admin.id, &role, req, &client)
+        .await
+        .to_response("MFA policy updated successfully")
+}
+
+/// MFA Router (routes requiring authentication, nested under `/auth/mfa` by the caller)
+pub fn protected_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(status))
+        .route("/totp/enroll", post(enroll_totp))
+        .route("/totp/confirm", post(confirm_totp))
+        .route("/totp/disable", post(disable_totp))
+        .route("/recovery-codes", post(regenerate_recovery_codes))
+        .route_layer(middleware::from_fn(forbid_impersonation))
+        .route_layer(middleware::from_fn_with_state(
+            RateLimitScope::Auth,
+            rate_limit,
+        ))
+}
+
+/// MFA Router (admin routes, nested under `/admin` by the caller)
+pub fn admin_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/mfa-policies", get(list_policies))
+        .route("/mfa-policies/{role}", put(update_policy))
+}
@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::domain::user::model::Role;
+
+// ============= MFA Request DTOs =============
+
+/// Request body carrying a code from the authenticator app
+#[derive(Debug, Deserialize, Validate)]
+pub struct TotpCodeRequest {
+    #[validate(length(min = 1, message = "Code cannot be empty"))]
+    pub code: String,
+}
+
+/// Request body for turning off two-factor authentication
+#[derive(Debug, Deserialize, Validate)]
+pub struct DisableTotpRequest {
+    #[validate(length(min = 1, message = "Password cannot be empty"))]
+    pub password: String,
+}
+
+/// Request body for changing whether a role must use two-factor authentication
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateMfaPolicyRequest {
+    pub required: bool,
+}
+
+// ============= MFA Response DTOs =============
+
+/// Two-factor authentication state of the current user
+#[derive(Debug, Serialize)]
+pub struct MfaStatusResponse {
+    pub totp_enabled: bool,
+    pub required: bool,
+    pub recovery_codes_remaining: u64,
+}
+
+/// New authenticator secret, to be confirmed with a code
+#[derive(Debug, Serialize)]
+pub struct TotpEnrollmentResponse {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+/// Recovery codes, shown only once
+#[derive(Debug, Serialize)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
+
+/// Whether a role must use two-factor authentication
+#[derive(Debug, Serialize)]
+pub struct MfaPolicyInfo {
+    pub role: Role,
+    pub required: bool,
+}
@@ -0,0 +1,578 @@
+// Import Domain
+use crate::entities::recovery_codes::{self, Entity as RecoveryCodes};
+use crate::entities::role_mfa_policies::{self, Entity as RoleMfaPolicies};
+use crate::entities::sessions::{self, Entity as Sessions};
+use crate::entities::user_totp::{self, Entity as UserTotp};
+use crate::entities::users::Model as UserModel;
+use sea_orm::sea_query::{Expr, OnConflict};
+use sea_orm::*;
+
+// Import Dtos
+use super::model::{
+    DisableTotpRequest, MfaPolicyInfo, MfaStatusResponse, RecoveryCodesResponse, TotpCodeRequest,
+    TotpEnrollmentResponse, UpdateMfaPolicyRequest,
+};
+use crate::domain::user::model::Role;
+
+// Import Utils
+use crate::domain::audit::model::{AuditAction, AuditEvent};
+use crate::domain::audit::service::AuditService;
+use crate::domain::error::AppError;
+use crate::domain::user::service::{revoke_sessions, IdentityCache};
+use crate::settings::Auth as AuthSettings;
+use crate::utils::client_info::ClientInfo;
+use crate::utils::password::PasswordUtil;
+use crate::utils::totp::{
+    generate_recovery_codes, generate_secret, hash_recovery_code, otpauth_uri, verify_code,
+};
+use serde_json::json;
+use std::sync::Arc;
+use subtle::ConstantTimeEq;
+use validator::Validate;
+
+// Number of recovery codes handed out at a time
+const RECOVERY_CODE_COUNT: usize = 10;
+
+/// MfaService handles TOTP enrollment, second-factor checks and per-role MFA policies
+#[derive(Clone)]
+pub struct MfaService {
+    db: DatabaseConnection,
+    auth_settings: AuthSettings,
+    identity_cache: Arc<IdentityCache>,
+    password_util: PasswordUtil,
+    audit_service: AuditService,
+}
+
+impl MfaService {
+    /// Create a new MfaService instance
+    pub fn new(
+        db: DatabaseConnection,
+        auth_settings: AuthSettings,
+        identity_cache: Arc<IdentityCache>,
+        password_util: PasswordUtil,
+        audit_service: AuditService,
+    ) -> Self {
+        Self {
+            db,
+            auth_settings,
+            identity_cache,
+            password_util,
+            audit_service,
+        }
+    }
+
+    /// Two-factor authentication state of a user
+    pub async fn status(&self, user_model: &UserModel) -> Result<MfaStatusResponse, AppError> {
+        let totp_enabled = self.is_enrolled(user_model.id).await?;
+        let required = self.is_required(user_model.role).await?;
+
+        let recovery_codes_remaining = RecoveryCodes::find()
+            .filter(recovery_codes::Column::UserId.eq(user_model.id))
+            .filter(recovery_codes::Column::UsedAt.is_null())
+            .count(&self.db)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Ok(MfaStatusResponse {
+            totp_enabled,
+            required,
+            recovery_codes_remaining,
+        })
+    }
+
+    /// Start TOTP enrollment with a new secret
+    ///
+    /// The secret is not used for login until it is confirmed with a code.
+    pub async fn enroll_totp(
+        &self,
+        user_model: &UserModel,
+    ) -> Result<TotpEnrollmentResponse, AppError> {
+        if self.is_enrolled(user_model.id).await? {
+            return Err(AppError::Conflict(
+                "Two-factor authentication is already enabled".to_string(),
+            ));
+        }
+
+        let secret = generate_secret();
+        let account_name = user_model
+            .email
+            .clone()
+            .or_else(|| user_model.username.clone())
+            .unwrap_or_else(|| user_model.id.to_string());
+        let otpauth_uri = otpauth_uri(&secret, &self.auth_settings.mfa_issuer, &account_name)
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        // Replace any earlier unconfirmed secret
+        let now = now();
+        let totp_model = user_totp::ActiveModel {
+            user_id: Set(user_model.id),
+            secret: Set(secret.clone()),
+            confirmed_at: Set(None),
+            last_used_step: Set(None),
+            created: Set(now),
+            updated: Set(now),
+        };
+
+        UserTotp::insert(totp_model)
+            .on_conflict(
+                OnConflict::column(user_totp::Column::UserId)
+                    .update_columns([
+                        user_totp::Column::Secret,
+                        user_totp::Column::ConfirmedAt,
+                        user_totp::Column::LastUsedStep,
+                        user_totp::Column::Updated,
+                    ])
+                    .to_owned(),
+            )
+            .exec(&self.db)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Ok(TotpEnrollmentResponse {
+            secret,
+            otpauth_uri,
+        })
+    }
+
+    /// Confirm TOTP enrollment with a code and hand out recovery codes
+    ///
+    /// The session the code was entered in counts as verified with the second factor.
+    pub async fn confirm_totp(
+        &self,
+        user_model: &UserModel,
+        session_id: uuid::Uuid,
+        code_req: TotpCodeRequest,
+        client: &ClientInfo,
+    ) -> Result<RecoveryCodesResponse, AppError> {
+        // Validate input
+        code_req.validate().map_err(AppError::from)?;
+
+        let txn = self
+            .db
+            .begin()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let totp_model = UserTotp::find_by_id(user_model.id)
+            .lock_exclusive()
+            .one(&txn)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .ok_or_else(|| {
+                AppError::NotFound("Two-factor authentication enrollment not started".to_string())
+            })?;
+
+        if totp_model.confirmed_at.is_some() {
+            return Err(AppError::Conflict(
+                "Two-factor authentication is already enabled".to_string(),
+            ));
+        }
+
+        let step = verify_code(&totp_model.secret, &code_req.code, unix_time())
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .ok_or_else(|| AppError::Unauthorized("Invalid authentication code".to_string()))?;
+
+        let now = now();
+        let mut active_model: user_totp::ActiveModel = totp_model.into();
+        active_model.confirmed_at = Set(Some(now));
+        active_model.last_used_step = Set(Some(step as i64));
+        active_model.updated = Set(now);
+        active_model
+            .update(&txn)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Sessions::update_many()
+            .col_expr(sessions::Column::MfaVerifiedAt, Expr::value(now))
+            .filter(sessions::Column::Id.eq(session_id))
+            .filter(sessions::Column::UserId.eq(user_model.id))
+            .exec(&txn)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let recovery_codes = self.replace_recovery_codes(&txn, user_model.id).await?;
+        self.audit_service
+            .record(
+                &txn,
+                AuditEvent::user(AuditAction::MfaEnable, user_model.id)
+                    .actor(user_model.id)
+                    .client(client),
+            )
+            .await?;
+
+        txn.commit()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        self.identity_cache.remove(&session_id);
+
+        Ok(RecoveryCodesResponse { recovery_codes })
+    }
+
+    /// Turn off two-factor authentication after checking the password, and log out the
+    /// other sessions of the user
+    pub async fn disable_totp(
+        &self,
+        user_model: &UserModel,
+        session_id: uuid::Uuid,
+        disable_req: DisableTotpRequest,
+        client: &ClientInfo,
+    ) -> Result<(), AppError> {
+        // Validate input
+        disable_req.validate().map_err(AppError::from)?;
+
+        // Verify password
+        if !self
+            .password_util
+            .verify_password(&disable_req.password, &user_model.password_hash)
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .valid
+        {
+            return Err(AppError::InvalidPassword(
+                "Password is incorrect".to_string(),
+            ));
+        }
+
+        if self.is_required(user_model.role).await? {
+            return Err(AppError::Forbidden(
+                "Two-factor authentication is required for your role".to_string(),
+            ));
+        }
+
+        let txn = self
+            .db
+            .begin()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        UserTotp::delete_by_id(user_model.id)
+            .exec(&txn)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        RecoveryCodes::delete_many()
+            .filter(recovery_codes::Column::UserId.eq(user_model.id))
+            .exec(&txn)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let revoked = revoke_sessions(
+            &txn,
+            Condition::all()
+                .add(sessions::Column::UserId.eq(user_model.id))
+                .add(sessions::Column::Id.ne(session_id)),
+        )
+        .await?;
+        self.audit_service
+            .record(
+                &txn,
+                AuditEvent::user(AuditAction::MfaDisable, user_model.id)
+                    .actor(user_model.id)
+                    .client(client),
+            )
+            .await?;
+
+        txn.commit()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        for session_id in revoked {
+            self.identity_cache.remove(&session_id);
+        }
+
+        Ok(())
+    }
+
+    /// Replace the recovery codes of a user, after checking a code from the authenticator app
+    pub async fn regenerate_recovery_codes(
+        &self,
+        user_model: &UserModel,
+        code_req: TotpCodeRequest,
+        client: &ClientInfo,
+    ) -> Result<RecoveryCodesResponse, AppError> {
+        // Validate input
+        code_req.validate().map_err(AppError::from)?;
+
+        let txn = self
+            .db
+            .begin()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        if !self
+            .verify_totp_code(&txn, user_model.id, &code_req.code)
+            .await?
+        {
+            return Err(AppError::Unauthorized(
+                "Invalid authentication code".to_string(),
+            ));
+        }
+
+        let recovery_codes = self.replace_recovery_codes(&txn, user_model.id).await?;
+        self.audit_service
+            .record(
+                &txn,
+                AuditEvent::user(AuditAction::RecoveryCodesRegenerate, user_model.id)
+                    .actor(user_model.id)
+                    .client(client),
+            )
+            .await?;
+
+        txn.commit()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Ok(RecoveryCodesResponse { recovery_codes })
+    }
+
+    /// Whether the user has confirmed a TOTP secret
+    pub async fn is_enrolled(&self, user_id: uuid::Uuid) -> Result<bool, AppError> {
+        let totp_model = UserTotp::find_by_id(user_id)
+            .one(&self.db)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Ok(totp_model.is_some_and(|totp| totp.confirmed_at.is_some()))
+    }
+
+    /// Whether members of a role must use two-factor authentication
+    pub async fn is_required(&self, role: Role) -> Result<bool, AppError> {
+        let policy = RoleMfaPolicies::find_by_id(role)
+            .one(&self.db)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Ok(policy.is_some_and(|policy| policy.required))
+    }
+
+    /// Check a second factor: a code from the authenticator app, or an unused recovery code.
+    /// A matching recovery code is used up.
+    pub async fn verify_second_factor(
+        &self,
+        user_id: uuid::Uuid,
+        code: &str,
+    ) -> Result<bool, AppError> {
+        let txn = self
+            .db
+            .begin()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let verified = if self.verify_totp_code(&txn, user_id, code).await? {
+            true
+        } else {
+            self.use_recovery_code(&txn, user_id, code).await?
+        };
+
+        txn.commit()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Ok(verified)
+    }
+
+    /// Whether each role must use two-factor authentication
+    pub async fn list_policies(&self) -> Result<Vec<MfaPolicyInfo>, AppError> {
+        let policies = RoleMfaPolicies::find()
+            .all(&self.db)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Ok(Role::ALL
+            .into_iter()
+            .map(|role| MfaPolicyInfo {
+                role,
+                required: policies
+                    .iter()
+                    .any(|policy| policy.role == role && policy.required),
+            })
+            .collect())
+    }
+
+    /// Require or stop requiring two-factor authentication for a role (admin action)
+    pub async fn set_policy(
+        &self,
+        admin_id: uuid::Uuid,
+        role: &str,
+        update_req: UpdateMfaPolicyRequest,
+        client: &ClientInfo,
+    ) -> Result<MfaPolicyInfo, AppError> {
+        let role = Role::parse(role)
+            .ok_or_else(|| AppError::validation(&format!("Unknown role '{}'", role)))?;
+
+        let txn = self
+            .db
+            .begin()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let required_before = RoleMfaPolicies::find_by_id(role)
+            .lock_exclusive()
+            .one(&txn)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .is_some_and(|policy| policy.required);
+
+        let policy_model = role_mfa_policies::ActiveModel {
+            role: Set(role),
+            required: Set(update_req.required),
+            updated: Set(now()),
+        };
+
+        RoleMfaPolicies::insert(policy_model)
+            .on_conflict(
+                OnConflict::column(role_mfa_policies::Column::Role)
+                    .update_columns([
+                        role_mfa_policies::Column::Required,
+                        role_mfa_policies::Column::Updated,
+                    ])
+                    .to_owned(),
+            )
+            .exec(&txn)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        self.audit_service
+            .record(
+                &txn,
+                AuditEvent::new(
+                    AuditAction::MfaPolicyChange,
+                    "role_mfa_policies",
+                    Some(role.as_str().to_string()),
+                )
+                .actor(admin_id)
+                .client(client)
+                .changes(
+                    Some(&json!({ "required": required_before })),
+                    Some(&json!({ "required": update_req.required })),
+                ),
+            )
+            .await?;
+
+        txn.commit()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        // Every cached session may now have a different pending state
+        self.identity_cache.clear();
+
+        Ok(MfaPolicyInfo {
+            role,
+            required: update_req.required,
+        })
+    }
+
+    /// Check a code against the confirmed TOTP secret, rejecting a code whose
+    /// time step was already used
+    async fn verify_totp_code<C: ConnectionTrait>(
+        &self,
+        conn: &C,
+        user_id: uuid::Uuid,
+        code: &str,
+    ) -> Result<bool, AppError> {
+        let totp_model = UserTotp::find_by_id(user_id)
+            .lock_exclusive()
+            .one(conn)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .filter(|totp| totp.confirmed_at.is_some());
+
+        let Some(totp_model) = totp_model else {
+            return Ok(false);
+        };
+
+        let step = verify_code(&totp_model.secret, code, unix_time())
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .map(|step| step as i64)
+            .filter(|step| totp_model.last_used_step.is_none_or(|last| *step > last));
+
+        let Some(step) = step else {
+            return Ok(false);
+        };
+
+        let mut active_model: user_totp::ActiveModel = totp_model.into();
+        active_model.last_used_step = Set(Some(step));
+        active_model.updated = Set(now());
+        active_model
+            .update(conn)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Ok(true)
+    }
+
+    /// Use up an unused recovery code of the user matching the given code. The hashes
+    /// are compared here in constant time, rather than looked up by the database.
+    async fn use_recovery_code<C: ConnectionTrait>(
+        &self,
+        conn: &C,
+        user_id: uuid::Uuid,
+        code: &str,
+    ) -> Result<bool, AppError> {
+        let code_hash = hash_recovery_code(code);
+
+        let code_models = RecoveryCodes::find()
+            .filter(recovery_codes::Column::UserId.eq(user_id))
+            .filter(recovery_codes::Column::UsedAt.is_null())
+            .lock_exclusive()
+            .all(conn)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let Some(code_model) = code_models.into_iter().find(|code_model| {
+            code_model
+                .code_hash
+                .as_bytes()
+                .ct_eq(code_hash.as_bytes())
+                .into()
+        }) else {
+            return Ok(false);
+        };
+
+        let mut active_model: recovery_codes::ActiveModel = code_model.into();
+        active_model.used_at = Set(Some(now()));
+        active_model
+            .update(conn)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Ok(true)
+    }
+
+    /// Delete the recovery codes of a user and create a new set.
+    /// Returns the plain codes; only their hashes are stored.
+    async fn replace_recovery_codes<C: ConnectionTrait>(
+        &self,
+        conn: &C,
+        user_id: uuid::Uuid,
+    ) -> Result<Vec<String>, AppError> {
+        RecoveryCodes::delete_many()
+            .filter(recovery_codes::Column::UserId.eq(user_id))
+            .exec(conn)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let now = now();
+        let codes = generate_recovery_codes(RECOVERY_CODE_COUNT);
+        let code_models = codes.iter().map(|code| recovery_codes::ActiveModel {
+            id: Set(uuid::Uuid::now_v7()),
+            user_id: Set(user_id),
+            code_hash: Set(hash_recovery_code(code)),
+            used_at: Set(None),
+            created: Set(now),
+        });
+
+        RecoveryCodes::insert_many(code_models)
+            .exec(conn)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Ok(codes)
+    }
+}
+
+fn now() -> chrono::DateTime<chrono::FixedOffset> {
+    chrono::Utc::now().with_timezone(&chrono::FixedOffset::east_opt(0).unwrap())
+}
+
+fn unix_time() -> u64 {
+    chrono::Utc::now().timestamp() as u64
+}
@@ -1,5 +1,11 @@
 pub mod admin;
+pub mod api_key;
+pub mod audit;
+pub mod crud;
 pub mod error;
 pub mod healthcheck;
+pub mod jwks;
+pub mod mfa;
+pub mod oidc;
 pub mod response;
 pub mod user;
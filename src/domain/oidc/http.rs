@@ -0,0 +1,62 @@
+use axum::{
+    extract::{Path, State},
+    middleware,
+    response::IntoResponse,
+    routing::{get, post},
+    Json, Router,
+};
+use axum_extra::extract::cookie::CookieJar;
+use std::sync::Arc;
+
+use super::model::OidcCallbackRequest;
+use crate::domain::error::ToResponse;
+use crate::domain::response::Response;
+use crate::domain::user::http::login_response;
+use crate::middleware::rate_limit;
+use crate::state::AppState;
+use crate::utils::client_info::ClientInfo;
+use crate::utils::rate_limit::RateLimitScope;
+
+/// List the providers users can sign in with
+pub async fn list_providers(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    Response::success_ok(
+        state.oidc_service.list_providers(),
+        "Providers retrieved successfully",
+    )
+}
+
+/// Start a login at a provider
+pub async fn authorize(
+    State(state): State<Arc<AppState>>,
+    Path(provider): Path<String>,
+) -> impl IntoResponse {
+    state
+        .oidc_service
+        .authorize(&provider)
+        .await
+        .to_response("Authorization URL created successfully")
+}
+
+/// Finish a login at a provider
+pub async fn callback(
+    State(state): State<Arc<AppState>>,
+    Path(provider): Path<String>,
+    jar: CookieJar,
+    client: ClientInfo,
+    Json(req): Json<OidcCallbackRequest>,
+) -> impl IntoResponse {
+    let result = state.oidc_service.callback(&provider, req, &client).await;
+    login_response(&state, jar, result)
+}
+
+/// OIDC Router (public routes, nested under `/auth/oidc`)
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/providers", get(list_providers))
+        .route("/{provider}/authorize", get(authorize))
+        .route("/{provider}/callback", post(callback))
+        .route_layer(middleware::from_fn_with_state(
+            RateLimitScope::Auth,
+            rate_limit,
+        ))
+}
@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+// ============= OIDC Request DTOs =============
+
+/// Request body with the parameters the provider redirected back with
+#[derive(Debug, Deserialize, Validate)]
+pub struct OidcCallbackRequest {
+    #[validate(length(min = 1, message = "Code cannot be empty"))]
+    pub code: String,
+    #[validate(length(min = 1, message = "State cannot be empty"))]
+    pub state: String,
+}
+
+// ============= OIDC Response DTOs =============
+
+/// Provider users can sign in with
+#[derive(Debug, Serialize)]
+pub struct OidcProviderInfo {
+    pub name: String,
+    pub display_name: String,
+}
+
+/// Provider page to send the browser to
+#[derive(Debug, Serialize)]
+pub struct OidcAuthorizationResponse {
+    pub authorization_url: String,
+}
+
+// ============= Provider DTOs =============
+
+/// Endpoints from the provider's discovery document
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+}
+
+/// Token endpoint response (only the ID token is used)
+#[derive(Debug, Deserialize)]
+pub struct ProviderTokenResponse {
+    pub id_token: String,
+}
+
+/// Claims of a provider's ID token
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdTokenClaims {
+    pub sub: String,
+    pub email: Option<String>,
+    #[serde(default)]
+    pub email_verified: bool,
+    pub name: Option<String>,
+    pub nonce: Option<String>,
+}
@@ -0,0 +1,445 @@
+// Import Domain
+use crate::entities::identities::{self, Entity as Identities};
+use crate::entities::oidc_login_states::{self, Entity as OidcLoginStates};
+use crate::entities::users::{self, Entity as Users, Model as UserModel};
+use sea_orm::*;
+
+// Import Dtos
+use super::model::{
+    IdTokenClaims, OidcAuthorizationResponse, OidcCallbackRequest, OidcProviderInfo,
+    ProviderMetadata, ProviderTokenResponse,
+};
+use crate::domain::user::model::{LoginResponse, Role, UserStatus};
+
+// Import Utils
+use crate::domain::error::AppError;
+use crate::domain::user::service::UserService;
+use crate::settings::{Oidc as OidcSettings, OidcProvider};
+use crate::utils::client_info::ClientInfo;
+use crate::utils::password::PasswordUtil;
+use crate::utils::token::{generate_token, hash_token};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use validator::Validate;
+
+const STATE_LENGTH: usize = 32;
+const NONCE_LENGTH: usize = 32;
+const CODE_VERIFIER_LENGTH: usize = 64;
+
+// Accounts created through a provider get a random password they never see
+const UNUSABLE_PASSWORD_LENGTH: usize = 48;
+
+const PROVIDER_REQUEST_TIMEOUT_SECONDS: u64 = 10;
+
+/// OidcService handles social login with OpenID Connect providers
+/// (authorization code flow with PKCE)
+#[derive(Clone)]
+pub struct OidcService {
+    db: DatabaseConnection,
+    oidc_settings: OidcSettings,
+    user_service: UserService,
+    password_util: PasswordUtil,
+    http: reqwest::Client,
+    metadata: Arc<RwLock<HashMap<String, ProviderMetadata>>>, // Discovery documents by provider
+}
+
+impl OidcService {
+    /// Create a new OidcService instance
+    pub fn new(
+        db: DatabaseConnection,
+        oidc_settings: OidcSettings,
+        user_service: UserService,
+        password_util: PasswordUtil,
+    ) -> anyhow::Result<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(PROVIDER_REQUEST_TIMEOUT_SECONDS))
+            .build()?;
+
+        Ok(Self {
+            db,
+            oidc_settings,
+            user_service,
+            password_util,
+            http,
+            metadata: Arc::new(RwLock::new(HashMap::new())),
+        })
+    }
+
+    /// List the configured providers
+    pub fn list_providers(&self) -> Vec<OidcProviderInfo> {
+        let mut providers: Vec<OidcProviderInfo> = self
+            .oidc_settings
+            .providers
+            .iter()
+            .map(|(name, provider)| OidcProviderInfo {
+                name: name.clone(),
+                display_name: provider
+                    .display_name
+                    .clone()
+                    .unwrap_or_else(|| name.clone()),
+            })
+            .collect();
+        providers.sort_by(|a, b| a.name.cmp(&b.name));
+        providers
+    }
+
+    /// Start a login: remember the state, nonce and PKCE verifier, and build the
+    /// provider URL to send the browser to
+    pub async fn authorize(
+        &self,
+        provider_name: &str,
+    ) -> Result<OidcAuthorizationResponse, AppError> {
+        let provider = self.provider(provider_name)?;
+        let metadata = self.metadata(provider_name, provider).await?;
+
+        let state = generate_token(STATE_LENGTH);
+        let nonce = generate_token(NONCE_LENGTH);
+        let code_verifier = generate_token(CODE_VERIFIER_LENGTH);
+        let code_challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()));
+
+        // Forget logins that were never completed
+        let now = now();
+        OidcLoginStates::delete_many()
+            .filter(oidc_login_states::Column::ExpiresAt.lt(now))
+            .exec(&self.db)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let state_model = oidc_login_states::ActiveModel {
+            state_hash: Set(hash_token(&state)),
+            provider: Set(provider_name.to_string()),
+            code_verifier: Set(code_verifier),
+            nonce: Set(nonce.clone()),
+            expires_at: Set(
+                now + chrono::Duration::minutes(self.oidc_settings.state_expiration_minutes)
+            ),
+            created: Set(now),
+        };
+
+        state_model
+            .insert(&self.db)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let authorization_url = reqwest::Url::parse_with_params(
+            &metadata.authorization_endpoint,
+            &[
+                ("response_type", "code"),
+                ("client_id", provider.client_id.as_str()),
+                ("redirect_uri", provider.redirect_uri.as_str()),
+                ("scope", provider.scopes.as_str()),
+                ("state", state.as_str()),
+                ("nonce", nonce.as_str()),
+                ("code_challenge", code_challenge.as_str()),
+                ("code_challenge_method", "S256"),
+            ],
+        )
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Ok(OidcAuthorizationResponse {
+            authorization_url: authorization_url.to_string(),
+        })
+    }
+
+    /// Finish a login with the code the provider redirected back with
+    ///
+    /// The external identity is linked to a user, creating one on first login.
+    /// Users with two-factor authentication still have to enter their code.
+    pub async fn callback(
+        &self,
+        provider_name: &str,
+        callback_req: OidcCallbackRequest,
+        client: &ClientInfo,
+    ) -> Result<LoginResponse, AppError> {
+        // Validate input
+        callback_req.validate().map_err(AppError::from)?;
+
+        let provider = self.provider(provider_name)?;
+
+        // Consume the state so the same code cannot be redeemed twice
+        let login_state = OidcLoginStates::find_by_id(hash_token(&callback_req.state))
+            .one(&self.db)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .ok_or_else(invalid_state)?;
+
+        let deleted = OidcLoginStates::delete_by_id(login_state.state_hash.clone())
+            .exec(&self.db)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        if deleted.rows_affected == 0
+            || login_state.provider != provider_name
+            || login_state.expires_at <= now()
+        {
+            return Err(invalid_state());
+        }
+
+        // Exchange the code for an ID token
+        let metadata = self.metadata(provider_name, provider).await?;
+        let response = self
+            .http
+            .post(&metadata.token_endpoint)
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", callback_req.code.as_str()),
+                ("redirect_uri", provider.redirect_uri.as_str()),
+                ("client_id", provider.client_id.as_str()),
+                ("client_secret", provider.client_secret.as_str()),
+                ("code_verifier", login_state.code_verifier.as_str()),
+            ])
+            .send()
+            .await
+            .map_err(provider_error)?;
+
+        if response.status().is_client_error() {
+            return Err(AppError::Unauthorized(
+                "Authorization code was rejected by the provider".to_string(),
+            ));
+        }
+
+        let tokens: ProviderTokenResponse = response
+            .error_for_status()
+            .map_err(provider_error)?
+            .json()
+            .await
+            .map_err(provider_error)?;
+
+        let claims = self
+            .verify_id_token(provider, &metadata, &tokens.id_token)
+            .await?;
+
+        if claims.nonce.as_deref() != Some(login_state.nonce.as_str()) {
+            return Err(AppError::Unauthorized(
+                "ID token does not belong to this login".to_string(),
+            ));
+        }
+
+        let user_model = self
+            .find_or_create_user(provider_name, provider, &claims)
+            .await?;
+
+        self.user_service.complete_login(user_model, client).await
+    }
+
+    fn provider(&self, provider_name: &str) -> Result<&OidcProvider, AppError> {
+        self.oidc_settings
+            .providers
+            .get(provider_name)
+            .ok_or_else(|| AppError::NotFound(format!("Unknown provider '{}'", provider_name)))
+    }
+
+    /// Get the provider's discovery document, fetching it on first use
+    async fn metadata(
+        &self,
+        provider_name: &str,
+        provider: &OidcProvider,
+    ) -> Result<ProviderMetadata, AppError> {
+        if let Some(metadata) = self.metadata.read().unwrap().get(provider_name) {
+            return Ok(metadata.clone());
+        }
+
+        let url = format!(
+            "{}/.well-known/openid-configuration",
+            provider.issuer.trim_end_matches('/')
+        );
+        let metadata: ProviderMetadata = self.fetch_json(&url).await?;
+
+        self.metadata
+            .write()
+            .unwrap()
+            .insert(provider_name.to_string(), metadata.clone());
+
+        Ok(metadata)
+    }
+
+    /// Check the ID token signature, issuer, audience and expiry
+    async fn verify_id_token(
+        &self,
+        provider: &OidcProvider,
+        metadata: &ProviderMetadata,
+        id_token: &str,
+    ) -> Result<IdTokenClaims, AppError> {
+        let invalid_token =
+            |_| AppError::Unauthorized("Invalid ID token from the provider".to_string());
+
+        let header = decode_header(id_token).map_err(invalid_token)?;
+        let decoding_key = match header.alg {
+            // Symmetric ID tokens are signed with the client secret
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => {
+                DecodingKey::from_secret(provider.client_secret.as_bytes())
+            }
+            _ => {
+                let jwks: JwkSet = self.fetch_json(&metadata.jwks_uri).await?;
+                let jwk = match &header.kid {
+                    Some(kid) => jwks.find(kid),
+                    None => jwks.keys.first(),
+                }
+                .ok_or_else(|| {
+                    AppError::Unauthorized("ID token signed with an unknown key".to_string())
+                })?;
+                DecodingKey::from_jwk(jwk).map_err(invalid_token)?
+            }
+        };
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&metadata.issuer]);
+        validation.set_audience(&[&provider.client_id]);
+
+        decode::<IdTokenClaims>(id_token, &decoding_key, &validation)
+            .map(|token_data| token_data.claims)
+            .map_err(invalid_token)
+    }
+
+    /// Find the user linked to an external identity, linking or creating one on first login
+    async fn find_or_create_user(
+        &self,
+        provider_name: &str,
+        provider: &OidcProvider,
+        claims: &IdTokenClaims,
+    ) -> Result<UserModel, AppError> {
+        let now = now();
+
+        // Returning user
+        let identity = Identities::find()
+            .filter(identities::Column::Provider.eq(provider_name))
+            .filter(identities::Column::Subject.eq(&claims.sub))
+            .one(&self.db)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        if let Some(identity) = identity {
+            let user_model = Users::find_by_id(identity.user_id)
+                .one(&self.db)
+                .await
+                .map_err(|e| AppError::Internal(e.to_string()))?
+                .ok_or_else(|| AppError::Unauthorized("User not found".to_string()))?;
+
+            let mut active_model: identities::ActiveModel = identity.into();
+            active_model.email = Set(claims.email.clone());
+            active_model.last_login = Set(Some(now));
+            active_model
+                .update(&self.db)
+                .await
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+
+            return Ok(user_model);
+        }
+
+        // Only addresses the provider has verified are trusted
+        let email = claims.email.clone().filter(|_| claims.email_verified);
+        let existing_user = match &email {
+            Some(email) => Users::find()
+                .filter(users::Column::Email.eq(email))
+                .one(&self.db)
+                .await
+                .map_err(|e| AppError::Internal(e.to_string()))?,
+            None => None,
+        };
+
+        let txn = self
+            .db
+            .begin()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let user_model = match existing_user {
+            Some(user_model) if provider.link_verified_email => {
+                // The provider has just verified the address
+                if user_model.status == UserStatus::Pending {
+                    let mut active_model: users::ActiveModel = user_model.into();
+                    active_model.status = Set(UserStatus::Active);
+                    active_model.updated = Set(now);
+                    active_model
+                        .update(&txn)
+                        .await
+                        .map_err(|e| AppError::Internal(e.to_string()))?
+                } else {
+                    user_model
+                }
+            }
+            Some(_) => {
+                return Err(AppError::Conflict(
+                    "An account with this email address already exists, log in with its password"
+                        .to_string(),
+                ));
+            }
+            None => {
+                let password_hash = self
+                    .password_util
+                    .hash_password(&generate_token(UNUSABLE_PASSWORD_LENGTH))
+                    .map_err(|e| AppError::Internal(e.to_string()))?;
+
+                let active_model = users::ActiveModel {
+                    id: Set(uuid::Uuid::now_v7()),
+                    username: Set(None),
+                    email: Set(email),
+                    pending_email: Set(None),
+                    display_name: Set(claims.name.clone()),
+                    password_hash: Set(password_hash),
+                    role: Set(Role::Student),
+                    status: Set(UserStatus::Active),
+                    created: Set(now),
+                    updated: Set(now),
+                };
+
+                active_model
+                    .insert(&txn)
+                    .await
+                    .map_err(|e| AppError::Internal(e.to_string()))?
+            }
+        };
+
+        let identity_model = identities::ActiveModel {
+            id: Set(uuid::Uuid::now_v7()),
+            user_id: Set(user_model.id),
+            provider: Set(provider_name.to_string()),
+            subject: Set(claims.sub.clone()),
+            email: Set(claims.email.clone()),
+            last_login: Set(Some(now)),
+            created: Set(now),
+        };
+
+        identity_model
+            .insert(&txn)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        txn.commit()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Ok(user_model)
+    }
+
+    async fn fetch_json<T: DeserializeOwned>(&self, url: &str) -> Result<T, AppError> {
+        self.http
+            .get(url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(provider_error)?
+            .json()
+            .await
+            .map_err(provider_error)
+    }
+}
+
+fn invalid_state() -> AppError {
+    AppError::Unauthorized("Invalid or expired login state".to_string())
+}
+
+fn provider_error(err: reqwest::Error) -> AppError {
+    AppError::ExternalServiceError(format!("Identity provider request failed: {}", err))
+}
+
+fn now() -> chrono::DateTime<chrono::FixedOffset> {
+    chrono::Utc::now().with_timezone(&chrono::FixedOffset::east_opt(0).unwrap())
+}
@@ -10,6 +10,7 @@ pub enum ErrorType {
     Forbidden,
     Internal,
     Conflict,
+    TooManyRequests,
 }
 
 // SuccessType enum
@@ -37,6 +38,16 @@ pub struct Pagination {
     pub pages: u32,
 }
 
+// CursorPagination struct, for keyset pagination
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CursorPagination {
+    pub limit: u32,
+    pub next_cursor: Option<String>,
+    pub prev_cursor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<u64>, // Only counted when requested
+}
+
 // Response struct
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Response<T> {
@@ -48,7 +59,11 @@ pub struct Response<T> {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub pagination: Option<Pagination>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<CursorPagination>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>, // Structured error information, such as policy violations
 }
 
 #[allow(unused)]
@@ -60,7 +75,9 @@ impl<T> Response<T> {
             status: Status::Success(status),
             data: Some(data),
             pagination: None,
+            cursor: None,
             error: None,
+            details: None,
         }
     }
 
@@ -79,7 +96,9 @@ impl<T> Response<T> {
             status: Status::Success(SuccessType::NoContent),
             data: None,
             pagination: None,
+            cursor: None,
             error: None,
+            details: None,
         }
     }
 
@@ -90,10 +109,27 @@ impl<T> Response<T> {
             status: Status::Error(error_type),
             data: None,
             pagination: None,
+            cursor: None,
             error,
+            details: None,
         }
     }
 
+    pub fn with_details(mut self, details: Option<serde_json::Value>) -> Self {
+        self.details = details;
+        self
+    }
+
+    pub fn with_pagination(mut self, pagination: Option<Pagination>) -> Self {
+        self.pagination = pagination;
+        self
+    }
+
+    pub fn with_cursor(mut self, cursor: Option<CursorPagination>) -> Self {
+        self.cursor = cursor;
+        self
+    }
+
     pub fn failure_validation(message: &str, error: Option<String>) -> Self {
         Self::failure(message, ErrorType::Validation, error)
     }
@@ -117,6 +153,10 @@ impl<T> Response<T> {
     pub fn failure_conflict(message: &str, error: Option<String>) -> Self {
         Self::failure(message, ErrorType::Conflict, error)
     }
+
+    pub fn failure_too_many_requests(message: &str, error: Option<String>) -> Self {
+        Self::failure(message, ErrorType::TooManyRequests, error)
+    }
 }
 
 impl<T: Serialize> IntoResponse for Response<T> {
@@ -130,6 +170,7 @@ impl<T: Serialize> IntoResponse for Response<T> {
                 Status::Error(ErrorType::Forbidden) => StatusCode::FORBIDDEN,
                 Status::Error(ErrorType::NotFound) => StatusCode::NOT_FOUND,
                 Status::Error(ErrorType::Conflict) => StatusCode::CONFLICT,
+                Status::Error(ErrorType::TooManyRequests) => StatusCode::TOO_MANY_REQUESTS,
                 Status::Error(ErrorType::Internal) => StatusCode::INTERNAL_SERVER_ERROR,
                 _ => StatusCode::INTERNAL_SERVER_ERROR,
             }
@@ -0,0 +1,99 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+use crate::domain::crud::{AuditHooks, CrudContext, CrudHooks};
+use crate::domain::error::AppError;
+use crate::entities::sea_orm_active_enums::{UserRole, UserStatus};
+use crate::entities::users::{Entity as Users, Model as UserModel};
+use crate::utils::password_policy::PersonalInfo;
+
+/// Hooks of the admin CRUD routes for users: passwords are checked against the policy
+/// and hashed, accounts created by an admin start out as active students, changes are
+/// audited, and cached identities of changed users are dropped
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UserHooks;
+
+#[async_trait]
+impl CrudHooks<Users> for UserHooks {
+    async fn before_create(
+        &self,
+        ctx: &CrudContext<'_>,
+        payload: &mut Value,
+    ) -> Result<(), AppError> {
+        // Role and status are read-only; changing them takes the admin actions
+        if let Some(obj) = payload.as_object_mut() {
+            obj.insert("role".to_string(), json!(UserRole::Student));
+            obj.insert("status".to_string(), json!(UserStatus::Active));
+        }
+        hash_password(ctx, payload, None)
+    }
+
+    async fn after_create(&self, ctx: &CrudContext<'_>, model: &UserModel) -> Result<(), AppError> {
+        <AuditHooks as CrudHooks<Users>>::after_create(&AuditHooks, ctx, model).await
+    }
+
+    async fn before_update(
+        &self,
+        ctx: &CrudContext<'_>,
+        current: &UserModel,
+        payload: &mut Value,
+    ) -> Result<(), AppError> {
+        hash_password(ctx, payload, Some(&current.password_hash))
+    }
+
+    async fn after_update(
+        &self,
+        ctx: &CrudContext<'_>,
+        before: &UserModel,
+        after: &UserModel,
+    ) -> Result<(), AppError> {
+        let user_id = after.id;
+        ctx.after_commit(move |state| state.user_service.forget_user(user_id));
+        <AuditHooks as CrudHooks<Users>>::after_update(&AuditHooks, ctx, before, after).await
+    }
+
+    async fn after_delete(&self, ctx: &CrudContext<'_>, model: &UserModel) -> Result<(), AppError> {
+        let user_id = model.id;
+        ctx.after_commit(move |state| state.user_service.forget_user(user_id));
+        <AuditHooks as CrudHooks<Users>>::after_delete(&AuditHooks, ctx, model).await
+    }
+}
+
+// Check a new password in the request body against the policy and replace it with its
+// hash. The value is always taken as a plain password; only the current hash, which
+// updates carry when no new password is given, is kept as it is.
+fn hash_password(
+    ctx: &CrudContext<'_>,
+    payload: &mut Value,
+    current_hash: Option<&str>,
+) -> Result<(), AppError> {
+    let Some(obj) = payload.as_object_mut() else {
+        return Ok(());
+    };
+    let Some(password) = obj.get("password_hash").and_then(Value::as_str) else {
+        return Ok(());
+    };
+    if current_hash == Some(password) {
+        return Ok(());
+    }
+
+    // Check the password against the policy, with the account details it is set with
+    let personal_info = PersonalInfo {
+        username: obj.get("username").and_then(Value::as_str),
+        email: obj.get("email").and_then(Value::as_str),
+    };
+    ctx.state
+        .password_util
+        .policy()
+        .check(password, personal_info)
+        .map_err(AppError::PasswordPolicy)?;
+
+    let hashed = ctx
+        .state
+        .password_util
+        .hash_password(password)
+        .map_err(|e| AppError::Internal(format!("Failed to hash password: {}", e)))?;
+    obj.insert("password_hash".to_string(), Value::String(hashed));
+
+    Ok(())
+}
@@ -1,62 +1,506 @@
 use axum::{
-    extract::State,
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    middleware,
     response::IntoResponse,
-    routing::{get, post},
+    routing::{delete, get, patch, post, put},
     Extension, Json, Router,
 };
+use axum_extra::extract::cookie::CookieJar;
 use std::sync::Arc;
 
-use super::model::{LoginRequest, RefreshTokenRequest, RegisterRequest};
-use crate::domain::error::ToResponse;
+use super::model::{
+    AdminActionRequest, AuthResponse, ChangePasswordRequest, ChangeRoleRequest, CurrentSession,
+    ForgotPasswordRequest, LoginRequest, LoginResponse, MagicLinkRequest, MfaVerifyRequest,
+    RefreshTokenRequest, RegisterRequest, ResendVerificationRequest, ResetPasswordRequest,
+    UpdateProfileRequest, UserInfo, VerifyEmailRequest,
+};
+use crate::domain::error::{AppError, ToResponse};
+use crate::domain::response::Response;
 use crate::entities::users::Model as User;
+use crate::middleware::{forbid_impersonation, rate_limit};
+use crate::settings::CookieMode;
 use crate::state::AppState;
+use crate::utils::client_info::ClientInfo;
+use crate::utils::cookies::{
+    clear_token_cookies, refresh_token_cookie, set_token_cookies, verify_csrf,
+};
+use crate::utils::rate_limit::RateLimitScope;
 
 /// Register a new user
 pub async fn register(
     State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    client: ClientInfo,
     Json(req): Json<RegisterRequest>,
 ) -> impl IntoResponse {
-    state
-        .user_service
-        .register(req)
-        .await
-        .to_response_created("User registered successfully")
+    let result = state.user_service.register(req, &client).await;
+    let (jar, result) = with_token_cookies(&state, jar, result);
+    (
+        jar,
+        result.to_response_created("User registered successfully"),
+    )
 }
 
 /// Login user
 pub async fn login(
     State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    client: ClientInfo,
     Json(req): Json<LoginRequest>,
+) -> impl IntoResponse {
+    let result = state.user_service.login(req, &client).await;
+    login_response(&state, jar, result)
+}
+
+/// Complete login with a second factor
+pub async fn verify_mfa(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    client: ClientInfo,
+    Json(req): Json<MfaVerifyRequest>,
+) -> impl IntoResponse {
+    let result = state.user_service.verify_mfa(req, &client).await;
+    let (jar, result) = with_token_cookies(&state, jar, result);
+    (jar, result.to_response("Login successful"))
+}
+
+/// Rotate refresh token and issue new tokens
+///
+/// In cookie mode the body may be omitted; the refresh token is then read from its
+/// cookie and the CSRF header is required.
+pub async fn refresh_token(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    client: ClientInfo,
+    body: Option<Json<RefreshTokenRequest>>,
+) -> impl IntoResponse {
+    let cookie_settings = &state.settings.cookies;
+    let mut req = body.map(|Json(req)| req).unwrap_or(RefreshTokenRequest {
+        token: String::new(),
+    });
+
+    if req.token.is_empty() && cookie_settings.mode != CookieMode::Off {
+        if let Some(token) = refresh_token_cookie(cookie_settings, &jar) {
+            if !verify_csrf(cookie_settings, &jar, &headers) {
+                return (
+                    jar,
+                    Response::<()>::failure_forbidden(
+                        "Access forbidden",
+                        Some("CSRF token missing or invalid".to_string()),
+                    ),
+                )
+                    .into_response();
+            }
+            req.token = token;
+        }
+    }
+
+    let result = state.user_service.refresh_token(req, &client).await;
+    let (jar, result) = with_token_cookies(&state, jar, result);
+    (jar, result.to_response("Token refreshed successfully")).into_response()
+}
+
+/// Verify email address (token in the request body)
+pub async fn verify_email(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<VerifyEmailRequest>,
 ) -> impl IntoResponse {
     state
         .user_service
-        .login(req)
+        .verify_email(req)
         .await
-        .to_response("Login successful")
+        .to_response("Email verified successfully")
 }
 
-/// Refresh access token
-pub async fn refresh_token(
+/// Verify email address (token in the query string, for links opened directly)
+pub async fn verify_email_link(
+    State(state): State<Arc<AppState>>,
+    Query(req): Query<VerifyEmailRequest>,
+) -> impl IntoResponse {
+    state
+        .user_service
+        .verify_email(req)
+        .await
+        .to_response("Email verified successfully")
+}
+
+/// Email a passwordless login link
+pub async fn request_magic_link(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<MagicLinkRequest>,
+) -> impl IntoResponse {
+    state
+        .user_service
+        .request_magic_link(req)
+        .await
+        .to_response_no_content("If the email is registered, a login link has been sent")
+}
+
+/// Log in with the token from a magic link email
+pub async fn magic_link_login(
+    State(state): State<Arc<AppState>>,
+    Path(token): Path<String>,
+    jar: CookieJar,
+    client: ClientInfo,
+) -> impl IntoResponse {
+    let result = state
+        .user_service
+        .login_with_magic_link(&token, &client)
+        .await;
+    login_response(&state, jar, result)
+}
+
+/// Resend the verification email
+pub async fn resend_verification(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<RefreshTokenRequest>,
+    Json(req): Json<ResendVerificationRequest>,
 ) -> impl IntoResponse {
     state
         .user_service
-        .refresh_token(req)
+        .resend_verification_email(req)
         .await
-        .to_response("Token refreshed successfully")
+        .to_response_no_content(
+            "If the account exists and is pending, a verification email has been sent",
+        )
+}
+
+/// Request a password reset email
+pub async fn forgot_password(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<ForgotPasswordRequest>,
+) -> impl IntoResponse {
+    state
+        .user_service
+        .forgot_password(req)
+        .await
+        .to_response_no_content("If the account exists, a password reset email has been sent")
+}
+
+/// Reset password with an emailed token
+pub async fn reset_password(
+    State(state): State<Arc<AppState>>,
+    client: ClientInfo,
+    Json(req): Json<ResetPasswordRequest>,
+) -> impl IntoResponse {
+    state
+        .user_service
+        .reset_password(req, &client)
+        .await
+        .to_response_no_content("Password reset successfully")
 }
 
 /// Get current user profile
 pub async fn me(Extension(user): Extension<User>) -> impl IntoResponse {
-    crate::domain::response::Response::success_ok(user, "User profile retrieved successfully")
+    Response::success_ok(UserInfo::from(user), "User profile retrieved successfully")
 }
 
-/// User/Auth Router
+/// Update current user profile
+pub async fn update_me(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    client: ClientInfo,
+    Json(req): Json<UpdateProfileRequest>,
+) -> impl IntoResponse {
+    state
+        .user_service
+        .update_profile(user, req, &client)
+        .await
+        .to_response("Profile updated successfully")
+}
+
+/// Change the password of the current user
+pub async fn change_password(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Extension(CurrentSession(session_id)): Extension<CurrentSession>,
+    client: ClientInfo,
+    Json(req): Json<ChangePasswordRequest>,
+) -> impl IntoResponse {
+    state
+        .user_service
+        .change_password(user, session_id, req, &client)
+        .await
+        .to_response_no_content("Password changed successfully")
+}
+
+/// Logout the current session
+pub async fn logout(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Extension(user): Extension<User>,
+    Extension(CurrentSession(session_id)): Extension<CurrentSession>,
+    client: ClientInfo,
+) -> impl IntoResponse {
+    let result = state.user_service.logout(user.id, session_id, &client).await;
+    (
+        remove_token_cookies(&state, jar),
+        result.to_response_no_content("Logged out successfully"),
+    )
+}
+
+/// Logout every session of the current user
+pub async fn logout_all(
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Extension(user): Extension<User>,
+    client: ClientInfo,
+) -> impl IntoResponse {
+    let result = state.user_service.logout_all(user.id, &client).await;
+    (
+        remove_token_cookies(&state, jar),
+        result.to_response_no_content("Logged out from all sessions successfully"),
+    )
+}
+
+/// List active sessions of the current user
+pub async fn list_sessions(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Extension(CurrentSession(session_id)): Extension<CurrentSession>,
+) -> impl IntoResponse {
+    state
+        .user_service
+        .list_sessions(user.id, session_id)
+        .await
+        .to_response("Sessions retrieved successfully")
+}
+
+/// Revoke one session of the current user
+pub async fn delete_session(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Path(id): Path<uuid::Uuid>,
+) -> impl IntoResponse {
+    state
+        .user_service
+        .revoke_session(user.id, id)
+        .await
+        .to_response_no_content("Session revoked successfully")
+}
+
+/// List accounts locked after too many failed logins (admin only)
+pub async fn list_locked_accounts(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    state
+        .user_service
+        .list_locked_accounts()
+        .await
+        .to_response("Locked accounts retrieved successfully")
+}
+
+/// Unlock an account (admin only)
+pub async fn unlock_account(
+    State(state): State<Arc<AppState>>,
+    Extension(admin): Extension<User>,
+    client: ClientInfo,
+    Path(id): Path<uuid::Uuid>,
+) -> impl IntoResponse {
+    state
+        .user_service
+        .unlock_account(admin.id, id, &client)
+        .await
+        .to_response_no_content("Account unlocked successfully")
+}
+
+/// Suspend a user and revoke their sessions (admin only)
+pub async fn suspend_user(
+    State(state): State<Arc<AppState>>,
+    Extension(admin): Extension<User>,
+    client: ClientInfo,
+    Path(id): Path<uuid::Uuid>,
+    Json(req): Json<AdminActionRequest>,
+) -> impl IntoResponse {
+    state
+        .user_service
+        .suspend_user(admin.id, id, req, &client)
+        .await
+        .to_response("User suspended successfully")
+}
+
+/// Reactivate a suspended user (admin only)
+pub async fn reactivate_user(
+    State(state): State<Arc<AppState>>,
+    Extension(admin): Extension<User>,
+    client: ClientInfo,
+    Path(id): Path<uuid::Uuid>,
+    Json(req): Json<AdminActionRequest>,
+) -> impl IntoResponse {
+    state
+        .user_service
+        .reactivate_user(admin.id, id, req, &client)
+        .await
+        .to_response("User reactivated successfully")
+}
+
+/// Invalidate a user's password and email them a reset link (admin only)
+pub async fn force_password_reset(
+    State(state): State<Arc<AppState>>,
+    Extension(admin): Extension<User>,
+    client: ClientInfo,
+    Path(id): Path<uuid::Uuid>,
+    Json(req): Json<AdminActionRequest>,
+) -> impl IntoResponse {
+    state
+        .user_service
+        .force_password_reset(admin.id, id, req, &client)
+        .await
+        .to_response_no_content("Password reset email sent")
+}
+
+/// Change the role of a user (admin only)
+pub async fn change_role(
+    State(state): State<Arc<AppState>>,
+    Extension(admin): Extension<User>,
+    client: ClientInfo,
+    Path(id): Path<uuid::Uuid>,
+    Json(req): Json<ChangeRoleRequest>,
+) -> impl IntoResponse {
+    state
+        .user_service
+        .change_role(admin.id, id, req, &client)
+        .await
+        .to_response("Role changed successfully")
+}
+
+/// Issue a short-lived token for acting as a user (admin only)
+pub async fn impersonate_user(
+    State(state): State<Arc<AppState>>,
+    Extension(admin): Extension<User>,
+    client: ClientInfo,
+    Path(id): Path<uuid::Uuid>,
+    Json(req): Json<AdminActionRequest>,
+) -> impl IntoResponse {
+    state
+        .user_service
+        .impersonate(admin.id, id, req, &client)
+        .await
+        .to_response("Impersonation started")
+}
+
+/// Respond to a login, putting the tokens in cookies unless a second factor is still required
+pub(crate) fn login_response(
+    state: &AppState,
+    jar: CookieJar,
+    result: Result<LoginResponse, AppError>,
+) -> (CookieJar, Response<LoginResponse>) {
+    let (jar, result) = match result {
+        Ok(LoginResponse::Authenticated(auth)) => {
+            let (jar, auth) = store_token_cookies(state, jar, auth);
+            (jar, Ok(LoginResponse::Authenticated(auth)))
+        }
+        result => (jar, result),
+    };
+    let message = match &result {
+        Ok(LoginResponse::MfaRequired(_)) => "Two-factor authentication required",
+        _ => "Login successful",
+    };
+    (jar, result.to_response(message))
+}
+
+/// Put newly issued tokens in cookies when cookie auth is enabled
+fn with_token_cookies(
+    state: &AppState,
+    jar: CookieJar,
+    result: Result<AuthResponse, AppError>,
+) -> (CookieJar, Result<AuthResponse, AppError>) {
+    match result {
+        // Accounts waiting for email verification get no tokens
+        Ok(auth) if auth.access_token.is_empty() => (jar, Ok(auth)),
+        Ok(auth) => {
+            let (jar, auth) = store_token_cookies(state, jar, auth);
+            (jar, Ok(auth))
+        }
+        Err(err) => (jar, Err(err)),
+    }
+}
+
+/// Set the token cookies, leaving the tokens out of the body in cookie-only mode
+pub(crate) fn store_token_cookies(
+    state: &AppState,
+    jar: CookieJar,
+    mut auth: AuthResponse,
+) -> (CookieJar, AuthResponse) {
+    let cookie_settings = &state.settings.cookies;
+    if cookie_settings.mode == CookieMode::Off {
+        return (jar, auth);
+    }
+
+    let jar = set_token_cookies(
+        cookie_settings,
+        jar,
+        &auth.access_token,
+        chrono::Duration::hours(state.settings.jwt.access_token_expiration_hours),
+        &auth.refresh_token,
+        chrono::Duration::hours(state.settings.jwt.refresh_token_expiration_hours),
+    );
+
+    if cookie_settings.mode == CookieMode::Cookie {
+        auth.access_token.clear();
+        auth.refresh_token.clear();
+    }
+
+    (jar, auth)
+}
+
+/// Expire the token cookies when cookie auth is enabled
+fn remove_token_cookies(state: &AppState, jar: CookieJar) -> CookieJar {
+    match state.settings.cookies.mode {
+        CookieMode::Off => jar,
+        _ => clear_token_cookies(&state.settings.cookies, jar),
+    }
+}
+
+/// User/Auth Router (public routes)
 pub fn router() -> Router<Arc<AppState>> {
     Router::new()
         .route("/register", post(register))
         .route("/login", post(login))
+        .route("/mfa/verify", post(verify_mfa))
         .route("/refresh", post(refresh_token))
+        .route("/verify-email", get(verify_email_link).post(verify_email))
+        .route("/verify-email/resend", post(resend_verification))
+        .route("/magic-link", post(request_magic_link))
+        .route("/magic-link/{token}", get(magic_link_login))
+        .route("/forgot-password", post(forgot_password))
+        .route("/reset-password", post(reset_password))
+        .route_layer(middleware::from_fn_with_state(
+            RateLimitScope::Auth,
+            rate_limit,
+        ))
+}
+
+/// User/Auth Router (routes requiring authentication)
+pub fn protected_router() -> Router<Arc<AppState>> {
+    Router::new()
         .route("/me", get(me))
+        .route("/logout", post(logout))
+        .route("/sessions", get(list_sessions))
+        .merge(account_security_router())
+        .route_layer(middleware::from_fn_with_state(
+            RateLimitScope::Auth,
+            rate_limit,
+        ))
+}
+
+// Routes that change the account or how it is secured, closed to admins impersonating the user
+fn account_security_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/me", patch(update_me))
+        .route("/change-password", post(change_password))
+        .route("/logout-all", post(logout_all))
+        .route("/sessions/{id}", delete(delete_session))
+        .route_layer(middleware::from_fn(forbid_impersonation))
+}
+
+/// User management Router (admin routes, nested under `/admin` by the caller)
+pub fn admin_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/locked-accounts", get(list_locked_accounts))
+        .route("/locked-accounts/{id}", delete(unlock_account))
+        .route("/users/{id}/suspend", post(suspend_user))
+        .route("/users/{id}/reactivate", post(reactivate_user))
+        .route("/users/{id}/force-password-reset", post(force_password_reset))
+        .route("/users/{id}/role", put(change_role))
+        .route("/users/{id}/impersonate", post(impersonate_user))
 }
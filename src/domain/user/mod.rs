@@ -1,5 +1,6 @@
+pub mod hooks;
 pub mod http;
 pub mod model;
 pub mod service;
 
-pub use http::router;
+pub use http::{admin_router, protected_router, router};
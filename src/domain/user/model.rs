@@ -1,22 +1,35 @@
 use serde::{Deserialize, Serialize};
 use validator::Validate;
 
+use crate::entities::sessions::Model as SessionModel;
 use crate::entities::users::Model as UserModel;
+use crate::utils::login_throttle::LoginAttempts;
 
-// Role enum
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub enum Role {
-    Student,
-    Teacher,
-    Admin,
-}
+// Role and UserStatus are stored as the `user_role` and `user_status` Postgres enums.
+// Role variants are ordered from least to most privileged, so roles can be compared with `<` / `>=`
+pub use crate::entities::sea_orm_active_enums::{UserRole as Role, UserStatus};
+
+impl Role {
+    pub const ALL: [Role; 3] = [Role::Student, Role::Teacher, Role::Admin];
 
-// UserStatus enum
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub enum UserStatus {
-    Active,
-    Pending,
-    Suspended,
+    /// Name of the role as stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Student => "student",
+            Role::Teacher => "teacher",
+            Role::Admin => "admin",
+        }
+    }
+
+    /// Parse a role name, e.g. from a path segment (case-insensitive)
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "student" => Some(Role::Student),
+            "teacher" => Some(Role::Teacher),
+            "admin" => Some(Role::Admin),
+            _ => None,
+        }
+    }
 }
 
 // Validation error for User
@@ -33,6 +46,45 @@ impl std::fmt::Display for UserValidationError {
 
 impl std::error::Error for UserValidationError {}
 
+/// Purpose of a single-use token stored in the `user_tokens` table
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UserTokenPurpose {
+    PasswordReset,
+    MagicLink,
+}
+
+impl UserTokenPurpose {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UserTokenPurpose::PasswordReset => "password_reset",
+            UserTokenPurpose::MagicLink => "magic_link",
+        }
+    }
+}
+
+/// Session of the authenticated request, added to request extensions by `auth_middleware`
+#[derive(Debug, Clone, Copy)]
+pub struct CurrentSession(pub uuid::Uuid);
+
+/// Marks a request whose user must use two-factor authentication but whose session
+/// has not completed it, added to request extensions by `auth_middleware`
+#[derive(Debug, Clone, Copy)]
+pub struct MfaPending;
+
+/// Admin acting as the user of the request through an impersonation token,
+/// added to request extensions by `auth_middleware`
+#[derive(Debug, Clone, Copy)]
+pub struct Impersonator(pub uuid::Uuid);
+
+/// User and session authenticated from an access token
+#[derive(Debug, Clone)]
+pub struct AccessIdentity {
+    pub user: UserModel,
+    pub session_id: uuid::Uuid,
+    pub mfa_pending: bool,
+    pub impersonated_by: Option<uuid::Uuid>, // Admin who opened the session, for impersonation tokens
+}
+
 // ============= Auth Request DTOs =============
 
 /// Request body for user registration
@@ -47,8 +99,8 @@ pub struct RegisterRequest {
     #[validate(email(message = "Invalid email format"))]
     pub email: Option<String>,
 
-    #[validate(length(min = 8, message = "Password must be at least 8 characters"))]
-    pub password: String,
+    #[validate(length(min = 1, message = "Password cannot be empty"))]
+    pub password: String, // Further checked against the password policy
 }
 
 /// Request body for user login
@@ -65,16 +117,91 @@ pub struct LoginRequest {
 /// Request body for token refresh
 #[derive(Debug, Deserialize, Validate)]
 pub struct RefreshTokenRequest {
+    #[serde(default)]
+    #[validate(length(min = 1, message = "Token cannot be empty"))]
+    pub token: String, // May be omitted when the refresh token is sent as a cookie
+}
+
+/// Request body for email verification
+#[derive(Debug, Deserialize, Validate)]
+pub struct VerifyEmailRequest {
     #[validate(length(min = 1, message = "Token cannot be empty"))]
     pub token: String,
 }
 
+/// Request body for resending the verification email
+#[derive(Debug, Deserialize, Validate)]
+pub struct ResendVerificationRequest {
+    #[validate(email(message = "Invalid email format"))]
+    pub email: String,
+}
+
+/// Request body for requesting a password reset email
+#[derive(Debug, Deserialize, Validate)]
+pub struct ForgotPasswordRequest {
+    #[validate(email(message = "Invalid email format"))]
+    pub email: String,
+}
+
+/// Request body for requesting a passwordless login link
+#[derive(Debug, Deserialize, Validate)]
+pub struct MagicLinkRequest {
+    #[validate(email(message = "Invalid email format"))]
+    pub email: String,
+}
+
+/// Request body for resetting the password with an emailed token
+#[derive(Debug, Deserialize, Validate)]
+pub struct ResetPasswordRequest {
+    #[validate(length(min = 1, message = "Token cannot be empty"))]
+    pub token: String,
+
+    #[validate(length(min = 1, message = "New password cannot be empty"))]
+    pub new_password: String, // Further checked against the password policy
+}
+
+/// Request body for completing a login with a second factor
+#[derive(Debug, Deserialize, Validate)]
+pub struct MfaVerifyRequest {
+    #[validate(length(min = 1, message = "MFA token cannot be empty"))]
+    pub mfa_token: String,
+
+    /// Code from the authenticator app, or a recovery code
+    #[validate(length(min = 1, message = "Code cannot be empty"))]
+    pub code: String,
+}
+
+/// Request body for updating the current user's profile; omitted fields are left unchanged
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateProfileRequest {
+    #[validate(length(min = 1, message = "Display name cannot be empty"))]
+    pub display_name: Option<String>,
+
+    #[validate(length(min = 3, message = "Username must be at least 3 characters"))]
+    pub username: Option<String>,
+
+    #[validate(email(message = "Invalid email format"))]
+    pub email: Option<String>,
+}
+
+/// Request body for changing the current user's password
+#[derive(Debug, Deserialize, Validate)]
+pub struct ChangePasswordRequest {
+    #[validate(length(min = 1, message = "Current password cannot be empty"))]
+    pub current_password: String,
+
+    #[validate(length(min = 1, message = "New password cannot be empty"))]
+    pub new_password: String, // Further checked against the password policy
+}
+
 // ============= Auth Response DTOs =============
 
 /// Response for authentication operations (login, register)
 #[derive(Debug, Serialize)]
 pub struct AuthResponse {
-    pub access_token: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub access_token: String, // Left out when sent as cookies or the account is pending
+    #[serde(skip_serializing_if = "String::is_empty")]
     pub refresh_token: String,
     pub user: UserInfo,
 }
@@ -86,6 +213,9 @@ pub struct UserInfo {
     pub display_name: String,
     pub username: String,
     pub email: String,
+    /// New address waiting for its verification link to be followed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pending_email: Option<String>,
     pub role: Role,
     pub status: UserStatus,
 }
@@ -97,23 +227,99 @@ impl From<UserModel> for UserInfo {
             display_name: user.display_name.unwrap_or_default(),
             username: user.username.unwrap_or_default(),
             email: user.email.unwrap_or_default(),
-            role: match user.role.as_str() {
-                "admin" => Role::Admin,
-                "teacher" => Role::Teacher,
-                _ => Role::Student,
-            },
-            status: match user.status.as_str() {
-                "active" => UserStatus::Active,
-                "pending" => UserStatus::Pending,
-                "suspended" => UserStatus::Suspended,
-                _ => UserStatus::Suspended,
-            },
+            pending_email: user.pending_email,
+            role: user.role,
+            status: user.status,
+        }
+    }
+}
+
+/// Response for login: the tokens, or a challenge for the second factor
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum LoginResponse {
+    Authenticated(AuthResponse),
+    MfaRequired(MfaChallengeResponse),
+}
+
+/// Challenge returned by login when the user has two-factor authentication enabled
+#[derive(Debug, Serialize)]
+pub struct MfaChallengeResponse {
+    pub mfa_required: bool,
+    pub mfa_token: String,
+    pub expires_in: i64, // Seconds
+}
+
+/// Active session information returned by the session listing
+#[derive(Debug, Serialize)]
+pub struct SessionInfo {
+    pub id: String,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub created: chrono::DateTime<chrono::FixedOffset>,
+    pub last_used: chrono::DateTime<chrono::FixedOffset>,
+    pub current: bool,
+}
+
+impl SessionInfo {
+    pub fn from_model(session: SessionModel, current_session_id: uuid::Uuid) -> Self {
+        Self {
+            id: session.id.to_string(),
+            user_agent: session.user_agent,
+            ip_address: session.ip_address,
+            created: session.created,
+            last_used: session.last_used,
+            current: session.id == current_session_id,
         }
     }
 }
 
-/// Token refresh response
+/// Account locked after too many failed logins, returned to admins
 #[derive(Debug, Serialize)]
-pub struct RefreshTokenResponse {
+pub struct LockedAccountInfo {
+    pub user: UserInfo,
+    pub failures: u32,
+    pub last_failure: chrono::DateTime<chrono::FixedOffset>,
+    pub locked_until: Option<chrono::DateTime<chrono::FixedOffset>>,
+}
+
+impl LockedAccountInfo {
+    pub fn from_attempts(user: UserModel, attempts: LoginAttempts) -> Self {
+        Self {
+            user: user.into(),
+            failures: attempts.failures,
+            last_failure: attempts.last_failure,
+            locked_until: attempts.locked_until,
+        }
+    }
+}
+
+// ============= Admin Request DTOs =============
+
+/// Request body for an admin action on a user account
+#[derive(Debug, Deserialize, Validate)]
+pub struct AdminActionRequest {
+    #[validate(length(min = 1, max = 500, message = "Reason must be 1 to 500 characters"))]
+    pub reason: String,
+}
+
+/// Request body for changing the role of a user
+#[derive(Debug, Deserialize, Validate)]
+pub struct ChangeRoleRequest {
+    pub role: Role,
+
+    #[validate(length(min = 1, max = 500, message = "Reason must be 1 to 500 characters"))]
+    pub reason: String,
+}
+
+// ============= Admin Response DTOs =============
+
+/// Access token issued to an admin impersonating a user. There is no refresh token;
+/// requests made with it carry the `X-Impersonated-By` response header.
+#[derive(Debug, Serialize)]
+pub struct ImpersonationResponse {
     pub access_token: String,
+    pub expires_in: i64, // Seconds
+    pub user: UserInfo,
+    pub impersonated_by: String,
 }
@@ -1,45 +1,90 @@
 // Import Domain
+use crate::entities::refresh_tokens::{self, Entity as RefreshTokens};
+use crate::entities::sessions::{self, Entity as Sessions};
+use crate::entities::user_tokens::{self, Entity as UserTokens, Model as UserTokenModel};
 use crate::entities::users::{self, Entity as Users, Model as UserModel};
+use sea_orm::sea_query::{Expr, IntoCondition};
 use sea_orm::*;
 
 // Import Dtos
 use super::model::{
-    AuthResponse, LoginRequest, RefreshTokenRequest, RefreshTokenResponse, RegisterRequest,
+    AccessIdentity, AdminActionRequest, AuthResponse, ChangePasswordRequest, ChangeRoleRequest,
+    ForgotPasswordRequest, ImpersonationResponse, LockedAccountInfo, LoginRequest,
+    LoginResponse, MagicLinkRequest, MfaChallengeResponse, MfaVerifyRequest,
+    RefreshTokenRequest, RegisterRequest, ResendVerificationRequest, ResetPasswordRequest, Role,
+    SessionInfo, UpdateProfileRequest, UserInfo, UserStatus, UserTokenPurpose,
+    VerifyEmailRequest,
 };
 
 // Import Utils
+use crate::domain::audit::model::{AuditAction, AuditEvent};
+use crate::domain::audit::service::AuditService;
 use crate::domain::error::AppError;
+use crate::domain::mfa::service::MfaService;
+use crate::settings::Auth as AuthSettings;
+use crate::utils::cache::TtlCache;
+use crate::utils::client_info::ClientInfo;
+use crate::utils::email::EmailService;
 use crate::utils::jwt::{JwtUtil, TokenType};
-use crate::utils::password::{hash_password, verify_password};
+use crate::utils::login_throttle::{LoginThrottle, ThrottleKey};
+use crate::utils::password::PasswordUtil;
+use crate::utils::password_policy::PersonalInfo;
+use crate::utils::token::{generate_token, hash_token};
+use std::sync::Arc;
 use validator::Validate;
 
+/// Recently verified access tokens by session ID, so most requests skip the database.
+/// Entries are dropped when the session, user or MFA policy changes through the services
+/// or the admin CRUD routes; other changes show up once the entry expires.
+pub type IdentityCache = TtlCache<uuid::Uuid, AccessIdentity>;
+
 /// UserService handles authentication and user management logic
 #[derive(Clone)]
 pub struct UserService {
     db: DatabaseConnection,
     jwt_util: JwtUtil,
+    email_service: EmailService,
+    auth_settings: AuthSettings,
+    login_throttle: LoginThrottle,
+    mfa_service: MfaService,
+    identity_cache: Arc<IdentityCache>,
+    password_util: PasswordUtil,
+    audit_service: AuditService,
 }
 
 impl UserService {
     /// Create a new UserService instance
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         db: DatabaseConnection,
-        jwt_secret: &str,
-        access_token_expiration_hours: i64,
-        refresh_token_expiration_hours: i64,
+        jwt_util: JwtUtil,
+        email_service: EmailService,
+        auth_settings: AuthSettings,
+        login_throttle: LoginThrottle,
+        mfa_service: MfaService,
+        identity_cache: Arc<IdentityCache>,
+        password_util: PasswordUtil,
+        audit_service: AuditService,
     ) -> Self {
         Self {
             db,
-            jwt_util: JwtUtil::new(
-                jwt_secret,
-                access_token_expiration_hours,
-                refresh_token_expiration_hours,
-            ),
+            jwt_util,
+            email_service,
+            auth_settings,
+            login_throttle,
+            mfa_service,
+            identity_cache,
+            password_util,
+            audit_service,
         }
     }
 
     /// Register a new user
-    pub async fn register(&self, register_req: RegisterRequest) -> Result<AuthResponse, AppError> {
+    pub async fn register(
+        &self,
+        register_req: RegisterRequest,
+        client: &ClientInfo,
+    ) -> Result<AuthResponse, AppError> {
         // Validate input
         register_req.validate().map_err(AppError::from)?;
 
@@ -50,48 +95,57 @@ impl UserService {
             ));
         }
 
+        // An email address is needed to verify the account
+        let require_verification = self.auth_settings.require_email_verification;
+        if require_verification && register_req.email.is_none() {
+            return Err(AppError::missing_field("email"));
+        }
+
+        // Check the password against the policy
+        self.password_util
+            .policy()
+            .check(
+                &register_req.password,
+                PersonalInfo {
+                    username: register_req.username.as_deref(),
+                    email: register_req.email.as_deref(),
+                },
+            )
+            .map_err(AppError::PasswordPolicy)?;
+
         // Check if username already exists
         if let Some(username) = &register_req.username {
-            let existing_user = Users::find()
-                .filter(users::Column::Username.eq(username))
-                .one(&self.db)
-                .await
-                .map_err(|e| AppError::Internal(e.to_string()))?;
-
-            if existing_user.is_some() {
-                return Err(AppError::username_already_exists(username));
-            }
+            self.ensure_username_available(username, None).await?;
         }
 
         // Check if email already exists
         if let Some(email) = &register_req.email {
-            let existing_user = Users::find()
-                .filter(users::Column::Email.eq(email))
-                .one(&self.db)
-                .await
-                .map_err(|e| AppError::Internal(e.to_string()))?;
-
-            if existing_user.is_some() {
-                return Err(AppError::email_already_exists(email));
-            }
+            self.ensure_email_available(email, None).await?;
         }
 
         // Hash password
-        let password_hash =
-            hash_password(&register_req.password).map_err(|e| AppError::Internal(e.to_string()))?;
+        let password_hash = self
+            .password_util
+            .hash_password(&register_req.password)
+            .map_err(|e| AppError::Internal(e.to_string()))?;
 
         // Create user model
-        let now = chrono::Utc::now().with_timezone(&chrono::FixedOffset::east_opt(0).unwrap());
+        let now = now();
         let user_id = uuid::Uuid::now_v7();
 
         let active_model = users::ActiveModel {
             id: Set(user_id),
             username: Set(register_req.username.clone()),
             email: Set(register_req.email.clone()),
+            pending_email: Set(None),
             display_name: Set(register_req.display_name.clone()),
             password_hash: Set(password_hash),
-            role: Set("student".to_string()),
-            status: Set("active".to_string()),
+            role: Set(Role::Student),
+            status: Set(if require_verification {
+                UserStatus::Pending
+            } else {
+                UserStatus::Active
+            }),
             created: Set(now),
             updated: Set(now),
         };
@@ -101,30 +155,62 @@ impl UserService {
             .await
             .map_err(|e| AppError::Internal(e.to_string()))?;
 
-        // Generate tokens
-        let email = user_model.email.as_deref().unwrap_or("");
-        let access_token = self
-            .jwt_util
-            .generate_access_token(user_model.id, email)
-            .map_err(|e| AppError::Internal(e.to_string()))?;
+        self.audit_service
+            .record_or_warn(
+                AuditEvent::user(AuditAction::Register, user_model.id)
+                    .actor(user_model.id)
+                    .client(client)
+                    .changes(None, Some(&user_json(&user_model))),
+            )
+            .await;
 
-        let refresh_token = self
-            .jwt_util
-            .generate_refresh_token(user_model.id, email)
-            .map_err(|e| AppError::Internal(e.to_string()))?;
+        // Pending accounts get a verification link; a delivery failure can be
+        // recovered from with the resend endpoint, so it does not fail the registration
+        if require_verification {
+            if let Err(err) = self.send_verification_email(&user_model).await {
+                tracing::error!(
+                    "Failed to send verification email to user {}: {:?}",
+                    user_model.id,
+                    err
+                );
+            }
+        }
 
-        Ok(AuthResponse {
-            access_token,
-            refresh_token,
-            user: user_model.into(),
-        })
+        // Pending accounts only get tokens once they log in after verifying their address
+        if require_verification {
+            return Ok(AuthResponse {
+                access_token: String::new(),
+                refresh_token: String::new(),
+                user: user_model.into(),
+            });
+        }
+
+        // Start a new session and generate tokens
+        let session_id = self
+            .create_session(&self.db, user_model.id, client, false)
+            .await?;
+        self.issue_tokens(&self.db, user_model, session_id, client)
+            .await
     }
 
     /// Login user
-    pub async fn login(&self, login_req: LoginRequest) -> Result<AuthResponse, AppError> {
+    ///
+    /// Users with two-factor authentication enabled get a short-lived MFA token instead,
+    /// to be exchanged for the tokens with `verify_mfa`.
+    pub async fn login(
+        &self,
+        login_req: LoginRequest,
+        client: &ClientInfo,
+    ) -> Result<LoginResponse, AppError> {
         // Validate input
         login_req.validate().map_err(AppError::from)?;
 
+        // Reject clients that are locked out before doing any work
+        let ip_key = client.ip_address.as_deref().map(ThrottleKey::Ip);
+        if let Some(ip_key) = ip_key {
+            self.ensure_not_locked(ip_key).await?;
+        }
+
         // Find user by username or email
         let user_model = Users::find()
             .filter(
@@ -134,54 +220,197 @@ impl UserService {
             )
             .one(&self.db)
             .await
-            .map_err(|e| AppError::Internal(e.to_string()))?
-            .ok_or_else(|| AppError::Unauthorized("Invalid credentials".to_string()))?;
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let Some(user_model) = user_model else {
+            self.password_util.verify_dummy_password(&login_req.password);
+            self.record_login_failure(None, ip_key).await?;
+            self.audit_service
+                .record_or_warn(
+                    AuditEvent::new(AuditAction::LoginFailed, "users", None).client(client),
+                )
+                .await;
+            return Err(AppError::Unauthorized("Invalid credentials".to_string()));
+        };
+
+        let account_key = ThrottleKey::Account(user_model.id);
+        self.ensure_not_locked(account_key).await?;
 
         // Verify password
-        if !verify_password(&login_req.password, &user_model.password_hash)
-            .map_err(|e| AppError::Internal(e.to_string()))?
-        {
+        let verification = self
+            .password_util
+            .verify_password(&login_req.password, &user_model.password_hash)
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        if !verification.valid {
+            self.record_login_failure(Some(account_key), ip_key).await?;
+            self.audit_service
+                .record_or_warn(
+                    AuditEvent::user(AuditAction::LoginFailed, user_model.id).client(client),
+                )
+                .await;
             return Err(AppError::Unauthorized("Invalid credentials".to_string()));
         }
 
+        // Upgrade hashes made with outdated parameters while the password is at hand
+        let user_model = if verification.needs_rehash {
+            self.rehash_password(user_model, &login_req.password).await
+        } else {
+            user_model
+        };
+
+        // The password is right, so earlier failures on this account no longer matter
+        self.login_throttle
+            .clear(account_key)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        self.complete_login(user_model, client).await
+    }
+
+    /// Finish a login once the user has proven who they are (password, identity provider, ...)
+    ///
+    /// Checks the account status, then starts a session or asks for the second factor.
+    pub async fn complete_login(
+        &self,
+        user_model: UserModel,
+        client: &ClientInfo,
+    ) -> Result<LoginResponse, AppError> {
         // Check user status
-        if user_model.status != "active" {
+        ensure_active(&user_model)?;
+
+        // Ask for the second factor before starting a session
+        if self.mfa_service.is_enrolled(user_model.id).await? {
+            let expiration_minutes = self.auth_settings.mfa_pending_expiration_minutes;
+            let mfa_token = self
+                .jwt_util
+                .generate_mfa_pending_token(
+                    user_model.id,
+                    user_model.email.as_deref().unwrap_or(""),
+                    expiration_minutes,
+                )
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+
+            return Ok(LoginResponse::MfaRequired(MfaChallengeResponse {
+                mfa_required: true,
+                mfa_token,
+                expires_in: expiration_minutes * 60,
+            }));
+        }
+
+        // Start a new session and generate tokens
+        let user_id = user_model.id;
+        let session_id = self
+            .create_session(&self.db, user_id, client, false)
+            .await?;
+        let response = self
+            .issue_tokens(&self.db, user_model, session_id, client)
+            .await?;
+
+        self.audit_service
+            .record_or_warn(
+                AuditEvent::user(AuditAction::Login, user_id)
+                    .actor(user_id)
+                    .client(client),
+            )
+            .await;
+
+        Ok(LoginResponse::Authenticated(response))
+    }
+
+    /// Complete a login with a code from the authenticator app or a recovery code
+    pub async fn verify_mfa(
+        &self,
+        verify_req: MfaVerifyRequest,
+        client: &ClientInfo,
+    ) -> Result<AuthResponse, AppError> {
+        // Validate input
+        verify_req.validate().map_err(AppError::from)?;
+
+        let claims = self
+            .jwt_util
+            .verify_token(&verify_req.mfa_token, TokenType::MfaPending)
+            .map_err(|_| AppError::Unauthorized("Invalid or expired MFA token".to_string()))?;
+
+        if claims.token_type != TokenType::MfaPending {
+            return Err(AppError::Unauthorized("Invalid token type".to_string()));
+        }
+
+        let user_id = claims
+            .sub
+            .parse::<uuid::Uuid>()
+            .map_err(|_| AppError::Unauthorized("Invalid user ID in token".to_string()))?;
+
+        // Codes are short, so wrong ones count towards the account lockout
+        let account_key = ThrottleKey::Account(user_id);
+        self.ensure_not_locked(account_key).await?;
+
+        let user_model = Users::find_by_id(user_id)
+            .one(&self.db)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .ok_or_else(|| AppError::Unauthorized("User not found".to_string()))?;
+
+        if user_model.status != UserStatus::Active {
             return Err(AppError::Forbidden(
                 "Account is suspended or inactive".to_string(),
             ));
         }
 
-        // Generate tokens
-        let email = user_model.email.as_deref().unwrap_or("");
-        let access_token = self
-            .jwt_util
-            .generate_access_token(user_model.id, email)
-            .map_err(|e| AppError::Internal(e.to_string()))?;
+        if !self
+            .mfa_service
+            .verify_second_factor(user_id, &verify_req.code)
+            .await?
+        {
+            let ip_key = client.ip_address.as_deref().map(ThrottleKey::Ip);
+            self.record_login_failure(Some(account_key), ip_key).await?;
+            self.audit_service
+                .record_or_warn(AuditEvent::user(AuditAction::LoginFailed, user_id).client(client))
+                .await;
+            return Err(AppError::Unauthorized(
+                "Invalid authentication code".to_string(),
+            ));
+        }
 
-        let refresh_token = self
-            .jwt_util
-            .generate_refresh_token(user_model.id, email)
+        self.login_throttle
+            .clear(account_key)
+            .await
             .map_err(|e| AppError::Internal(e.to_string()))?;
 
-        Ok(AuthResponse {
-            access_token,
-            refresh_token,
-            user: user_model.into(),
-        })
+        // Start a new session and generate tokens
+        let session_id = self
+            .create_session(&self.db, user_id, client, true)
+            .await?;
+        let response = self
+            .issue_tokens(&self.db, user_model, session_id, client)
+            .await?;
+
+        self.audit_service
+            .record_or_warn(
+                AuditEvent::user(AuditAction::Login, user_id)
+                    .actor(user_id)
+                    .client(client),
+            )
+            .await;
+
+        Ok(response)
     }
 
-    /// Refresh access token
+    /// Rotate a refresh token and issue a new token pair
+    ///
+    /// Every refresh token can be used exactly once. Presenting a token that was
+    /// already rotated means it has leaked, so the whole session is revoked.
     pub async fn refresh_token(
         &self,
         refresh_req: RefreshTokenRequest,
-    ) -> Result<RefreshTokenResponse, AppError> {
+        client: &ClientInfo,
+    ) -> Result<AuthResponse, AppError> {
         // Validate input
         refresh_req.validate().map_err(AppError::from)?;
 
         // Validate refresh token
         let claims = self
             .jwt_util
-            .verify_token(&refresh_req.token)
+            .verify_token(&refresh_req.token, TokenType::Refresh)
             .map_err(|_| AppError::Unauthorized("Invalid refresh token".to_string()))?;
 
         if claims.token_type != TokenType::Refresh {
@@ -193,33 +422,100 @@ impl UserService {
             .parse::<uuid::Uuid>()
             .map_err(|_| AppError::Unauthorized("Invalid user ID in token".to_string()))?;
 
+        let txn = self
+            .db
+            .begin()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        // Look up the stored token, locking it so concurrent refreshes cannot both succeed
+        let stored_token = RefreshTokens::find()
+            .filter(refresh_tokens::Column::JtiHash.eq(hash_token(&claims.jti)))
+            .lock_exclusive()
+            .one(&txn)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .ok_or_else(|| AppError::Unauthorized("Refresh token not recognized".to_string()))?;
+
+        if stored_token.user_id != user_id || stored_token.revoked_at.is_some() {
+            return Err(AppError::Unauthorized(
+                "Refresh token has been revoked".to_string(),
+            ));
+        }
+
+        // Reuse detection: revoke the session and every token in it
+        if stored_token.used_at.is_some() {
+            tracing::warn!(
+                "Refresh token reuse detected for user {} (session {})",
+                user_id,
+                stored_token.session_id
+            );
+            let revoked =
+                revoke_sessions(&txn, sessions::Column::Id.eq(stored_token.session_id)).await?;
+            txn.commit()
+                .await
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+            self.forget_sessions(&revoked);
+
+            return Err(AppError::Unauthorized(
+                "Refresh token has already been used".to_string(),
+            ));
+        }
+
         let user = Users::find_by_id(user_id)
-            .one(&self.db)
+            .one(&txn)
             .await
             .map_err(|e| AppError::Internal(e.to_string()))?
             .ok_or_else(|| AppError::Unauthorized("User not found".to_string()))?;
 
-        if user.status != "active" && user.status != "pending" {
-            return Err(AppError::Forbidden(
-                "Account is suspended or inactive".to_string(),
-            ));
+        ensure_active(&user)?;
+
+        // Make sure the session itself has not been logged out
+        let session = Sessions::find_by_id(stored_token.session_id)
+            .one(&txn)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .filter(|session| session.revoked_at.is_none())
+            .ok_or_else(|| AppError::Unauthorized("Session has been revoked".to_string()))?;
+
+        // Mark the presented token as used
+        let mut used_token: refresh_tokens::ActiveModel = stored_token.into();
+        used_token.used_at = Set(Some(now()));
+        used_token
+            .update(&txn)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        // Record the latest device metadata on the session
+        let session_id = session.id;
+        let mut session: sessions::ActiveModel = session.into();
+        session.last_used = Set(now());
+        if client.user_agent.is_some() {
+            session.user_agent = Set(client.user_agent.clone());
+        }
+        if client.ip_address.is_some() {
+            session.ip_address = Set(client.ip_address.clone());
         }
+        session
+            .update(&txn)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
 
-        // Generate new access token
-        let email = user.email.as_deref().unwrap_or("");
-        let access_token = self
-            .jwt_util
-            .generate_access_token(user_id, email)
+        // Issue the next token pair in the same session
+        let response = self.issue_tokens(&txn, user, session_id, client).await?;
+
+        txn.commit()
+            .await
             .map_err(|e| AppError::Internal(e.to_string()))?;
 
-        Ok(RefreshTokenResponse { access_token })
+        Ok(response)
     }
 
-    /// Verify access token and return user
-    pub async fn verify_token(&self, token: &str) -> Result<UserModel, AppError> {
+    /// Verify access token and return user with the session the token belongs to
+    pub async fn verify_token(&self, token: &str) -> Result<AccessIdentity, AppError> {
         let claims = self
             .jwt_util
-            .verify_token(token)
+            .verify_token(token, TokenType::Access)
             .map_err(|_| AppError::Unauthorized("Invalid access token".to_string()))?;
 
         if claims.token_type != TokenType::Access {
@@ -231,18 +527,1450 @@ impl UserService {
             .parse::<uuid::Uuid>()
             .map_err(|_| AppError::Unauthorized("Invalid user ID in token".to_string()))?;
 
+        let session_id = claims
+            .sid
+            .parse::<uuid::Uuid>()
+            .map_err(|_| AppError::Unauthorized("Invalid session ID in token".to_string()))?;
+
+        let actor_id = claims
+            .act
+            .map(|actor| actor.sub.parse::<uuid::Uuid>())
+            .transpose()
+            .map_err(|_| AppError::Unauthorized("Invalid actor ID in token".to_string()))?;
+
+        // Sessions verified recently skip the database
+        if let Some(identity) = self.identity_cache.get(&session_id) {
+            if identity.user.id == user_id && identity.impersonated_by == actor_id {
+                return Ok(identity);
+            }
+        }
+
         let user_model = Users::find_by_id(user_id)
             .one(&self.db)
             .await
             .map_err(|e| AppError::Internal(e.to_string()))?
             .ok_or_else(|| AppError::Unauthorized("User not found".to_string()))?;
 
-        if user_model.status != "active" && user_model.status != "pending" {
+        ensure_active(&user_model)?;
+
+        // Check that the session has not been logged out
+        let session = Sessions::find_by_id(session_id)
+            .one(&self.db)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .filter(|session| session.user_id == user_id && session.revoked_at.is_none())
+            .ok_or_else(|| AppError::Unauthorized("Session has been revoked".to_string()))?;
+
+        // Impersonation tokens are only accepted for the session the admin opened,
+        // and other tokens never are
+        let impersonated_by = session.impersonated_by;
+        if impersonated_by != actor_id {
+            return Err(AppError::Unauthorized("Invalid access token".to_string()));
+        }
+
+        // Sessions started before the role required a second factor must complete it first
+        let mfa_pending = session.mfa_verified_at.is_none()
+            && self.mfa_service.is_required(user_model.role).await?;
+
+        // Refresh the last-used time, at most once per interval to avoid a write per request
+        if now() - session.last_used > chrono::Duration::minutes(SESSION_TOUCH_INTERVAL_MINUTES) {
+            let mut session: sessions::ActiveModel = session.into();
+            session.last_used = Set(now());
+            session
+                .update(&self.db)
+                .await
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+        }
+
+        let identity = AccessIdentity {
+            user: user_model,
+            session_id,
+            mfa_pending,
+            impersonated_by,
+        };
+        self.identity_cache.insert(session_id, identity.clone());
+
+        Ok(identity)
+    }
+
+    /// Verify an email address and activate the pending account
+    pub async fn verify_email(&self, verify_req: VerifyEmailRequest) -> Result<UserInfo, AppError> {
+        // Validate input
+        verify_req.validate().map_err(AppError::from)?;
+
+        let claims = self
+            .jwt_util
+            .verify_token(&verify_req.token, TokenType::EmailVerification)
+            .map_err(|_| AppError::Unauthorized("Invalid verification token".to_string()))?;
+
+        if claims.token_type != TokenType::EmailVerification {
+            return Err(AppError::Unauthorized("Invalid token type".to_string()));
+        }
+
+        let user_id = claims
+            .sub
+            .parse::<uuid::Uuid>()
+            .map_err(|_| AppError::Unauthorized("Invalid user ID in token".to_string()))?;
+
+        let user_model = Users::find_by_id(user_id)
+            .one(&self.db)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .ok_or_else(|| AppError::user_not_found(user_id))?;
+
+        // A link sent to a new address of an active account makes it the account's address
+        if user_model.pending_email.as_deref() == Some(claims.email.as_str()) {
+            return self.confirm_pending_email(user_model).await;
+        }
+
+        // The link is only valid for the address it was sent to
+        if user_model.email.as_deref() != Some(claims.email.as_str()) {
+            return Err(AppError::Unauthorized(
+                "Verification token does not match the current email address".to_string(),
+            ));
+        }
+
+        // Verifying twice is harmless
+        if user_model.status == UserStatus::Active {
+            return Ok(user_model.into());
+        }
+
+        if user_model.status != UserStatus::Pending {
             return Err(AppError::Forbidden(
                 "Account is suspended or inactive".to_string(),
             ));
         }
 
-        Ok(user_model)
+        let mut active_model: users::ActiveModel = user_model.into();
+        active_model.status = Set(UserStatus::Active);
+        active_model.updated = Set(now());
+
+        let user_model = active_model
+            .update(&self.db)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        self.forget_user(user_model.id);
+
+        Ok(user_model.into())
+    }
+
+    // Replace the address of a user with their verified pending one
+    async fn confirm_pending_email(&self, user_model: UserModel) -> Result<UserInfo, AppError> {
+        // The address may have been taken since it was asked for
+        if let Some(email) = &user_model.pending_email {
+            self.ensure_email_available(email, Some(user_model.id))
+                .await?;
+        }
+
+        let mut active_model: users::ActiveModel = user_model.clone().into();
+        active_model.email = Set(user_model.pending_email);
+        active_model.pending_email = Set(None);
+        active_model.updated = Set(now());
+
+        let user_model = active_model
+            .update(&self.db)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        self.forget_user(user_model.id);
+
+        Ok(user_model.into())
+    }
+
+    /// Send a new verification link to a pending account
+    ///
+    /// Always succeeds for unknown or already verified addresses, so the endpoint
+    /// cannot be used to find out which emails are registered.
+    pub async fn resend_verification_email(
+        &self,
+        resend_req: ResendVerificationRequest,
+    ) -> Result<(), AppError> {
+        // Validate input
+        resend_req.validate().map_err(AppError::from)?;
+
+        let user_model = Users::find()
+            .filter(users::Column::Email.eq(&resend_req.email))
+            .one(&self.db)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        if let Some(user_model) = user_model.filter(|user| user.status == UserStatus::Pending) {
+            self.send_verification_email(&user_model)
+                .await
+                .map_err(|e| AppError::ExternalServiceError(e.to_string()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Email a single-use password reset link
+    ///
+    /// Always succeeds for unknown addresses, so the endpoint cannot be used to
+    /// find out which emails are registered.
+    pub async fn forgot_password(&self, forgot_req: ForgotPasswordRequest) -> Result<(), AppError> {
+        // Validate input
+        forgot_req.validate().map_err(AppError::from)?;
+
+        let user_model = Users::find()
+            .filter(users::Column::Email.eq(&forgot_req.email))
+            .one(&self.db)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        // Suspended accounts cannot regain access through a reset
+        let Some(user_model) = user_model.filter(|user| user.status != UserStatus::Suspended) else {
+            return Ok(());
+        };
+
+        let expiration_minutes = self.auth_settings.password_reset_expiration_minutes;
+        let token = self
+            .issue_user_token(
+                user_model.id,
+                UserTokenPurpose::PasswordReset,
+                chrono::Duration::minutes(expiration_minutes),
+            )
+            .await?;
+
+        self.email_service
+            .send_password_reset_email(
+                &forgot_req.email,
+                user_model.display_name.as_deref(),
+                &token,
+                expiration_minutes,
+            )
+            .await
+            .map_err(|e| AppError::ExternalServiceError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Email a single-use link that logs the user in without a password
+    ///
+    /// Always succeeds for unknown addresses, so the endpoint cannot be used to
+    /// find out which emails are registered. At most one link is sent per interval.
+    pub async fn request_magic_link(&self, link_req: MagicLinkRequest) -> Result<(), AppError> {
+        // Validate input
+        link_req.validate().map_err(AppError::from)?;
+
+        let user_model = Users::find()
+            .filter(users::Column::Email.eq(&link_req.email))
+            .one(&self.db)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        // Suspended accounts cannot log in anyway
+        let Some(user_model) = user_model.filter(|user| user.status != UserStatus::Suspended) else {
+            return Ok(());
+        };
+
+        // Don't flood the inbox when the form is submitted repeatedly
+        let recently_sent = UserTokens::find()
+            .filter(user_tokens::Column::UserId.eq(user_model.id))
+            .filter(user_tokens::Column::Purpose.eq(UserTokenPurpose::MagicLink.as_str()))
+            .filter(
+                user_tokens::Column::Created
+                    .gt(now() - chrono::Duration::seconds(MAGIC_LINK_RESEND_INTERVAL_SECONDS)),
+            )
+            .count(&self.db)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        if recently_sent > 0 {
+            return Ok(());
+        }
+
+        let expiration_minutes = self.auth_settings.magic_link_expiration_minutes;
+        let token = self
+            .issue_user_token(
+                user_model.id,
+                UserTokenPurpose::MagicLink,
+                chrono::Duration::minutes(expiration_minutes),
+            )
+            .await?;
+
+        self.email_service
+            .send_magic_link_email(
+                &link_req.email,
+                user_model.display_name.as_deref(),
+                &token,
+                expiration_minutes,
+            )
+            .await
+            .map_err(|e| AppError::ExternalServiceError(e.to_string()))?;
+
+        Ok(())
     }
+
+    /// Log in with an emailed magic link token
+    ///
+    /// Users with two-factor authentication enabled still have to complete it with `verify_mfa`.
+    pub async fn login_with_magic_link(
+        &self,
+        token: &str,
+        client: &ClientInfo,
+    ) -> Result<LoginResponse, AppError> {
+        let txn = self
+            .db
+            .begin()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let link_token = self
+            .consume_user_token(&txn, token, UserTokenPurpose::MagicLink)
+            .await?;
+
+        let mut user_model = Users::find_by_id(link_token.user_id)
+            .one(&txn)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .ok_or_else(|| AppError::user_not_found(link_token.user_id))?;
+
+        // Following the emailed link also proves ownership of the address
+        let activated = user_model.status == UserStatus::Pending;
+        if activated {
+            let mut active_model: users::ActiveModel = user_model.into();
+            active_model.status = Set(UserStatus::Active);
+            active_model.updated = Set(now());
+            user_model = active_model
+                .update(&txn)
+                .await
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+        }
+
+        txn.commit()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        if activated {
+            self.forget_user(user_model.id);
+        }
+
+        self.complete_login(user_model, client).await
+    }
+
+    /// Set a new password using an emailed reset token and log out every session
+    pub async fn reset_password(
+        &self,
+        reset_req: ResetPasswordRequest,
+        client: &ClientInfo,
+    ) -> Result<(), AppError> {
+        // Validate input
+        reset_req.validate().map_err(AppError::from)?;
+
+        // Hash password
+        let password_hash = self
+            .password_util
+            .hash_password(&reset_req.new_password)
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let txn = self
+            .db
+            .begin()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let reset_token = self
+            .consume_user_token(&txn, &reset_req.token, UserTokenPurpose::PasswordReset)
+            .await?;
+
+        let user_model = Users::find_by_id(reset_token.user_id)
+            .one(&txn)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .ok_or_else(|| AppError::user_not_found(reset_token.user_id))?;
+
+        if user_model.status == UserStatus::Suspended {
+            return Err(AppError::Forbidden(
+                "Account is suspended or inactive".to_string(),
+            ));
+        }
+
+        // Check the password against the policy; the token stays usable if it fails
+        self.password_util
+            .policy()
+            .check(&reset_req.new_password, personal_info(&user_model))
+            .map_err(AppError::PasswordPolicy)?;
+
+        // Following the emailed link also proves ownership of the address
+        let status = if user_model.status == UserStatus::Pending {
+            UserStatus::Active
+        } else {
+            user_model.status
+        };
+
+        let before = user_json(&user_model);
+        let mut active_model: users::ActiveModel = user_model.into();
+        active_model.password_hash = Set(password_hash);
+        active_model.status = Set(status);
+        active_model.updated = Set(now());
+        let user_model = active_model
+            .update(&txn)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        // Existing sessions may belong to whoever knew the old password
+        let revoked =
+            revoke_sessions(&txn, sessions::Column::UserId.eq(reset_token.user_id)).await?;
+        self.audit_service
+            .record(
+                &txn,
+                AuditEvent::user(AuditAction::PasswordReset, user_model.id)
+                    .actor(user_model.id)
+                    .client(client)
+                    .changes(Some(&before), Some(&user_json(&user_model))),
+            )
+            .await?;
+
+        txn.commit()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        self.forget_sessions(&revoked);
+
+        // The owner proved access to the email, so let them log in right away
+        self.login_throttle
+            .clear(ThrottleKey::Account(reset_token.user_id))
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Update the display name, username or email of the current user
+    ///
+    /// When verification is required, a new email address of an active account is kept as
+    /// `pending_email` and replaces the current one once its verification link is followed.
+    pub async fn update_profile(
+        &self,
+        user_model: UserModel,
+        update_req: UpdateProfileRequest,
+        client: &ClientInfo,
+    ) -> Result<UserInfo, AppError> {
+        // Validate input
+        update_req.validate().map_err(AppError::from)?;
+
+        let username_changed = update_req
+            .username
+            .as_ref()
+            .is_some_and(|username| user_model.username.as_ref() != Some(username));
+        let email_changed = update_req
+            .email
+            .as_ref()
+            .is_some_and(|email| user_model.email.as_ref() != Some(email));
+
+        // Check if the new username or email is taken by another user
+        if username_changed {
+            if let Some(username) = &update_req.username {
+                self.ensure_username_available(username, Some(user_model.id))
+                    .await?;
+            }
+        }
+
+        if email_changed {
+            if let Some(email) = &update_req.email {
+                self.ensure_email_available(email, Some(user_model.id))
+                    .await?;
+            }
+        }
+
+        // An active account keeps its verified address until the new one is verified too,
+        // while a pending one has nothing verified to keep
+        let require_verification = self.auth_settings.require_email_verification;
+        let keep_email = require_verification && user_model.status == UserStatus::Active;
+        let before = user_json(&user_model);
+        let mut active_model: users::ActiveModel = user_model.clone().into();
+
+        if let Some(display_name) = update_req.display_name {
+            active_model.display_name = Set(Some(display_name));
+        }
+        if username_changed {
+            active_model.username = Set(update_req.username);
+        }
+        if email_changed && keep_email {
+            active_model.pending_email = Set(update_req.email);
+        } else if update_req.email.is_some() {
+            // Setting the current address again drops a pending change
+            if email_changed {
+                active_model.email = Set(update_req.email);
+            }
+            active_model.pending_email = Set(None);
+        }
+        active_model.updated = Set(now());
+
+        let user_model = active_model
+            .update(&self.db)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        self.forget_user(user_model.id);
+
+        self.audit_service
+            .record_or_warn(
+                AuditEvent::user(AuditAction::ProfileUpdate, user_model.id)
+                    .actor(user_model.id)
+                    .client(client)
+                    .changes(Some(&before), Some(&user_json(&user_model))),
+            )
+            .await;
+
+        // Links sent to the previous address no longer match, so send a new one
+        if email_changed && require_verification {
+            if let Err(err) = self.send_verification_email(&user_model).await {
+                tracing::error!(
+                    "Failed to send verification email to user {}: {:?}",
+                    user_model.id,
+                    err
+                );
+            }
+        }
+
+        Ok(user_model.into())
+    }
+
+    /// Change the password of the current user and log out their other sessions
+    pub async fn change_password(
+        &self,
+        user_model: UserModel,
+        session_id: uuid::Uuid,
+        change_req: ChangePasswordRequest,
+        client: &ClientInfo,
+    ) -> Result<(), AppError> {
+        // Validate input
+        change_req.validate().map_err(AppError::from)?;
+
+        // Verify current password
+        if !self
+            .password_util
+            .verify_password(&change_req.current_password, &user_model.password_hash)
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .valid
+        {
+            return Err(AppError::InvalidPassword(
+                "Current password is incorrect".to_string(),
+            ));
+        }
+
+        // Check the new password against the policy
+        self.password_util
+            .policy()
+            .check(&change_req.new_password, personal_info(&user_model))
+            .map_err(AppError::PasswordPolicy)?;
+
+        // Hash password
+        let password_hash = self
+            .password_util
+            .hash_password(&change_req.new_password)
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let user_id = user_model.id;
+        let txn = self
+            .db
+            .begin()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let before = user_json(&user_model);
+        let mut active_model: users::ActiveModel = user_model.into();
+        active_model.password_hash = Set(password_hash);
+        active_model.updated = Set(now());
+        let user_model = active_model
+            .update(&txn)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        revoke_sessions(
+            &txn,
+            Condition::all()
+                .add(sessions::Column::UserId.eq(user_id))
+                .add(sessions::Column::Id.ne(session_id)),
+        )
+        .await?;
+        self.audit_service
+            .record(
+                &txn,
+                AuditEvent::user(AuditAction::PasswordChange, user_id)
+                    .actor(user_id)
+                    .client(client)
+                    .changes(Some(&before), Some(&user_json(&user_model))),
+            )
+            .await?;
+
+        txn.commit()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        self.forget_user(user_id);
+
+        Ok(())
+    }
+
+    /// List accounts that are locked after too many failed logins
+    pub async fn list_locked_accounts(&self) -> Result<Vec<LockedAccountInfo>, AppError> {
+        let locked = self
+            .login_throttle
+            .locked_accounts()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let user_ids: Vec<uuid::Uuid> = locked.iter().map(|(user_id, _)| *user_id).collect();
+        let mut user_models: std::collections::HashMap<uuid::Uuid, UserModel> = Users::find()
+            .filter(users::Column::Id.is_in(user_ids))
+            .all(&self.db)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .into_iter()
+            .map(|user| (user.id, user))
+            .collect();
+
+        // Counters of deleted users are skipped
+        Ok(locked
+            .into_iter()
+            .filter_map(|(user_id, attempts)| {
+                let user_model = user_models.remove(&user_id)?;
+                Some(LockedAccountInfo::from_attempts(user_model, attempts))
+            })
+            .collect())
+    }
+
+    /// Unlock an account and reset its failed login counter
+    pub async fn unlock_account(
+        &self,
+        admin_id: uuid::Uuid,
+        user_id: uuid::Uuid,
+        client: &ClientInfo,
+    ) -> Result<(), AppError> {
+        Users::find_by_id(user_id)
+            .one(&self.db)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .ok_or_else(|| AppError::user_not_found(user_id))?;
+
+        self.login_throttle
+            .clear(ThrottleKey::Account(user_id))
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        self.audit_service
+            .record_or_warn(
+                AuditEvent::user(AuditAction::Unlock, user_id)
+                    .actor(admin_id)
+                    .client(client),
+            )
+            .await;
+
+        Ok(())
+    }
+
+    /// Suspend a user and log out all of their sessions (admin action)
+    pub async fn suspend_user(
+        &self,
+        admin_id: uuid::Uuid,
+        user_id: uuid::Uuid,
+        action_req: AdminActionRequest,
+        client: &ClientInfo,
+    ) -> Result<UserInfo, AppError> {
+        // Validate input
+        action_req.validate().map_err(AppError::from)?;
+
+        let user_model = self
+            .find_user_for_admin_action(admin_id, user_id, AuditAction::Suspend)
+            .await?;
+
+        if user_model.status == UserStatus::Suspended {
+            return Err(AppError::Conflict("User is already suspended".to_string()));
+        }
+
+        let txn = self
+            .db
+            .begin()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let before = user_json(&user_model);
+        let mut active_model: users::ActiveModel = user_model.into();
+        active_model.status = Set(UserStatus::Suspended);
+        active_model.updated = Set(now());
+        let user_model = active_model
+            .update(&txn)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        revoke_sessions(&txn, sessions::Column::UserId.eq(user_id)).await?;
+        self.audit_service
+            .record(
+                &txn,
+                AuditEvent::user(AuditAction::Suspend, user_id)
+                    .actor(admin_id)
+                    .client(client)
+                    .reason(&action_req.reason)
+                    .changes(Some(&before), Some(&user_json(&user_model))),
+            )
+            .await?;
+
+        txn.commit()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        self.forget_user(user_id);
+
+        Ok(user_model.into())
+    }
+
+    /// Lift the suspension of a user (admin action)
+    pub async fn reactivate_user(
+        &self,
+        admin_id: uuid::Uuid,
+        user_id: uuid::Uuid,
+        action_req: AdminActionRequest,
+        client: &ClientInfo,
+    ) -> Result<UserInfo, AppError> {
+        // Validate input
+        action_req.validate().map_err(AppError::from)?;
+
+        let user_model = self
+            .find_user_for_admin_action(admin_id, user_id, AuditAction::Reactivate)
+            .await?;
+
+        if user_model.status != UserStatus::Suspended {
+            return Err(AppError::Conflict("User is not suspended".to_string()));
+        }
+
+        let txn = self
+            .db
+            .begin()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let before = user_json(&user_model);
+        let mut active_model: users::ActiveModel = user_model.into();
+        active_model.status = Set(UserStatus::Active);
+        active_model.updated = Set(now());
+        let user_model = active_model
+            .update(&txn)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        self.audit_service
+            .record(
+                &txn,
+                AuditEvent::user(AuditAction::Reactivate, user_id)
+                    .actor(admin_id)
+                    .client(client)
+                    .reason(&action_req.reason)
+                    .changes(Some(&before), Some(&user_json(&user_model))),
+            )
+            .await?;
+
+        txn.commit()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        self.forget_user(user_id);
+
+        Ok(user_model.into())
+    }
+
+    /// Make a user choose a new password: the current one stops working, all sessions
+    /// are logged out and a reset link is emailed (admin action)
+    pub async fn force_password_reset(
+        &self,
+        admin_id: uuid::Uuid,
+        user_id: uuid::Uuid,
+        action_req: AdminActionRequest,
+        client: &ClientInfo,
+    ) -> Result<(), AppError> {
+        // Validate input
+        action_req.validate().map_err(AppError::from)?;
+
+        let user_model = self
+            .find_user_for_admin_action(admin_id, user_id, AuditAction::ForcePasswordReset)
+            .await?;
+
+        let email = user_model
+            .email
+            .clone()
+            .ok_or_else(|| AppError::validation("User has no email address to send a reset link to"))?;
+
+        // Replace the password with a random one nobody knows
+        let password_hash = self
+            .password_util
+            .hash_password(&generate_token(USER_TOKEN_LENGTH))
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let txn = self
+            .db
+            .begin()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let display_name = user_model.display_name.clone();
+        let before = user_json(&user_model);
+        let mut active_model: users::ActiveModel = user_model.into();
+        active_model.password_hash = Set(password_hash);
+        active_model.updated = Set(now());
+        let user_model = active_model
+            .update(&txn)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        revoke_sessions(&txn, sessions::Column::UserId.eq(user_id)).await?;
+        self.audit_service
+            .record(
+                &txn,
+                AuditEvent::user(AuditAction::ForcePasswordReset, user_id)
+                    .actor(admin_id)
+                    .client(client)
+                    .reason(&action_req.reason)
+                    .changes(Some(&before), Some(&user_json(&user_model))),
+            )
+            .await?;
+
+        txn.commit()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        self.forget_user(user_id);
+
+        let expiration_minutes = self.auth_settings.password_reset_expiration_minutes;
+        let token = self
+            .issue_user_token(
+                user_id,
+                UserTokenPurpose::PasswordReset,
+                chrono::Duration::minutes(expiration_minutes),
+            )
+            .await?;
+
+        self.email_service
+            .send_password_reset_email(&email, display_name.as_deref(), &token, expiration_minutes)
+            .await
+            .map_err(|e| AppError::ExternalServiceError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Change the role of a user (admin action)
+    pub async fn change_role(
+        &self,
+        admin_id: uuid::Uuid,
+        user_id: uuid::Uuid,
+        role_req: ChangeRoleRequest,
+        client: &ClientInfo,
+    ) -> Result<UserInfo, AppError> {
+        // Validate input
+        role_req.validate().map_err(AppError::from)?;
+
+        let user_model = self
+            .find_user_for_admin_action(admin_id, user_id, AuditAction::ChangeRole)
+            .await?;
+
+        if user_model.role == role_req.role {
+            return Err(AppError::Conflict(format!(
+                "User already has the {:?} role",
+                role_req.role
+            )));
+        }
+
+        let txn = self
+            .db
+            .begin()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let before = user_json(&user_model);
+        let mut active_model: users::ActiveModel = user_model.into();
+        active_model.role = Set(role_req.role);
+        active_model.updated = Set(now());
+        let user_model = active_model
+            .update(&txn)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        self.audit_service
+            .record(
+                &txn,
+                AuditEvent::user(AuditAction::ChangeRole, user_id)
+                    .actor(admin_id)
+                    .client(client)
+                    .reason(&role_req.reason)
+                    .changes(Some(&before), Some(&user_json(&user_model))),
+            )
+            .await?;
+
+        txn.commit()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        self.forget_user(user_id);
+
+        Ok(user_model.into())
+    }
+
+    /// Issue a short-lived access token that lets an admin act as a user, e.g. to see
+    /// what a student sees. Admin accounts cannot be impersonated.
+    pub async fn impersonate(
+        &self,
+        admin_id: uuid::Uuid,
+        user_id: uuid::Uuid,
+        action_req: AdminActionRequest,
+        client: &ClientInfo,
+    ) -> Result<ImpersonationResponse, AppError> {
+        // Validate input
+        action_req.validate().map_err(AppError::from)?;
+
+        let user_model = self
+            .find_user_for_admin_action(admin_id, user_id, AuditAction::Impersonate)
+            .await?;
+
+        if user_model.role == Role::Admin {
+            return Err(AppError::Forbidden(
+                "Admin accounts cannot be impersonated".to_string(),
+            ));
+        }
+        if user_model.status == UserStatus::Suspended {
+            return Err(AppError::Conflict(
+                "Suspended users cannot be impersonated".to_string(),
+            ));
+        }
+
+        let txn = self
+            .db
+            .begin()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        // The admin completed any second factor their own session required
+        let now = now();
+        let session = sessions::ActiveModel {
+            id: Set(uuid::Uuid::now_v7()),
+            user_id: Set(user_id),
+            user_agent: Set(client.user_agent.clone()),
+            ip_address: Set(client.ip_address.clone()),
+            last_used: Set(now),
+            revoked_at: Set(None),
+            mfa_verified_at: Set(Some(now)),
+            impersonated_by: Set(Some(admin_id)),
+            created: Set(now),
+        }
+        .insert(&txn)
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        self.audit_service
+            .record(
+                &txn,
+                AuditEvent::user(AuditAction::Impersonate, user_id)
+                    .actor(admin_id)
+                    .client(client)
+                    .reason(&action_req.reason),
+            )
+            .await?;
+
+        let expiration_minutes = self.auth_settings.impersonation_expiration_minutes;
+        let access_token = self
+            .jwt_util
+            .generate_impersonation_token(
+                user_id,
+                user_model.email.as_deref().unwrap_or(""),
+                user_model.role.as_str(),
+                session.id,
+                admin_id,
+                expiration_minutes,
+            )
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        txn.commit()
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        tracing::info!(%admin_id, %user_id, session_id = %session.id, "Admin started impersonating a user");
+
+        Ok(ImpersonationResponse {
+            access_token,
+            expires_in: expiration_minutes * 60,
+            user: user_model.into(),
+            impersonated_by: admin_id.to_string(),
+        })
+    }
+
+    /// Log out the current session
+    pub async fn logout(
+        &self,
+        user_id: uuid::Uuid,
+        session_id: uuid::Uuid,
+        client: &ClientInfo,
+    ) -> Result<(), AppError> {
+        self.revoke_session(user_id, session_id).await?;
+
+        self.audit_service
+            .record_or_warn(
+                AuditEvent::user(AuditAction::Logout, user_id)
+                    .actor(user_id)
+                    .client(client),
+            )
+            .await;
+
+        Ok(())
+    }
+
+    /// Log out every session of a user
+    pub async fn logout_all(
+        &self,
+        user_id: uuid::Uuid,
+        client: &ClientInfo,
+    ) -> Result<u64, AppError> {
+        let revoked = revoke_sessions(&self.db, sessions::Column::UserId.eq(user_id)).await?;
+        self.forget_sessions(&revoked);
+
+        self.audit_service
+            .record_or_warn(
+                AuditEvent::user(AuditAction::LogoutAll, user_id)
+                    .actor(user_id)
+                    .client(client),
+            )
+            .await;
+
+        Ok(revoked.len() as u64)
+    }
+
+    /// List the active sessions of a user
+    pub async fn list_sessions(
+        &self,
+        user_id: uuid::Uuid,
+        current_session_id: uuid::Uuid,
+    ) -> Result<Vec<SessionInfo>, AppError> {
+        let sessions = Sessions::find()
+            .filter(sessions::Column::UserId.eq(user_id))
+            .filter(sessions::Column::RevokedAt.is_null())
+            .order_by_desc(sessions::Column::LastUsed)
+            .all(&self.db)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        // Sessions whose refresh tokens have all expired can no longer be used
+        let active_session_ids: Vec<uuid::Uuid> = RefreshTokens::find()
+            .select_only()
+            .column(refresh_tokens::Column::SessionId)
+            .distinct()
+            .filter(refresh_tokens::Column::UserId.eq(user_id))
+            .filter(refresh_tokens::Column::RevokedAt.is_null())
+            .filter(refresh_tokens::Column::UsedAt.is_null())
+            .filter(refresh_tokens::Column::ExpiresAt.gt(now()))
+            .into_tuple()
+            .all(&self.db)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Ok(sessions
+            .into_iter()
+            .filter(|session| active_session_ids.contains(&session.id))
+            .map(|session| SessionInfo::from_model(session, current_session_id))
+            .collect())
+    }
+
+    /// Revoke one session of a user
+    pub async fn revoke_session(
+        &self,
+        user_id: uuid::Uuid,
+        session_id: uuid::Uuid,
+    ) -> Result<(), AppError> {
+        let revoked = revoke_sessions(
+            &self.db,
+            Condition::all()
+                .add(sessions::Column::Id.eq(session_id))
+                .add(sessions::Column::UserId.eq(user_id)),
+        )
+        .await?;
+        self.forget_sessions(&revoked);
+
+        if revoked.is_empty() {
+            return Err(AppError::NotFound(format!(
+                "Session with id {} not found",
+                session_id
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Generate an access token and a tracked refresh token for a user's session
+    async fn issue_tokens<C: ConnectionTrait>(
+        &self,
+        conn: &C,
+        user_model: UserModel,
+        session_id: uuid::Uuid,
+        client: &ClientInfo,
+    ) -> Result<AuthResponse, AppError> {
+        let email = user_model.email.as_deref().unwrap_or("");
+        let access_token = self
+            .jwt_util
+            .generate_access_token(user_model.id, email, user_model.role.as_str(), session_id)
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let jti = uuid::Uuid::now_v7();
+        let refresh_token = self
+            .jwt_util
+            .generate_refresh_token(user_model.id, email, user_model.role.as_str(), session_id, jti)
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        // Store only the hash of the token ID
+        let now = now();
+        let token_model = refresh_tokens::ActiveModel {
+            id: Set(uuid::Uuid::now_v7()),
+            user_id: Set(user_model.id),
+            session_id: Set(session_id),
+            jti_hash: Set(hash_token(&jti.to_string())),
+            user_agent: Set(client.user_agent.clone()),
+            ip_address: Set(client.ip_address.clone()),
+            expires_at: Set(
+                now + chrono::Duration::hours(self.jwt_util.refresh_token_expiration_hours())
+            ),
+            used_at: Set(None),
+            revoked_at: Set(None),
+            created: Set(now),
+        };
+
+        token_model
+            .insert(conn)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Ok(AuthResponse {
+            access_token,
+            refresh_token,
+            user: user_model.into(),
+        })
+    }
+
+    /// Email a signed verification link to a user
+    // Send the link to the pending address when there is one
+    async fn send_verification_email(&self, user_model: &UserModel) -> anyhow::Result<()> {
+        let email = user_model
+            .pending_email
+            .as_deref()
+            .or(user_model.email.as_deref())
+            .ok_or_else(|| anyhow::anyhow!("User has no email address"))?;
+
+        let token = self.jwt_util.generate_email_verification_token(
+            user_model.id,
+            email,
+            self.auth_settings.email_verification_expiration_hours,
+        )?;
+
+        self.email_service
+            .send_verification_email(email, user_model.display_name.as_deref(), &token)
+            .await
+    }
+
+    /// Fail if the account or client is locked out after too many failed logins
+    async fn ensure_not_locked(&self, key: ThrottleKey<'_>) -> Result<(), AppError> {
+        let locked_for = self
+            .login_throttle
+            .locked_for(key)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        match locked_for {
+            Some(seconds) => Err(AppError::TooManyLoginAttempts(format!(
+                "Too many failed login attempts, try again in {} seconds",
+                seconds
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Count a failed login against the account (if it exists) and the client IP
+    async fn record_login_failure(
+        &self,
+        account_key: Option<ThrottleKey<'_>>,
+        ip_key: Option<ThrottleKey<'_>>,
+    ) -> Result<(), AppError> {
+        for key in account_key.into_iter().chain(ip_key) {
+            self.login_throttle
+                .record_failure(key)
+                .await
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// Fail if another user already has this username
+    async fn ensure_username_available(
+        &self,
+        username: &str,
+        exclude_user_id: Option<uuid::Uuid>,
+    ) -> Result<(), AppError> {
+        let mut query = Users::find().filter(users::Column::Username.eq(username));
+        if let Some(user_id) = exclude_user_id {
+            query = query.filter(users::Column::Id.ne(user_id));
+        }
+
+        let existing_user = query
+            .one(&self.db)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        if existing_user.is_some() {
+            return Err(AppError::username_already_exists(username));
+        }
+
+        Ok(())
+    }
+
+    /// Fail if another user already has this email address
+    async fn ensure_email_available(
+        &self,
+        email: &str,
+        exclude_user_id: Option<uuid::Uuid>,
+    ) -> Result<(), AppError> {
+        let mut query = Users::find().filter(users::Column::Email.eq(email));
+        if let Some(user_id) = exclude_user_id {
+            query = query.filter(users::Column::Id.ne(user_id));
+        }
+
+        let existing_user = query
+            .one(&self.db)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        if existing_user.is_some() {
+            return Err(AppError::email_already_exists(email));
+        }
+
+        Ok(())
+    }
+
+    /// Create a single-use token for a user, invalidating earlier tokens with the same purpose.
+    /// Returns the plain token; only its hash is stored.
+    async fn issue_user_token(
+        &self,
+        user_id: uuid::Uuid,
+        purpose: UserTokenPurpose,
+        lifetime: chrono::Duration,
+    ) -> Result<String, AppError> {
+        let now = now();
+
+        UserTokens::update_many()
+            .col_expr(user_tokens::Column::UsedAt, Expr::value(now))
+            .filter(user_tokens::Column::UserId.eq(user_id))
+            .filter(user_tokens::Column::Purpose.eq(purpose.as_str()))
+            .filter(user_tokens::Column::UsedAt.is_null())
+            .exec(&self.db)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let token = generate_token(USER_TOKEN_LENGTH);
+        let token_model = user_tokens::ActiveModel {
+            id: Set(uuid::Uuid::now_v7()),
+            user_id: Set(user_id),
+            purpose: Set(purpose.as_str().to_string()),
+            token_hash: Set(hash_token(&token)),
+            expires_at: Set(now + lifetime),
+            used_at: Set(None),
+            created: Set(now),
+        };
+
+        token_model
+            .insert(&self.db)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Ok(token)
+    }
+
+    /// Mark a single-use token as used, failing if it is unknown, used or expired
+    async fn consume_user_token<C: ConnectionTrait>(
+        &self,
+        conn: &C,
+        token: &str,
+        purpose: UserTokenPurpose,
+    ) -> Result<UserTokenModel, AppError> {
+        let token_model = UserTokens::find()
+            .filter(user_tokens::Column::TokenHash.eq(hash_token(token)))
+            .filter(user_tokens::Column::Purpose.eq(purpose.as_str()))
+            .lock_exclusive()
+            .one(conn)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .filter(|token| token.used_at.is_none() && token.expires_at > now())
+            .ok_or_else(|| AppError::Unauthorized("Invalid or expired token".to_string()))?;
+
+        let mut active_model: user_tokens::ActiveModel = token_model.into();
+        active_model.used_at = Set(Some(now()));
+
+        active_model
+            .update(conn)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))
+    }
+
+    /// Create a session for a new login
+    async fn create_session<C: ConnectionTrait>(
+        &self,
+        conn: &C,
+        user_id: uuid::Uuid,
+        client: &ClientInfo,
+        mfa_verified: bool,
+    ) -> Result<uuid::Uuid, AppError> {
+        let now = now();
+        let session = sessions::ActiveModel {
+            id: Set(uuid::Uuid::now_v7()),
+            user_id: Set(user_id),
+            user_agent: Set(client.user_agent.clone()),
+            ip_address: Set(client.ip_address.clone()),
+            last_used: Set(now),
+            revoked_at: Set(None),
+            mfa_verified_at: Set(mfa_verified.then_some(now)),
+            impersonated_by: Set(None),
+            created: Set(now),
+        };
+
+        let session = session
+            .insert(conn)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Ok(session.id)
+    }
+
+    /// Replace a password hash made with outdated parameters by a fresh one.
+    /// Failures are only logged, since the login itself succeeded.
+    async fn rehash_password(&self, user_model: UserModel, password: &str) -> UserModel {
+        let password_hash = match self.password_util.hash_password(password) {
+            Ok(password_hash) => password_hash,
+            Err(e) => {
+                tracing::warn!("Failed to rehash password of user {}: {}", user_model.id, e);
+                return user_model;
+            }
+        };
+
+        // Only replace the hash that was verified, in case the password changed meanwhile
+        let result = Users::update_many()
+            .col_expr(
+                users::Column::PasswordHash,
+                Expr::value(password_hash.clone()),
+            )
+            .filter(users::Column::Id.eq(user_model.id))
+            .filter(users::Column::PasswordHash.eq(&user_model.password_hash))
+            .exec(&self.db)
+            .await;
+
+        match result {
+            Ok(result) if result.rows_affected > 0 => UserModel {
+                password_hash,
+                ..user_model
+            },
+            Ok(_) => user_model,
+            Err(e) => {
+                tracing::warn!("Failed to rehash password of user {}: {}", user_model.id, e);
+                user_model
+            }
+        }
+    }
+
+    /// Load the target of an admin action. Admins cannot act on their own account,
+    /// so they cannot lock themselves out by accident.
+    async fn find_user_for_admin_action(
+        &self,
+        admin_id: uuid::Uuid,
+        user_id: uuid::Uuid,
+        action: AuditAction,
+    ) -> Result<UserModel, AppError> {
+        if admin_id == user_id {
+            return Err(AppError::Forbidden(format!(
+                "The {} action cannot be applied to your own account",
+                action.as_str()
+            )));
+        }
+
+        Users::find_by_id(user_id)
+            .one(&self.db)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .ok_or_else(|| AppError::user_not_found(user_id))
+    }
+
+    /// Drop the cached identities of a user after their account changed
+    pub fn forget_user(&self, user_id: uuid::Uuid) {
+        self.identity_cache
+            .retain(|_, identity| identity.user.id != user_id);
+    }
+
+    /// Drop the cached identities of revoked sessions, once the revocation is committed
+    fn forget_sessions(&self, session_ids: &[uuid::Uuid]) {
+        for session_id in session_ids {
+            self.identity_cache.remove(session_id);
+        }
+    }
+}
+
+// Length of the random tokens emailed to users
+const USER_TOKEN_LENGTH: usize = 48;
+
+// Minimum time between two magic links sent to the same user
+const MAGIC_LINK_RESEND_INTERVAL_SECONDS: i64 = 60;
+
+// How often the last-used time of a session is refreshed by authenticated requests
+const SESSION_TOUCH_INTERVAL_MINUTES: i64 = 5;
+
+/// Revoke the active sessions matching a condition, along with their refresh tokens.
+/// Returns the IDs of the sessions revoked, for the caller to forget after committing.
+pub async fn revoke_sessions<C: ConnectionTrait>(
+    conn: &C,
+    condition: impl IntoCondition,
+) -> Result<Vec<uuid::Uuid>, AppError> {
+    let now = now();
+
+    let session_ids: Vec<uuid::Uuid> = Sessions::find()
+        .select_only()
+        .column(sessions::Column::Id)
+        .filter(condition)
+        .filter(sessions::Column::RevokedAt.is_null())
+        .into_tuple()
+        .all(conn)
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    if session_ids.is_empty() {
+        return Ok(session_ids);
+    }
+
+    Sessions::update_many()
+        .col_expr(sessions::Column::RevokedAt, Expr::value(now))
+        .filter(sessions::Column::Id.is_in(session_ids.clone()))
+        .exec(conn)
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    RefreshTokens::update_many()
+        .col_expr(refresh_tokens::Column::RevokedAt, Expr::value(now))
+        .filter(refresh_tokens::Column::SessionId.is_in(session_ids.clone()))
+        .filter(refresh_tokens::Column::RevokedAt.is_null())
+        .exec(conn)
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(session_ids)
+}
+
+/// Check that an account may be used: pending accounts have to verify their email
+/// address first, and suspended ones are shut out
+pub fn ensure_active(user_model: &UserModel) -> Result<(), AppError> {
+    match user_model.status {
+        UserStatus::Active => Ok(()),
+        UserStatus::Pending => Err(AppError::Forbidden(
+            "Email address has not been verified".to_string(),
+        )),
+        _ => Err(AppError::Forbidden(
+            "Account is suspended or inactive".to_string(),
+        )),
+    }
+}
+
+// Account details a new password of the user must not contain
+fn personal_info(user_model: &UserModel) -> PersonalInfo<'_> {
+    PersonalInfo {
+        username: user_model.username.as_deref(),
+        email: user_model.email.as_deref(),
+    }
+}
+
+// Account fields as recorded in audit event diffs; the password hash is redacted there
+fn user_json(user_model: &UserModel) -> serde_json::Value {
+    serde_json::to_value(user_model).unwrap_or_default()
+}
+
+// Current time in the timezone used by the entity timestamps
+fn now() -> chrono::DateTime<chrono::FixedOffset> {
+    chrono::Utc::now().with_timezone(&chrono::FixedOffset::east_opt(0).unwrap())
 }
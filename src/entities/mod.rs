@@ -3,9 +3,21 @@
 #[allow(unused)]
 pub mod prelude;
 
+pub mod api_keys;
+pub mod audit_events;
+pub mod identities;
 pub mod lessons;
+pub mod login_attempts;
 pub mod notes;
+pub mod oidc_login_states;
+pub mod recovery_codes;
+pub mod refresh_tokens;
+pub mod role_mfa_policies;
+pub mod sea_orm_active_enums;
 pub mod sentences;
+pub mod sessions;
+pub mod user_tokens;
+pub mod user_totp;
 pub mod users;
 pub mod word_sentences;
 pub mod words;
@@ -1,8 +1,19 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.19
 
+pub use super::api_keys::Entity as ApiKeys;
+pub use super::audit_events::Entity as AuditEvents;
+pub use super::identities::Entity as Identities;
 pub use super::lessons::Entity as Lessons;
+pub use super::login_attempts::Entity as LoginAttempts;
 pub use super::notes::Entity as Notes;
+pub use super::oidc_login_states::Entity as OidcLoginStates;
+pub use super::recovery_codes::Entity as RecoveryCodes;
+pub use super::refresh_tokens::Entity as RefreshTokens;
+pub use super::role_mfa_policies::Entity as RoleMfaPolicies;
 pub use super::sentences::Entity as Sentences;
+pub use super::sessions::Entity as Sessions;
+pub use super::user_tokens::Entity as UserTokens;
+pub use super::user_totp::Entity as UserTotp;
 pub use super::users::Entity as Users;
 pub use super::word_sentences::Entity as WordSentences;
 pub use super::words::Entity as Words;
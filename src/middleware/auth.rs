@@ -1,12 +1,21 @@
 use axum::extract::{Request, State};
 use axum::http::header::AUTHORIZATION;
+use axum::http::HeaderValue;
 use axum::middleware::Next;
 use axum::response::{IntoResponse, Response};
+use axum_extra::extract::cookie::CookieJar;
 
+use crate::domain::api_key::model::{required_scope, API_KEY_HEADER};
 use crate::domain::error::AppError;
 use crate::domain::response::Response as ApiResponse;
+use crate::domain::user::model::{CurrentSession, Impersonator, MfaPending};
 use crate::entities::users::Model as User;
+use crate::settings::CookieMode;
 use crate::state::AppState;
+use crate::utils::cookies::{access_token_cookie, verify_csrf};
+
+/// Response header naming the admin behind a request made with an impersonation token
+pub const IMPERSONATED_BY_HEADER: &str = "x-impersonated-by";
 
 /// Middleware function to authenticate and authorize requests
 pub async fn auth_middleware(
@@ -14,41 +23,133 @@ pub async fn auth_middleware(
     mut request: Request,
     next: Next,
 ) -> Result<Response, Response> {
-    // Extract token from Authorization header
-    let token = extract_bearer_token(&request).ok_or_else(|| {
+    // Requests from scripts and integrations authenticate with an API key instead
+    if let Some(key) = request.headers().get(API_KEY_HEADER) {
+        let key = key.to_str().unwrap_or_default().to_string();
+        return authenticate_api_key(&state, &key, request, next).await;
+    }
+
+    let missing_token = || {
         ApiResponse::<()>::failure_unauthorized(
             "Missing or invalid Authorization header",
             Some("Authorization header must be in format: Bearer <token>".to_string()),
         )
         .into_response()
-    })?;
+    };
+
+    // Extract token from Authorization header, falling back to the access cookie
+    let cookie_settings = &state.settings.cookies;
+    let token = match extract_bearer_token(&request) {
+        Some(token) => token,
+        None if cookie_settings.mode != CookieMode::Off => {
+            let jar = CookieJar::from_headers(request.headers());
+            let token = access_token_cookie(cookie_settings, &jar).ok_or_else(missing_token)?;
+
+            // Browsers attach cookies to cross-site requests, so state-changing
+            // requests must prove they can read the CSRF cookie
+            if !request.method().is_safe() && !verify_csrf(cookie_settings, &jar, request.headers())
+            {
+                return Err(ApiResponse::<()>::failure_forbidden(
+                    "Access forbidden",
+                    Some("CSRF token missing or invalid".to_string()),
+                )
+                .into_response());
+            }
+
+            token
+        }
+        None => return Err(missing_token()),
+    };
 
     // Verify token and get user
-    let user = state
+    let identity = state
         .user_service
         .verify_token(&token)
         .await
-        .map_err(|err| match err {
-            AppError::Unauthorized(msg) => {
-                ApiResponse::<()>::failure_unauthorized("Authentication failed", Some(msg))
-                    .into_response()
-            }
-            AppError::Forbidden(msg) => {
-                ApiResponse::<()>::failure_forbidden("Access forbidden", Some(msg)).into_response()
-            }
-            _ => {
-                ApiResponse::<()>::failure_internal("Internal server error", Some(err.to_string()))
-                    .into_response()
-            }
-        })?;
+        .map_err(auth_error)?;
+
+    // Add user and session to request extensions
+    request.extensions_mut().insert(identity.user);
+    request
+        .extensions_mut()
+        .insert(CurrentSession(identity.session_id));
+    if identity.mfa_pending {
+        request.extensions_mut().insert(MfaPending);
+    }
+    if let Some(admin_id) = identity.impersonated_by {
+        request.extensions_mut().insert(Impersonator(admin_id));
+    }
+
+    // Continue with the next middleware
+    let mut response = next.run(request).await;
+
+    // Mark impersonated responses so clients can make it obvious whose view is shown
+    if let Some(admin_id) = identity.impersonated_by {
+        if let Ok(value) = HeaderValue::from_str(&admin_id.to_string()) {
+            response.headers_mut().insert(IMPERSONATED_BY_HEADER, value);
+        }
+    }
 
-    // Add user to request extensions
-    request.extensions_mut().insert(user);
+    Ok(response)
+}
+
+// Authenticate with an API key, which only grants the endpoints its scopes cover
+async fn authenticate_api_key(
+    state: &AppState,
+    key: &str,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, Response> {
+    let identity = state
+        .api_key_service
+        .authenticate(key)
+        .await
+        .map_err(auth_error)?;
+
+    // Check the key's scopes before anything else sees the request
+    let scope = required_scope(request.method(), request.uri().path()).ok_or_else(|| {
+        ApiResponse::<()>::failure_forbidden(
+            "Access forbidden",
+            Some("This endpoint cannot be used with an API key".to_string()),
+        )
+        .into_response()
+    })?;
+    if !identity.key.scopes.contains(&scope) {
+        return Err(ApiResponse::<()>::failure_forbidden(
+            "Access forbidden",
+            Some(format!("API key is missing the {} scope", scope)),
+        )
+        .into_response());
+    }
+
+    tracing::debug!(api_key_id = %identity.key.key_id, "Request authenticated with an API key");
+
+    // Add user and key to request extensions
+    request.extensions_mut().insert(identity.user);
+    request.extensions_mut().insert(identity.key);
+    if identity.mfa_pending {
+        request.extensions_mut().insert(MfaPending);
+    }
 
     // Continue with the next middleware
     Ok(next.run(request).await)
 }
 
+// Helper function to turn an authentication error into a response
+fn auth_error(err: AppError) -> Response {
+    match err {
+        AppError::Unauthorized(msg) => {
+            ApiResponse::<()>::failure_unauthorized("Authentication failed", Some(msg))
+                .into_response()
+        }
+        AppError::Forbidden(msg) => {
+            ApiResponse::<()>::failure_forbidden("Access forbidden", Some(msg)).into_response()
+        }
+        _ => ApiResponse::<()>::failure_internal("Internal server error", Some(err.to_string()))
+            .into_response(),
+    }
+}
+
 // Helper function to extract Bearer token from Authorization header
 fn extract_bearer_token(request: &Request) -> Option<String> {
     let auth_header = request.headers().get(AUTHORIZATION)?.to_str().ok()?;
@@ -0,0 +1,77 @@
+use axum::extract::{Request, State};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+use crate::domain::response::Response as ApiResponse;
+use crate::domain::user::model::{Impersonator, MfaPending, Role};
+use crate::entities::users::Model as User;
+
+/// Middleware function to require a minimum role on a router or route
+///
+/// Must be layered inside `auth_middleware`, which puts the authenticated user
+/// into the request extensions. Usage:
+/// `router.route_layer(middleware::from_fn_with_state(Role::Admin, require_role))`
+pub async fn require_role(
+    State(min_role): State<Role>,
+    request: Request,
+    next: Next,
+) -> Result<Response, Response> {
+    // Get the authenticated user from request extensions
+    let user = request.extensions().get::<User>().ok_or_else(|| {
+        ApiResponse::<()>::failure_unauthorized(
+            "Authentication required",
+            Some("No authenticated user found for this request".to_string()),
+        )
+        .into_response()
+    })?;
+
+    if user.role < min_role {
+        return Err(ApiResponse::<()>::failure_forbidden(
+            "Access forbidden",
+            Some(format!("This action requires the {:?} role", min_role)),
+        )
+        .into_response());
+    }
+
+    // Continue with the next middleware
+    Ok(next.run(request).await)
+}
+
+/// Middleware function to block sessions that still have to complete two-factor authentication
+///
+/// Must be layered inside `auth_middleware`, which marks sessions of users whose role
+/// requires a second factor they have not completed. Usage:
+/// `router.route_layer(middleware::from_fn(require_mfa))`
+pub async fn require_mfa(request: Request, next: Next) -> Result<Response, Response> {
+    if request.extensions().get::<MfaPending>().is_some() {
+        return Err(ApiResponse::<()>::failure_forbidden(
+            "Two-factor authentication required",
+            Some(
+                "Your role requires two-factor authentication; enable it with /auth/mfa/totp/enroll"
+                    .to_string(),
+            ),
+        )
+        .into_response());
+    }
+
+    // Continue with the next middleware
+    Ok(next.run(request).await)
+}
+
+/// Middleware function to block requests made by an admin impersonating the user
+///
+/// Must be layered inside `auth_middleware`, which marks requests made with an
+/// impersonation token. Usage: `router.route_layer(middleware::from_fn(forbid_impersonation))`
+pub async fn forbid_impersonation(request: Request, next: Next) -> Result<Response, Response> {
+    if let Some(Impersonator(admin_id)) = request.extensions().get::<Impersonator>() {
+        tracing::info!(%admin_id, path = %request.uri().path(), "Blocked an impersonated request");
+        return Err(ApiResponse::<()>::failure_forbidden(
+            "Access forbidden",
+            Some("This action is not available while impersonating a user".to_string()),
+        )
+        .into_response());
+    }
+
+    // Continue with the next middleware
+    Ok(next.run(request).await)
+}
@@ -1,3 +1,7 @@
 pub mod auth;
+pub mod authorization;
+pub mod rate_limit;
 
 pub use auth::auth_middleware;
+pub use authorization::{forbid_impersonation, require_mfa, require_role};
+pub use rate_limit::rate_limit;
@@ -0,0 +1,67 @@
+use axum::extract::{FromRequestParts, Request, State};
+use axum::http::header::RETRY_AFTER;
+use axum::http::HeaderValue;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use std::sync::Arc;
+
+use crate::domain::response::Response as ApiResponse;
+use crate::entities::users::Model as User;
+use crate::utils::client_info::ClientInfo;
+use crate::utils::rate_limit::{RateLimitScope, RateLimiter};
+
+/// Middleware function to rate limit a router or route
+///
+/// Requests are counted per authenticated user when `auth_middleware` ran first,
+/// and per client IP otherwise. The shared `RateLimiter` is read from the request
+/// extensions. Usage:
+/// `router.route_layer(middleware::from_fn_with_state(RateLimitScope::Auth, rate_limit))`
+pub async fn rate_limit(
+    State(scope): State<RateLimitScope>,
+    request: Request,
+    next: Next,
+) -> Result<Response, Response> {
+    let limiter = request
+        .extensions()
+        .get::<Arc<RateLimiter>>()
+        .cloned()
+        .ok_or_else(|| {
+            ApiResponse::<()>::failure_internal(
+                "Internal server error",
+                Some("Rate limiter is not configured".to_string()),
+            )
+            .into_response()
+        })?;
+
+    // Identify the client
+    let (mut parts, body) = request.into_parts();
+    let client_key = match parts.extensions.get::<User>() {
+        Some(user) => format!("user:{}", user.id),
+        None => {
+            let client = ClientInfo::from_request_parts(&mut parts, &())
+                .await
+                .unwrap_or_default();
+            format!("ip:{}", client.ip_address.unwrap_or_default())
+        }
+    };
+    let request = Request::from_parts(parts, body);
+
+    if let Err(retry_after) = limiter.check(scope, &client_key) {
+        let retry_after_secs = (retry_after.as_secs_f64().ceil() as u64).max(1);
+        let mut response = ApiResponse::<()>::failure_too_many_requests(
+            "Too many requests",
+            Some(format!(
+                "Rate limit exceeded, retry in {} seconds",
+                retry_after_secs
+            )),
+        )
+        .into_response();
+        response
+            .headers_mut()
+            .insert(RETRY_AFTER, HeaderValue::from(retry_after_secs));
+        return Err(response);
+    }
+
+    // Continue with the next middleware
+    Ok(next.run(request).await)
+}
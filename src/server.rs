@@ -4,14 +4,19 @@ use axum::http::{header, Method, Response, StatusCode, Uri};
 use axum::middleware;
 use axum::response::{IntoResponse, Json};
 use axum::routing::get;
-use axum::{extract::Path, Router};
+use axum::{extract::Path, Extension, Router};
 use std::net::{IpAddr, SocketAddr};
-use tower_http::cors::{Any, CorsLayer};
+use tower_http::cors::{AllowHeaders, AllowOrigin, Any, CorsLayer};
 
 use crate::domain::admin;
+use crate::domain::api_key::protected_router as api_key_protected_router;
 use crate::domain::healthcheck::router as healthcheck_router;
-use crate::domain::user::router as user_router;
+use crate::domain::jwks::router as jwks_router;
+use crate::domain::mfa::protected_router as mfa_protected_router;
+use crate::domain::oidc::router as oidc_router;
+use crate::domain::user::{protected_router as user_protected_router, router as user_router};
 use crate::middleware::auth_middleware;
+use crate::settings::Server as ServerSettings;
 
 use serde_json::json;
 
@@ -48,25 +53,54 @@ async fn fallback(uri: Uri) -> impl IntoResponse {
     (StatusCode::NOT_FOUND, body)
 }
 
+/// CORS for browser apps on other origins. Listed origins may send cookies; without a
+/// list any origin is allowed, but browsers then leave cookies out.
+fn cors_layer(settings: &ServerSettings) -> anyhow::Result<CorsLayer> {
+    let cors = CorsLayer::new().allow_methods([
+        Method::GET,
+        Method::POST,
+        Method::PUT,
+        Method::PATCH,
+        Method::DELETE,
+        Method::OPTIONS,
+    ]);
+
+    let origins = settings
+        .cors_allowed_origins
+        .split(',')
+        .map(str::trim)
+        .filter(|origin| !origin.is_empty())
+        .map(|origin| {
+            origin
+                .parse()
+                .with_context(|| format!("Invalid CORS origin '{}'", origin))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    if origins.is_empty() {
+        return Ok(cors.allow_origin(Any).allow_headers(Any));
+    }
+
+    // Credentials rule out wildcards, so the requested headers are echoed instead
+    Ok(cors
+        .allow_origin(AllowOrigin::list(origins))
+        .allow_headers(AllowHeaders::mirror_request())
+        .allow_credentials(true))
+}
+
 /// Serve the application routes
 pub async fn serve(state: &AppState) -> anyhow::Result<()> {
     // CORS setup
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods([
-            Method::GET,
-            Method::POST,
-            Method::PUT,
-            Method::DELETE,
-            Method::OPTIONS,
-        ])
-        .allow_headers(Any);
+    let cors = cors_layer(&state.settings.server)?;
 
     // Shared state
     let shared_state = std::sync::Arc::new(state.clone());
 
     // Protected routes (require authentication)
     let protected_routes = Router::new()
+        .nest("/auth", user_protected_router())
+        .nest("/auth/mfa", mfa_protected_router())
+        .nest("/auth/api-keys", api_key_protected_router())
         .merge(admin::router())
         .layer(middleware::from_fn_with_state(
             state.clone(),
@@ -77,7 +111,9 @@ pub async fn serve(state: &AppState) -> anyhow::Result<()> {
     // Public routes
     let app = Router::new()
         .nest("/healthcheck", healthcheck_router())
+        .nest("/.well-known", jwks_router())
         .nest("/auth", user_router())
+        .nest("/auth/oidc", oidc_router())
         // Serve static files from the embedded assets
         .route(
             "/admin",
@@ -90,6 +126,8 @@ pub async fn serve(state: &AppState) -> anyhow::Result<()> {
         .with_state(shared_state)
         .merge(protected_routes)
         .fallback(fallback)
+        .layer(Extension(state.rate_limiter.clone()))
+        .layer(Extension(state.trusted_proxies.clone()))
         .layer(cors);
 
     // Server host ip
@@ -109,10 +147,13 @@ pub async fn serve(state: &AppState) -> anyhow::Result<()> {
         .await
         .context("failed to bind TCP listener")?;
 
-    // Start server
-    axum::serve(listener, app)
-        .await
-        .context("axum::serve failed")?;
+    // Start server (with peer addresses available to the client info extractor)
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .context("axum::serve failed")?;
 
     Ok(())
 }
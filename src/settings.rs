@@ -1,5 +1,7 @@
+use chrono::{DateTime, Utc};
 use config::{Config, Environment};
 use serde::Deserialize;
+use std::collections::HashMap;
 
 // Define the Database struct to hold the database configuration
 #[derive(Debug, Deserialize, Clone, PartialEq)]
@@ -40,15 +42,54 @@ pub struct Server {
     pub env: ServerEnv, // e.g., "dev", "prod"
     pub host: String,
     pub port: u16,
+    #[serde(default)]
+    pub trusted_proxies: String, // Comma-separated IPs or CIDR ranges whose forwarding headers are believed
+    #[serde(default)]
+    pub cors_allowed_origins: String, // Comma-separated origins allowed to send credentials; any origin, without credentials, when empty
 }
 
 // Define the Jwt struct to hold the JWT configuration
 #[derive(Debug, Deserialize, Clone)]
 #[allow(unused)]
 pub struct Jwt {
-    pub secret: String,
+    pub secret: String, // HS256 secret, used for signing while no `active_kid` is set
     pub access_token_expiration_hours: i64,
     pub refresh_token_expiration_hours: i64,
+    #[serde(default = "default_jwt_issuer")]
+    pub issuer: String, // `iss` claim of every token
+    #[serde(default = "default_jwt_audience")]
+    pub audience: String, // `aud` claim of access tokens, checked by other services
+    pub active_kid: Option<String>, // Key from `keys` that signs new tokens
+    #[serde(default)]
+    pub keys: HashMap<String, JwtKey>, // Asymmetric signing keys by kid (lowercase, e.g. APP__JWT__KEYS__2026_01__ALGORITHM)
+    pub secret_retired_at: Option<DateTime<Utc>>, // Keep accepting HS256 tokens after switching to `active_kid`
+    pub retired_key_grace_hours: Option<i64>,     // Defaults to the refresh token lifetime
+}
+
+fn default_jwt_issuer() -> String {
+    "chill-labs".to_string()
+}
+
+fn default_jwt_audience() -> String {
+    "chill-labs-api".to_string()
+}
+
+// Define an enum for the asymmetric JWT algorithms
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+pub enum JwtAlgorithm {
+    #[serde(rename = "RS256", alias = "rs256")]
+    Rs256,
+    #[serde(rename = "EdDSA", alias = "eddsa")]
+    EdDsa,
+}
+
+// Define the JwtKey struct to hold one asymmetric signing key
+#[derive(Debug, Deserialize, Clone)]
+#[allow(unused)]
+pub struct JwtKey {
+    pub algorithm: JwtAlgorithm,
+    pub private_key_file: String, // PEM file (PKCS#1 or PKCS#8 for RSA, PKCS#8 for Ed25519)
+    pub retired_at: Option<DateTime<Utc>>, // Verified for `retired_key_grace_hours` after this
 }
 
 // Define the Admin struct to hold the admin user configuration
@@ -59,6 +100,479 @@ pub struct Admin {
     pub password: String,
 }
 
+// Define an enum for the mail transport
+#[derive(Debug, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "lowercase")] // Allows "smtp", "file", "memory" or "log"
+pub enum MailerKind {
+    Smtp,
+    File,
+    Memory,
+    #[default]
+    Log,
+}
+
+// Define the Email struct to hold the email configuration
+#[derive(Debug, Deserialize, Clone)]
+#[allow(unused)]
+pub struct Email {
+    #[serde(default)]
+    pub mailer: MailerKind, // e.g., "smtp" in production, "file" / "memory" / "log" for dev and tests
+    pub smtp_host: Option<String>,
+    pub smtp_port: Option<u16>,
+    pub smtp_username: Option<String>,
+    pub smtp_password: Option<String>,
+    #[serde(default = "default_from_email")]
+    pub from_email: String,
+    #[serde(default = "default_from_name")]
+    pub from_name: String,
+    pub file_dir: Option<String>, // Directory for the file mailer
+    #[serde(default = "default_verification_url_base")]
+    pub verification_url_base: String, // The token is appended as `?token=...`
+    #[serde(default = "default_password_reset_url_base")]
+    pub password_reset_url_base: String, // The token is appended as `?token=...`
+    #[serde(default = "default_magic_link_url_base")]
+    pub magic_link_url_base: String, // The token is appended as `/{token}`
+}
+
+impl Default for Email {
+    fn default() -> Self {
+        Self {
+            mailer: MailerKind::default(),
+            smtp_host: None,
+            smtp_port: None,
+            smtp_username: None,
+            smtp_password: None,
+            from_email: default_from_email(),
+            from_name: default_from_name(),
+            file_dir: None,
+            verification_url_base: default_verification_url_base(),
+            password_reset_url_base: default_password_reset_url_base(),
+            magic_link_url_base: default_magic_link_url_base(),
+        }
+    }
+}
+
+fn default_from_email() -> String {
+    "no-reply@chilllabs.local".to_string()
+}
+
+fn default_from_name() -> String {
+    "Chill Labs".to_string()
+}
+
+fn default_verification_url_base() -> String {
+    "http://localhost:3000/auth/verify-email".to_string()
+}
+
+fn default_password_reset_url_base() -> String {
+    "http://localhost:3000/reset-password".to_string()
+}
+
+fn default_magic_link_url_base() -> String {
+    "http://localhost:3000/auth/magic-link".to_string()
+}
+
+// Define the Auth struct to hold the authentication feature configuration
+#[derive(Debug, Deserialize, Clone)]
+#[allow(unused)]
+pub struct Auth {
+    #[serde(default)]
+    pub require_email_verification: bool, // New accounts stay Pending until the email is verified
+    #[serde(default = "default_email_verification_expiration_hours")]
+    pub email_verification_expiration_hours: i64,
+    #[serde(default = "default_password_reset_expiration_minutes")]
+    pub password_reset_expiration_minutes: i64,
+    #[serde(default = "default_magic_link_expiration_minutes")]
+    pub magic_link_expiration_minutes: i64,
+    #[serde(default = "default_mfa_issuer")]
+    pub mfa_issuer: String, // Shown next to the account in authenticator apps
+    #[serde(default = "default_mfa_pending_expiration_minutes")]
+    pub mfa_pending_expiration_minutes: i64, // Time to enter the code after the password
+    #[serde(default = "default_identity_cache_seconds")]
+    pub identity_cache_seconds: u64, // How long a verified access token skips the database, 0 to disable
+    #[serde(default = "default_impersonation_expiration_minutes")]
+    pub impersonation_expiration_minutes: i64, // Lifetime of the access token issued to an impersonating admin
+}
+
+impl Default for Auth {
+    fn default() -> Self {
+        Self {
+            require_email_verification: false,
+            email_verification_expiration_hours: default_email_verification_expiration_hours(),
+            password_reset_expiration_minutes: default_password_reset_expiration_minutes(),
+            magic_link_expiration_minutes: default_magic_link_expiration_minutes(),
+            mfa_issuer: default_mfa_issuer(),
+            mfa_pending_expiration_minutes: default_mfa_pending_expiration_minutes(),
+            identity_cache_seconds: default_identity_cache_seconds(),
+            impersonation_expiration_minutes: default_impersonation_expiration_minutes(),
+        }
+    }
+}
+
+fn default_email_verification_expiration_hours() -> i64 {
+    24
+}
+
+fn default_password_reset_expiration_minutes() -> i64 {
+    30
+}
+
+fn default_magic_link_expiration_minutes() -> i64 {
+    10
+}
+
+fn default_mfa_issuer() -> String {
+    "Chill Labs".to_string()
+}
+
+fn default_mfa_pending_expiration_minutes() -> i64 {
+    5
+}
+
+fn default_identity_cache_seconds() -> u64 {
+    30
+}
+
+fn default_impersonation_expiration_minutes() -> i64 {
+    15
+}
+
+// Define the Password struct to hold the password hashing configuration
+#[derive(Debug, Deserialize, Clone)]
+pub struct Password {
+    #[serde(default = "default_memory_cost_kib")]
+    pub memory_cost_kib: u32, // Argon2 memory size in KiB
+    #[serde(default = "default_time_cost")]
+    pub time_cost: u32, // Argon2 iterations
+    #[serde(default = "default_parallelism")]
+    pub parallelism: u32, // Argon2 lanes
+    pub pepper: Option<String>, // Server-side secret mixed into new hashes; never store it in the database
+    #[serde(default = "default_pepper_id")]
+    pub pepper_id: String, // Recorded in each peppered hash (at most 8 bytes); change it with the pepper
+    #[serde(default)]
+    pub retired_peppers: HashMap<String, String>, // Earlier peppers by id, still accepted until rehashed
+    #[serde(default)]
+    pub policy: PasswordPolicy,
+}
+
+impl Default for Password {
+    fn default() -> Self {
+        Self {
+            memory_cost_kib: default_memory_cost_kib(),
+            time_cost: default_time_cost(),
+            parallelism: default_parallelism(),
+            pepper: None,
+            pepper_id: default_pepper_id(),
+            retired_peppers: HashMap::new(),
+            policy: PasswordPolicy::default(),
+        }
+    }
+}
+
+fn default_memory_cost_kib() -> u32 {
+    19 * 1024
+}
+
+fn default_time_cost() -> u32 {
+    2
+}
+
+fn default_parallelism() -> u32 {
+    1
+}
+
+fn default_pepper_id() -> String {
+    "1".to_string()
+}
+
+// Define the PasswordPolicy struct to hold the rules new passwords must follow
+#[derive(Debug, Deserialize, Clone)]
+pub struct PasswordPolicy {
+    #[serde(default = "default_password_min_length")]
+    pub min_length: usize, // In characters
+    #[serde(default = "default_password_max_length")]
+    pub max_length: usize, // Bounds the hashing work per request
+    #[serde(default)]
+    pub require_lowercase: bool,
+    #[serde(default)]
+    pub require_uppercase: bool,
+    #[serde(default)]
+    pub require_digit: bool,
+    #[serde(default)]
+    pub require_symbol: bool,
+    #[serde(default = "default_reject_personal_info")]
+    pub reject_personal_info: bool, // Reject passwords containing the username or email
+    #[serde(default = "default_reject_common")]
+    pub reject_common: bool, // Reject passwords from the bundled list of common and breached ones
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: default_password_min_length(),
+            max_length: default_password_max_length(),
+            require_lowercase: false,
+            require_uppercase: false,
+            require_digit: false,
+            require_symbol: false,
+            reject_personal_info: default_reject_personal_info(),
+            reject_common: default_reject_common(),
+        }
+    }
+}
+
+fn default_password_min_length() -> usize {
+    8
+}
+
+fn default_password_max_length() -> usize {
+    128
+}
+
+fn default_reject_personal_info() -> bool {
+    true
+}
+
+fn default_reject_common() -> bool {
+    true
+}
+
+// Define an enum for where failed login attempts are counted
+#[derive(Debug, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "lowercase")] // Allows "memory" or "postgres"
+pub enum LoginAttemptStoreKind {
+    #[default]
+    Memory,
+    Postgres,
+}
+
+// Define the LoginProtection struct to hold the brute-force protection configuration
+#[derive(Debug, Deserialize, Clone)]
+#[allow(unused)]
+pub struct LoginProtection {
+    #[serde(default)]
+    pub store: LoginAttemptStoreKind, // "postgres" shares the counters between instances
+    #[serde(default = "default_max_failures_per_account")]
+    pub max_failures_per_account: u32,
+    #[serde(default = "default_max_failures_per_ip")]
+    pub max_failures_per_ip: u32,
+    #[serde(default = "default_failure_window_minutes")]
+    pub failure_window_minutes: i64, // Failures older than this are forgotten
+    #[serde(default = "default_lockout_base_seconds")]
+    pub lockout_base_seconds: i64, // Doubled for every failure past the threshold
+    #[serde(default = "default_lockout_max_minutes")]
+    pub lockout_max_minutes: i64,
+}
+
+impl Default for LoginProtection {
+    fn default() -> Self {
+        Self {
+            store: LoginAttemptStoreKind::default(),
+            max_failures_per_account: default_max_failures_per_account(),
+            max_failures_per_ip: default_max_failures_per_ip(),
+            failure_window_minutes: default_failure_window_minutes(),
+            lockout_base_seconds: default_lockout_base_seconds(),
+            lockout_max_minutes: default_lockout_max_minutes(),
+        }
+    }
+}
+
+fn default_max_failures_per_account() -> u32 {
+    5
+}
+
+fn default_max_failures_per_ip() -> u32 {
+    20
+}
+
+fn default_failure_window_minutes() -> i64 {
+    15
+}
+
+fn default_lockout_base_seconds() -> i64 {
+    60
+}
+
+fn default_lockout_max_minutes() -> i64 {
+    60
+}
+
+// Define the RateLimitBudget struct to hold one token bucket configuration
+#[derive(Debug, Deserialize, Clone)]
+#[allow(unused)]
+pub struct RateLimitBudget {
+    pub capacity: u32,          // Burst size
+    pub refill_per_minute: u32, // Sustained requests per minute
+}
+
+// Define the RateLimit struct to hold the request rate limiting configuration
+#[derive(Debug, Deserialize, Clone)]
+#[allow(unused)]
+pub struct RateLimit {
+    #[serde(default = "default_rate_limit_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_auth_rate_limit")]
+    pub auth: RateLimitBudget, // `/auth/*`
+    #[serde(default = "default_admin_rate_limit")]
+    pub admin: RateLimitBudget, // Admin user management
+    #[serde(default = "default_learning_rate_limit")]
+    pub learning: RateLimitBudget, // Lessons, notes, words and sentences
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self {
+            enabled: default_rate_limit_enabled(),
+            auth: default_auth_rate_limit(),
+            admin: default_admin_rate_limit(),
+            learning: default_learning_rate_limit(),
+        }
+    }
+}
+
+fn default_rate_limit_enabled() -> bool {
+    true
+}
+
+fn default_auth_rate_limit() -> RateLimitBudget {
+    RateLimitBudget {
+        capacity: 20,
+        refill_per_minute: 10,
+    }
+}
+
+fn default_admin_rate_limit() -> RateLimitBudget {
+    RateLimitBudget {
+        capacity: 60,
+        refill_per_minute: 60,
+    }
+}
+
+fn default_learning_rate_limit() -> RateLimitBudget {
+    RateLimitBudget {
+        capacity: 120,
+        refill_per_minute: 300,
+    }
+}
+
+// Define an enum for how tokens are handed to browsers
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")] // Allows "off", "cookie" or "both"
+pub enum CookieMode {
+    #[default]
+    Off, // Tokens only in response bodies, sent back as Bearer tokens
+    Cookie, // Tokens only in HttpOnly cookies
+    Both,   // Tokens in cookies and response bodies
+}
+
+// Define an enum for the SameSite cookie attribute
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")] // Allows "strict", "lax" or "none"
+pub enum CookieSameSite {
+    Strict,
+    #[default]
+    Lax,
+    None,
+}
+
+// Define the Cookies struct to hold the cookie-based auth configuration
+#[derive(Debug, Deserialize, Clone)]
+#[allow(unused)]
+pub struct Cookies {
+    #[serde(default)]
+    pub mode: CookieMode,
+    #[serde(default = "default_cookie_secure")]
+    pub secure: bool, // Disable only for local development over plain HTTP
+    #[serde(default)]
+    pub same_site: CookieSameSite,
+    pub domain: Option<String>, // Defaults to the host that set the cookie
+    #[serde(default = "default_access_cookie_name")]
+    pub access_cookie_name: String,
+    #[serde(default = "default_refresh_cookie_name")]
+    pub refresh_cookie_name: String,
+    #[serde(default = "default_csrf_cookie_name")]
+    pub csrf_cookie_name: String, // Readable by scripts, echoed back in `csrf_header_name`
+    #[serde(default = "default_csrf_header_name")]
+    pub csrf_header_name: String,
+}
+
+impl Default for Cookies {
+    fn default() -> Self {
+        Self {
+            mode: CookieMode::default(),
+            secure: default_cookie_secure(),
+            same_site: CookieSameSite::default(),
+            domain: None,
+            access_cookie_name: default_access_cookie_name(),
+            refresh_cookie_name: default_refresh_cookie_name(),
+            csrf_cookie_name: default_csrf_cookie_name(),
+            csrf_header_name: default_csrf_header_name(),
+        }
+    }
+}
+
+fn default_cookie_secure() -> bool {
+    true
+}
+
+fn default_access_cookie_name() -> String {
+    "access_token".to_string()
+}
+
+fn default_refresh_cookie_name() -> String {
+    "refresh_token".to_string()
+}
+
+fn default_csrf_cookie_name() -> String {
+    "csrf_token".to_string()
+}
+
+fn default_csrf_header_name() -> String {
+    "x-csrf-token".to_string()
+}
+
+// Define the OidcProvider struct to hold one OpenID Connect provider
+#[derive(Debug, Deserialize, Clone)]
+#[allow(unused)]
+pub struct OidcProvider {
+    pub display_name: Option<String>,
+    pub issuer: String, // Discovery document at `<issuer>/.well-known/openid-configuration`
+    pub client_id: String,
+    pub client_secret: String,
+    pub redirect_uri: String, // Frontend page that receives `code` and `state` and posts them back
+    #[serde(default = "default_oidc_scopes")]
+    pub scopes: String,
+    #[serde(default)]
+    pub link_verified_email: bool, // Sign in to an existing account with the same verified email
+}
+
+fn default_oidc_scopes() -> String {
+    "openid email profile".to_string()
+}
+
+// Define the Oidc struct to hold the social login configuration
+#[derive(Debug, Deserialize, Clone)]
+#[allow(unused)]
+pub struct Oidc {
+    #[serde(default)]
+    pub providers: HashMap<String, OidcProvider>, // By name, e.g. APP__OIDC__PROVIDERS__GOOGLE__CLIENT_ID
+    #[serde(default = "default_oidc_state_expiration_minutes")]
+    pub state_expiration_minutes: i64, // Time to complete the login at the provider
+}
+
+impl Default for Oidc {
+    fn default() -> Self {
+        Self {
+            providers: HashMap::new(),
+            state_expiration_minutes: default_oidc_state_expiration_minutes(),
+        }
+    }
+}
+
+fn default_oidc_state_expiration_minutes() -> i64 {
+    10
+}
+
 // Define the Settings struct to hold all the configuration settings
 #[derive(Debug, Deserialize, Clone)]
 #[allow(unused)]
@@ -69,6 +583,20 @@ pub struct Settings {
     pub server: Server,
     pub jwt: Jwt,
     pub admin: Admin,
+    #[serde(default)]
+    pub email: Email,
+    #[serde(default)]
+    pub auth: Auth,
+    #[serde(default)]
+    pub password: Password,
+    #[serde(default)]
+    pub login_protection: LoginProtection,
+    #[serde(default)]
+    pub rate_limit: RateLimit,
+    #[serde(default)]
+    pub cookies: Cookies,
+    #[serde(default)]
+    pub oidc: Oidc,
 }
 
 // Implement the Settings struct
@@ -1,22 +1,43 @@
 use crate::entities::users;
-use crate::utils::password::hash_password;
 use sea_orm::{
     ActiveModelTrait, ColumnTrait, ConnectOptions, DatabaseConnection, EntityTrait, QueryFilter,
     Set,
 };
+use std::sync::Arc;
 use std::time::Duration;
 
 // Auth domain
-use crate::domain::user::service::UserService;
+use crate::domain::api_key::service::ApiKeyService;
+use crate::domain::audit::service::AuditService;
+use crate::domain::mfa::service::MfaService;
+use crate::domain::oidc::service::OidcService;
+use crate::domain::user::model::{Role, UserStatus};
+use crate::domain::user::service::{IdentityCache, UserService};
 
 // Settings
 use crate::settings::Settings;
 
+// Utils
+use crate::utils::client_info::TrustedProxies;
+use crate::utils::email::{build_mailer, EmailService};
+use crate::utils::jwt::JwtUtil;
+use crate::utils::login_throttle::{build_login_attempt_store, LoginThrottle};
+use crate::utils::password::PasswordUtil;
+use crate::utils::rate_limit::RateLimiter;
+
 #[derive(Clone)]
 pub struct AppState {
     pub settings: Settings,
     pub db: DatabaseConnection,
+    pub jwt_util: JwtUtil,
+    pub password_util: PasswordUtil,
     pub user_service: UserService,
+    pub mfa_service: MfaService,
+    pub oidc_service: OidcService,
+    pub api_key_service: ApiKeyService,
+    pub audit_service: AuditService,
+    pub rate_limiter: Arc<RateLimiter>,
+    pub trusted_proxies: Arc<TrustedProxies>,
 }
 
 impl AppState {
@@ -25,29 +46,85 @@ impl AppState {
         // Initialize database connection
         let db = init_db(settings).await?;
 
+        // Initialize password hashing
+        let password_util = PasswordUtil::from_settings(&settings.password)?;
+
         // Seed admin user
-        seed_admin_user(&db, settings).await?;
+        seed_admin_user(&db, &password_util, settings).await?;
+
+        // Initialize JWT signing
+        let jwt_util = JwtUtil::from_settings(&settings.jwt)?;
+
+        // Initialize email service
+        let mailer = build_mailer(&settings.email)?;
+        let email_service = EmailService::new(mailer, &settings.email);
 
-        // Initialize JWT secret
-        let jwt_secret = settings.jwt.secret.clone();
+        // Initialize login brute-force protection
+        let login_attempt_store = build_login_attempt_store(&settings.login_protection, db.clone());
+        let login_throttle =
+            LoginThrottle::new(login_attempt_store, settings.login_protection.clone());
 
-        // Initialize JWT expiration hours
-        let access_token_expiration_hours = settings.jwt.access_token_expiration_hours;
-        let refresh_token_expiration_hours = settings.jwt.refresh_token_expiration_hours;
+        // Initialize the cache of verified access tokens
+        let identity_cache = Arc::new(IdentityCache::new(Duration::from_secs(
+            settings.auth.identity_cache_seconds,
+        )));
+
+        // Initialize audit log
+        let audit_service = AuditService::new(db.clone());
+
+        // Initialize MFA service
+        let mfa_service = MfaService::new(
+            db.clone(),
+            settings.auth.clone(),
+            identity_cache.clone(),
+            password_util.clone(),
+            audit_service.clone(),
+        );
 
         // Initialize user service
         let user_service = UserService::new(
             db.clone(),
-            &jwt_secret,
-            access_token_expiration_hours,
-            refresh_token_expiration_hours,
+            jwt_util.clone(),
+            email_service,
+            settings.auth.clone(),
+            login_throttle,
+            mfa_service.clone(),
+            identity_cache,
+            password_util.clone(),
+            audit_service.clone(),
         );
 
+        // Initialize social login
+        let oidc_service = OidcService::new(
+            db.clone(),
+            settings.oidc.clone(),
+            user_service.clone(),
+            password_util.clone(),
+        )?;
+
+        // Initialize API keys
+        let api_key_service =
+            ApiKeyService::new(db.clone(), mfa_service.clone(), audit_service.clone());
+
+        // Initialize rate limiter
+        let rate_limiter = Arc::new(RateLimiter::new(settings.rate_limit.clone()));
+
+        // Proxies allowed to report the client IP
+        let trusted_proxies = Arc::new(TrustedProxies::parse(&settings.server.trusted_proxies)?);
+
         // Initialize state
         Ok(Self {
             settings: settings.clone(),
             db,
+            jwt_util,
+            password_util,
             user_service,
+            mfa_service,
+            oidc_service,
+            api_key_service,
+            audit_service,
+            rate_limiter,
+            trusted_proxies,
         })
     }
 }
@@ -76,7 +153,11 @@ async fn init_db(settings: &Settings) -> anyhow::Result<DatabaseConnection> {
     Ok(db)
 }
 
-async fn seed_admin_user(db: &DatabaseConnection, settings: &Settings) -> anyhow::Result<()> {
+async fn seed_admin_user(
+    db: &DatabaseConnection,
+    password_util: &PasswordUtil,
+    settings: &Settings,
+) -> anyhow::Result<()> {
     // Get admin email and password from settings (required fields)
     let email = &settings.admin.email;
     let password = &settings.admin.password;
@@ -88,17 +169,18 @@ async fn seed_admin_user(db: &DatabaseConnection, settings: &Settings) -> anyhow
 
     if existing_user.is_none() {
         tracing::info!("Seeding admin user: {}", email);
-        let password_hash = hash_password(password)?;
+        let password_hash = password_util.hash_password(password)?;
         let now = chrono::Utc::now().with_timezone(&chrono::FixedOffset::east_opt(0).unwrap());
 
         let user = users::ActiveModel {
             id: Set(uuid::Uuid::now_v7()),
             username: Set(Some("admin".to_string())),
             email: Set(Some(email.clone())),
+            pending_email: Set(None),
             display_name: Set(Some("Admin User".to_string())),
             password_hash: Set(password_hash),
-            role: Set("admin".to_string()),
-            status: Set("active".to_string()),
+            role: Set(Role::Admin),
+            status: Set(UserStatus::Active),
             created: Set(now),
             updated: Set(now),
         };
@@ -0,0 +1,272 @@
+use anyhow::Context;
+use axum::extract::{ConnectInfo, FromRequestParts};
+use axum::http::header::USER_AGENT;
+use axum::http::request::Parts;
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+/// Device metadata of the client making a request
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl<S> FromRequestParts<S> for ClientInfo
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let user_agent = parts
+            .headers
+            .get(USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string());
+
+        Ok(Self {
+            ip_address: client_ip(parts).map(|ip| ip.to_string()),
+            user_agent,
+        })
+    }
+}
+
+/// Reverse proxies whose forwarding headers are believed, from `server.trusted_proxies`
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies {
+    networks: Vec<(IpAddr, u8)>, // Address and prefix length
+}
+
+impl TrustedProxies {
+    /// Parse a comma-separated list of addresses and CIDR ranges, e.g. "10.0.0.0/8, ::1"
+    pub fn parse(list: &str) -> anyhow::Result<Self> {
+        let networks = list
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (address, prefix) = match entry.split_once('/') {
+                    Some((address, prefix)) => (address, Some(prefix)),
+                    None => (entry, None),
+                };
+                let address: IpAddr = address
+                    .parse()
+                    .with_context(|| format!("Invalid trusted proxy '{}'", entry))?;
+                let max_prefix = if address.is_ipv4() { 32 } else { 128 };
+                let prefix = match prefix {
+                    Some(prefix) => prefix
+                        .parse::<u8>()
+                        .ok()
+                        .filter(|prefix| *prefix <= max_prefix)
+                        .with_context(|| format!("Invalid trusted proxy '{}'", entry))?,
+                    None => max_prefix,
+                };
+                // An IPv4-mapped range is matched as the IPv4 range it maps to
+                let canonical = address.to_canonical();
+                let prefix = if canonical.is_ipv4() && address.is_ipv6() {
+                    prefix
+                        .checked_sub(96)
+                        .with_context(|| format!("Invalid trusted proxy '{}'", entry))?
+                } else {
+                    prefix
+                };
+                Ok((canonical, prefix))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self { networks })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let (ip, length) = address_bits(ip.to_canonical());
+        self.networks.iter().any(|(network, prefix)| {
+            let (network, network_length) = address_bits(*network);
+            // Shifting by the full length would overflow for a /0 IPv6 range
+            let shift = u32::from(length - prefix.min(&length));
+            network_length == length && (shift == 128 || network >> shift == ip >> shift)
+        })
+    }
+}
+
+// Address as an integer, with its length in bits
+fn address_bits(ip: IpAddr) -> (u128, u8) {
+    match ip {
+        IpAddr::V4(ip) => (u32::from(ip).into(), 32),
+        IpAddr::V6(ip) => (u128::from(ip), 128),
+    }
+}
+
+// The address of the connection, unless it is a trusted proxy: then the client is the
+// nearest address in X-Forwarded-For that is not a trusted proxy (entries to the left
+// of it could have been made up by the client), or else X-Real-IP
+fn client_ip(parts: &Parts) -> Option<IpAddr> {
+    let socket_ip = parts
+        .extensions
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip().to_canonical());
+
+    let trusted_proxies = parts.extensions.get::<Arc<TrustedProxies>>();
+    let (Some(peer), Some(trusted_proxies)) = (socket_ip, trusted_proxies) else {
+        return socket_ip;
+    };
+    if !trusted_proxies.contains(peer) {
+        return Some(peer);
+    }
+
+    let forwarded_for: Vec<&str> = parts
+        .headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|hop| !hop.is_empty())
+        .collect();
+
+    if !forwarded_for.is_empty() {
+        let mut client = peer;
+        for hop in forwarded_for.into_iter().rev() {
+            // A malformed hop was not written by a trusted proxy, so stop at the last good one
+            let Ok(hop) = hop.parse::<IpAddr>() else {
+                break;
+            };
+            client = hop.to_canonical();
+            if !trusted_proxies.contains(client) {
+                break;
+            }
+        }
+        return Some(client);
+    }
+
+    let real_ip = parts
+        .headers
+        .get("x-real-ip")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<IpAddr>().ok())
+        .map(|ip| ip.to_canonical());
+
+    real_ip.or(Some(peer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Request;
+
+    fn ip(address: &str) -> IpAddr {
+        address.parse().unwrap()
+    }
+
+    // Request parts as seen behind `peer`, with the given proxies trusted
+    fn parts(peer: &str, trusted: Option<&str>, headers: &[(&str, &str)]) -> Parts {
+        let mut request = Request::builder();
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let (mut parts, _) = request.body(()).unwrap().into_parts();
+        parts
+            .extensions
+            .insert(ConnectInfo(SocketAddr::new(ip(peer), 443)));
+        if let Some(trusted) = trusted {
+            parts
+                .extensions
+                .insert(Arc::new(TrustedProxies::parse(trusted).unwrap()));
+        }
+        parts
+    }
+
+    #[test]
+    fn parses_addresses_and_ranges() {
+        let proxies = TrustedProxies::parse(" 10.0.0.0/8, 192.168.1.1 ,, fd00::/8").unwrap();
+        assert!(proxies.contains(ip("10.1.2.3")));
+        assert!(!proxies.contains(ip("11.0.0.1")));
+        assert!(proxies.contains(ip("192.168.1.1")));
+        assert!(!proxies.contains(ip("192.168.1.2")));
+        assert!(proxies.contains(ip("fd12::1")));
+        assert!(!proxies.contains(ip("fe80::1")));
+    }
+
+    #[test]
+    fn rejects_invalid_entries() {
+        assert!(TrustedProxies::parse("not-an-ip").is_err());
+        assert!(TrustedProxies::parse("10.0.0.0/33").is_err());
+        assert!(TrustedProxies::parse("::1/129").is_err());
+        assert!(TrustedProxies::parse("10.0.0.0/x").is_err());
+        assert!(TrustedProxies::parse("::ffff:10.0.0.0/64").is_err());
+        assert!(TrustedProxies::parse("").unwrap().networks.is_empty());
+    }
+
+    #[test]
+    fn matches_ipv4_mapped_ipv6_addresses() {
+        let proxies = TrustedProxies::parse("127.0.0.1").unwrap();
+        assert!(proxies.contains(ip("::ffff:127.0.0.1")));
+
+        let proxies = TrustedProxies::parse("::ffff:10.0.0.0/104").unwrap();
+        assert!(proxies.contains(ip("10.0.0.1")));
+        assert!(!proxies.contains(ip("11.0.0.1")));
+    }
+
+    #[test]
+    fn zero_length_ranges_match_their_whole_family() {
+        let proxies = TrustedProxies::parse("0.0.0.0/0, ::/0").unwrap();
+        assert!(proxies.contains(ip("203.0.113.7")));
+        assert!(proxies.contains(ip("2001:db8::1")));
+
+        let proxies = TrustedProxies::parse("0.0.0.0/0").unwrap();
+        assert!(!proxies.contains(ip("2001:db8::1")));
+    }
+
+    #[test]
+    fn ignores_forwarding_headers_without_trusted_proxies() {
+        let headers = [
+            ("x-forwarded-for", "203.0.113.7"),
+            ("x-real-ip", "203.0.113.8"),
+        ];
+        let parts = parts("198.51.100.1", None, &headers);
+        assert_eq!(client_ip(&parts), Some(ip("198.51.100.1")));
+    }
+
+    #[test]
+    fn ignores_forwarding_headers_from_untrusted_peers() {
+        let headers = [("x-forwarded-for", "203.0.113.7")];
+        let parts = parts("198.51.100.1", Some("10.0.0.0/8"), &headers);
+        assert_eq!(client_ip(&parts), Some(ip("198.51.100.1")));
+    }
+
+    #[test]
+    fn takes_nearest_untrusted_forwarded_address() {
+        // The leftmost entry was sent by the client and cannot be believed
+        let headers = [("x-forwarded-for", "1.1.1.1, 203.0.113.7, 10.0.0.2")];
+        let parts = parts("10.0.0.1", Some("10.0.0.0/8"), &headers);
+        assert_eq!(client_ip(&parts), Some(ip("203.0.113.7")));
+    }
+
+    #[test]
+    fn reads_forwarded_addresses_across_header_lines() {
+        let headers = [
+            ("x-forwarded-for", "203.0.113.7"),
+            ("x-forwarded-for", "10.0.0.2"),
+        ];
+        let parts = parts("10.0.0.1", Some("10.0.0.0/8"), &headers);
+        assert_eq!(client_ip(&parts), Some(ip("203.0.113.7")));
+    }
+
+    #[test]
+    fn stops_at_malformed_forwarded_address() {
+        let headers = [("x-forwarded-for", "203.0.113.7, garbage, 10.0.0.2")];
+        let parts = parts("10.0.0.1", Some("10.0.0.0/8"), &headers);
+        assert_eq!(client_ip(&parts), Some(ip("10.0.0.2")));
+    }
+
+    #[test]
+    fn falls_back_to_real_ip_then_peer() {
+        let headers = [("x-real-ip", " 203.0.113.7 ")];
+        let parts_with_real_ip = parts("10.0.0.1", Some("10.0.0.0/8"), &headers);
+        assert_eq!(client_ip(&parts_with_real_ip), Some(ip("203.0.113.7")));
+
+        let parts = parts("10.0.0.1", Some("10.0.0.0/8"), &[]);
+        assert_eq!(client_ip(&parts), Some(ip("10.0.0.1")));
+    }
+}
@@ -0,0 +1,118 @@
+use axum::http::HeaderMap;
+use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
+
+use crate::settings::{CookieSameSite, Cookies as CookieSettings};
+use crate::utils::token::generate_token;
+
+const CSRF_TOKEN_LENGTH: usize = 32;
+
+// Refresh tokens are only sent to the auth endpoints that use them
+const REFRESH_COOKIE_PATH: &str = "/auth";
+
+/// Store freshly issued tokens in HttpOnly cookies, along with a new CSRF token
+/// that scripts can read and echo back in a header
+pub fn set_token_cookies(
+    settings: &CookieSettings,
+    jar: CookieJar,
+    access_token: &str,
+    access_lifetime: chrono::Duration,
+    refresh_token: &str,
+    refresh_lifetime: chrono::Duration,
+) -> CookieJar {
+    let csrf_token = generate_token(CSRF_TOKEN_LENGTH);
+
+    let mut csrf_cookie = build_cookie(settings, &settings.csrf_cookie_name, &csrf_token, "/");
+    csrf_cookie.set_http_only(false);
+    csrf_cookie.set_max_age(to_time_duration(refresh_lifetime));
+
+    let mut access_cookie = build_cookie(settings, &settings.access_cookie_name, access_token, "/");
+    access_cookie.set_max_age(to_time_duration(access_lifetime));
+
+    let mut refresh_cookie = build_cookie(
+        settings,
+        &settings.refresh_cookie_name,
+        refresh_token,
+        REFRESH_COOKIE_PATH,
+    );
+    refresh_cookie.set_max_age(to_time_duration(refresh_lifetime));
+
+    jar.add(access_cookie).add(refresh_cookie).add(csrf_cookie)
+}
+
+/// Expire the token and CSRF cookies
+pub fn clear_token_cookies(settings: &CookieSettings, jar: CookieJar) -> CookieJar {
+    jar.remove(build_cookie(
+        settings,
+        &settings.access_cookie_name,
+        "",
+        "/",
+    ))
+    .remove(build_cookie(
+        settings,
+        &settings.refresh_cookie_name,
+        "",
+        REFRESH_COOKIE_PATH,
+    ))
+    .remove(build_cookie(settings, &settings.csrf_cookie_name, "", "/"))
+}
+
+pub fn access_token_cookie(settings: &CookieSettings, jar: &CookieJar) -> Option<String> {
+    cookie_value(jar, &settings.access_cookie_name)
+}
+
+pub fn refresh_token_cookie(settings: &CookieSettings, jar: &CookieJar) -> Option<String> {
+    cookie_value(jar, &settings.refresh_cookie_name)
+}
+
+/// Double-submit check: the CSRF header must match the CSRF cookie.
+/// Other sites can make the browser send our cookies, but cannot read them to set the header.
+pub fn verify_csrf(settings: &CookieSettings, jar: &CookieJar, headers: &HeaderMap) -> bool {
+    let Some(expected) = cookie_value(jar, &settings.csrf_cookie_name) else {
+        return false;
+    };
+    let Some(provided) = headers
+        .get(settings.csrf_header_name.as_str())
+        .and_then(|value| value.to_str().ok())
+    else {
+        return false;
+    };
+
+    // Compare in constant time
+    expected.len() == provided.len()
+        && expected
+            .bytes()
+            .zip(provided.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn cookie_value(jar: &CookieJar, name: &str) -> Option<String> {
+    jar.get(name)
+        .map(|cookie| cookie.value().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+fn build_cookie(
+    settings: &CookieSettings,
+    name: &str,
+    value: &str,
+    path: &'static str,
+) -> Cookie<'static> {
+    let mut cookie = Cookie::build((name.to_string(), value.to_string()))
+        .path(path)
+        .http_only(true)
+        .secure(settings.secure)
+        .same_site(match settings.same_site {
+            CookieSameSite::Strict => SameSite::Strict,
+            CookieSameSite::Lax => SameSite::Lax,
+            CookieSameSite::None => SameSite::None,
+        });
+    if let Some(domain) = &settings.domain {
+        cookie = cookie.domain(domain.clone());
+    }
+    cookie.build()
+}
+
+fn to_time_duration(duration: chrono::Duration) -> time::Duration {
+    time::Duration::seconds(duration.num_seconds())
+}
@@ -0,0 +1,327 @@
+use anyhow::Context;
+use async_trait::async_trait;
+use lettre::message::{header::ContentType, Mailbox, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use crate::settings::{Email as EmailSettings, MailerKind};
+
+/// An outgoing email
+#[derive(Debug, Clone)]
+pub struct EmailMessage {
+    pub to_email: String,
+    pub to_name: Option<String>,
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: String,
+}
+
+/// Transport used to deliver emails
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, message: &EmailMessage) -> anyhow::Result<()>;
+}
+
+// ============= Mailer implementations =============
+
+/// Delivers emails through an SMTP relay
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    pub fn new(settings: &EmailSettings) -> anyhow::Result<Self> {
+        let host = settings
+            .smtp_host
+            .as_deref()
+            .context("email.smtp_host is required for the smtp mailer")?;
+
+        let mut builder = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)
+            .context("Failed to create SMTP transport")?;
+
+        if let Some(port) = settings.smtp_port {
+            builder = builder.port(port);
+        }
+
+        if let (Some(username), Some(password)) = (&settings.smtp_username, &settings.smtp_password)
+        {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            from: sender_mailbox(settings)?,
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, message: &EmailMessage) -> anyhow::Result<()> {
+        let to = Mailbox::new(
+            message.to_name.clone(),
+            message
+                .to_email
+                .parse()
+                .context("Invalid recipient address")?,
+        );
+
+        let email = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(&message.subject)
+            .multipart(
+                MultiPart::alternative()
+                    .singlepart(
+                        SinglePart::builder()
+                            .header(ContentType::TEXT_PLAIN)
+                            .body(message.text_body.clone()),
+                    )
+                    .singlepart(
+                        SinglePart::builder()
+                            .header(ContentType::TEXT_HTML)
+                            .body(message.html_body.clone()),
+                    ),
+            )
+            .context("Failed to build email")?;
+
+        self.transport
+            .send(email)
+            .await
+            .context("Failed to send email")?;
+
+        Ok(())
+    }
+}
+
+/// Writes each email to a text file, for local development
+pub struct FileMailer {
+    dir: PathBuf,
+}
+
+impl FileMailer {
+    pub fn new(dir: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir).context("Failed to create mail directory")?;
+        Ok(Self { dir })
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, message: &EmailMessage) -> anyhow::Result<()> {
+        let file_name = format!("{}.txt", uuid::Uuid::now_v7());
+        let content = format!(
+            "To: {}\nSubject: {}\n\n{}\n",
+            message.to_email, message.subject, message.text_body
+        );
+
+        tokio::fs::write(self.dir.join(file_name), content)
+            .await
+            .context("Failed to write email file")?;
+
+        Ok(())
+    }
+}
+
+/// Keeps sent emails in memory so they can be inspected, for tests
+#[derive(Default)]
+pub struct MemoryMailer {
+    messages: Mutex<Vec<EmailMessage>>,
+}
+
+#[allow(unused)]
+impl MemoryMailer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// All emails sent so far
+    pub fn messages(&self) -> Vec<EmailMessage> {
+        self.messages.lock().unwrap().clone()
+    }
+
+    /// The most recent email sent to an address
+    pub fn last_message_to(&self, email: &str) -> Option<EmailMessage> {
+        self.messages
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|message| message.to_email == email)
+            .cloned()
+    }
+}
+
+#[async_trait]
+impl Mailer for MemoryMailer {
+    async fn send(&self, message: &EmailMessage) -> anyhow::Result<()> {
+        self.messages.lock().unwrap().push(message.clone());
+        Ok(())
+    }
+}
+
+/// Logs emails instead of sending them
+pub struct LogMailer;
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, message: &EmailMessage) -> anyhow::Result<()> {
+        tracing::info!(
+            "Email to {} - {}\n{}",
+            message.to_email,
+            message.subject,
+            message.text_body
+        );
+        Ok(())
+    }
+}
+
+// Build the mailer selected in the settings
+pub fn build_mailer(settings: &EmailSettings) -> anyhow::Result<Arc<dyn Mailer>> {
+    let mailer: Arc<dyn Mailer> = match settings.mailer {
+        MailerKind::Smtp => Arc::new(SmtpMailer::new(settings)?),
+        MailerKind::File => Arc::new(FileMailer::new(
+            settings.file_dir.as_deref().unwrap_or("mail"),
+        )?),
+        MailerKind::Memory => Arc::new(MemoryMailer::new()),
+        MailerKind::Log => Arc::new(LogMailer),
+    };
+    Ok(mailer)
+}
+
+fn sender_mailbox(settings: &EmailSettings) -> anyhow::Result<Mailbox> {
+    Ok(Mailbox::new(
+        Some(settings.from_name.clone()),
+        settings
+            .from_email
+            .parse()
+            .context("Invalid email.from_email address")?,
+    ))
+}
+
+// ============= Email service =============
+
+/// Composes the application emails and sends them through a mailer
+#[derive(Clone)]
+pub struct EmailService {
+    mailer: Arc<dyn Mailer>,
+    verification_url_base: String,
+    password_reset_url_base: String,
+    magic_link_url_base: String,
+}
+
+impl EmailService {
+    pub fn new(mailer: Arc<dyn Mailer>, settings: &EmailSettings) -> Self {
+        Self {
+            mailer,
+            verification_url_base: settings.verification_url_base.clone(),
+            password_reset_url_base: settings.password_reset_url_base.clone(),
+            magic_link_url_base: settings.magic_link_url_base.clone(),
+        }
+    }
+
+    /// Send the link a new user follows to verify their email address
+    pub async fn send_verification_email(
+        &self,
+        to_email: &str,
+        to_name: Option<&str>,
+        token: &str,
+    ) -> anyhow::Result<()> {
+        let link = format!("{}?token={}", self.verification_url_base, token);
+        let greeting = greeting(to_name);
+        let html_greeting = escape_html(&greeting);
+
+        let message = EmailMessage {
+            to_email: to_email.to_string(),
+            to_name: to_name.map(|name| name.to_string()),
+            subject: "Verify your email address".to_string(),
+            text_body: format!(
+                "{}\n\nPlease verify your email address by opening this link:\n{}\n\nIf you did not create an account, you can ignore this email.",
+                greeting, link
+            ),
+            html_body: format!(
+                "<p>{}</p><p>Please verify your email address by clicking the link below:</p><p><a href=\"{}\">Verify email address</a></p><p>If you did not create an account, you can ignore this email.</p>",
+                html_greeting, link
+            ),
+        };
+
+        self.mailer.send(&message).await
+    }
+
+    /// Send the link a user follows to choose a new password
+    pub async fn send_password_reset_email(
+        &self,
+        to_email: &str,
+        to_name: Option<&str>,
+        token: &str,
+        expiration_minutes: i64,
+    ) -> anyhow::Result<()> {
+        let link = format!("{}?token={}", self.password_reset_url_base, token);
+        let greeting = greeting(to_name);
+        let html_greeting = escape_html(&greeting);
+
+        let message = EmailMessage {
+            to_email: to_email.to_string(),
+            to_name: to_name.map(|name| name.to_string()),
+            subject: "Reset your password".to_string(),
+            text_body: format!(
+                "{}\n\nWe received a request to reset your password. Open this link to choose a new one:\n{}\n\nThe link expires in {} minutes and can only be used once. If you did not request a password reset, you can ignore this email.",
+                greeting, link, expiration_minutes
+            ),
+            html_body: format!(
+                "<p>{}</p><p>We received a request to reset your password. Click the link below to choose a new one:</p><p><a href=\"{}\">Reset password</a></p><p>The link expires in {} minutes and can only be used once. If you did not request a password reset, you can ignore this email.</p>",
+                html_greeting, link, expiration_minutes
+            ),
+        };
+
+        self.mailer.send(&message).await
+    }
+
+    /// Send a link that logs the user in without a password
+    pub async fn send_magic_link_email(
+        &self,
+        to_email: &str,
+        to_name: Option<&str>,
+        token: &str,
+        expiration_minutes: i64,
+    ) -> anyhow::Result<()> {
+        let link = format!("{}/{}", self.magic_link_url_base, token);
+        let greeting = greeting(to_name);
+        let html_greeting = escape_html(&greeting);
+
+        let message = EmailMessage {
+            to_email: to_email.to_string(),
+            to_name: to_name.map(|name| name.to_string()),
+            subject: "Your login link".to_string(),
+            text_body: format!(
+                "{}\n\nOpen this link to log in:\n{}\n\nThe link expires in {} minutes and can only be used once. If you did not ask to log in, you can ignore this email.",
+                greeting, link, expiration_minutes
+            ),
+            html_body: format!(
+                "<p>{}</p><p>Click the link below to log in:</p><p><a href=\"{}\">Log in</a></p><p>The link expires in {} minutes and can only be used once. If you did not ask to log in, you can ignore this email.</p>",
+                html_greeting, link, expiration_minutes
+            ),
+        };
+
+        self.mailer.send(&message).await
+    }
+}
+
+fn greeting(name: Option<&str>) -> String {
+    match name {
+        Some(name) if !name.is_empty() => format!("Hi {},", name),
+        _ => "Hi,".to_string(),
+    }
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
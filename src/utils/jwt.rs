@@ -1,13 +1,28 @@
-use anyhow::Context;
-use chrono::{Duration, Utc};
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use anyhow::{bail, Context};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use ed25519_dalek::pkcs8::DecodePrivateKey;
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+    OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse,
+};
+use jsonwebtoken::{
+    decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::settings::{Jwt as JwtSettings, JwtAlgorithm, JwtKey};
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub enum TokenType {
     Access,
     Refresh,
+    EmailVerification,
+    MfaPending,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -17,78 +32,375 @@ pub struct Claims {
     pub iat: usize,            // Issued at (as UTC timestamp)
     pub email: String,         // User email
     pub token_type: TokenType, // Token type (access or refresh)
+    pub jti: String,           // Unique token ID (tracked server-side for refresh tokens)
+    #[serde(default)]
+    pub sid: String, // Session ID
+    #[serde(default)]
+    pub role: String, // User role when the token was issued
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<Actor>, // Admin acting as the user, only on impersonation tokens
+    pub iss: String,           // Issuer
+    pub aud: String, // Audience (the API for access tokens, the issuer for internal tokens)
+}
+
+/// Party acting on behalf of the subject (RFC 8693 `act` claim)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Actor {
+    pub sub: String, // Admin user ID
+}
+
+// User a token is issued to
+struct TokenSubject<'a> {
+    user_id: Uuid,
+    email: &'a str,
+    role: &'a str,
+}
+
+// Key accepted when verifying tokens
+struct VerificationKey {
+    algorithm: Algorithm,
+    decoding_key: DecodingKey,
+    jwk: Option<Jwk>,                  // Public key, None for the HS256 secret
+    expires_at: Option<DateTime<Utc>>, // End of the grace window for retired keys
+}
+
+impl VerificationKey {
+    fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| now >= expires_at)
+    }
 }
 
 // JWT utility struct
 #[derive(Clone)]
 pub struct JwtUtil {
+    signing_kid: Option<String>,
+    signing_algorithm: Algorithm,
     encoding_key: EncodingKey,
-    decoding_key: DecodingKey,
+    verification_keys: Arc<HashMap<Option<String>, VerificationKey>>, // By kid, None for the HS256 secret
+    issuer: String,
+    audience: String,
     access_token_expiration_hours: i64,
     refresh_token_expiration_hours: i64,
 }
 
 impl JwtUtil {
-    pub fn new(
-        secret: &str,
-        access_token_expiration_hours: i64,
-        refresh_token_expiration_hours: i64,
-    ) -> Self {
-        let encoding_key = EncodingKey::from_secret(secret.as_bytes());
-        let decoding_key = DecodingKey::from_secret(secret.as_bytes());
-        Self {
-            encoding_key,
-            decoding_key,
-            access_token_expiration_hours,
-            refresh_token_expiration_hours,
+    /// Load the signing and verification keys from the JWT settings.
+    ///
+    /// Tokens are signed with the key named by `active_kid`, or with the HS256 secret
+    /// while none is set. Every other configured key keeps verifying tokens (and is
+    /// published in the JWKS) until its grace window after `retired_at` has passed.
+    pub fn from_settings(settings: &JwtSettings) -> anyhow::Result<Self> {
+        let grace = Duration::hours(
+            settings
+                .retired_key_grace_hours
+                .unwrap_or(settings.refresh_token_expiration_hours),
+        );
+
+        let mut verification_keys = HashMap::new();
+        let mut signing_key = None;
+
+        for (kid, key) in &settings.keys {
+            let (encoding_key, decoding_key, jwk) = load_key(kid, key)
+                .with_context(|| format!("Failed to load JWT signing key '{}'", kid))?;
+            let algorithm = algorithm_for(key.algorithm);
+
+            if settings.active_kid.as_deref() == Some(kid.as_str()) {
+                if key.retired_at.is_some() {
+                    bail!("JWT signing key '{}' is active but marked as retired", kid);
+                }
+                signing_key = Some((Some(kid.clone()), algorithm, encoding_key));
+            }
+
+            verification_keys.insert(
+                Some(kid.clone()),
+                VerificationKey {
+                    algorithm,
+                    decoding_key,
+                    jwk: Some(jwk),
+                    expires_at: key.retired_at.map(|retired_at| retired_at + grace),
+                },
+            );
+        }
+
+        // The HS256 secret signs until an asymmetric key is activated,
+        // after which it is only accepted if explicitly retired with a grace window
+        let secret = settings.secret.as_bytes();
+        let secret_expires_at = match &settings.active_kid {
+            None => {
+                signing_key = Some((None, Algorithm::HS256, EncodingKey::from_secret(secret)));
+                Some(None)
+            }
+            Some(_) => settings
+                .secret_retired_at
+                .map(|retired_at| Some(retired_at + grace)),
+        };
+        if let Some(expires_at) = secret_expires_at {
+            verification_keys.insert(
+                None,
+                VerificationKey {
+                    algorithm: Algorithm::HS256,
+                    decoding_key: DecodingKey::from_secret(secret),
+                    jwk: None,
+                    expires_at,
+                },
+            );
         }
+
+        let (signing_kid, signing_algorithm, encoding_key) = signing_key.with_context(|| {
+            format!(
+                "JWT active_kid '{}' has no matching key in jwt.keys",
+                settings.active_kid.as_deref().unwrap_or_default()
+            )
+        })?;
+
+        Ok(Self {
+            signing_kid,
+            signing_algorithm,
+            encoding_key,
+            verification_keys: Arc::new(verification_keys),
+            issuer: settings.issuer.clone(),
+            audience: settings.audience.clone(),
+            access_token_expiration_hours: settings.access_token_expiration_hours,
+            refresh_token_expiration_hours: settings.refresh_token_expiration_hours,
+        })
     }
 
-    pub fn generate_access_token(&self, user_id: Uuid, email: &str) -> anyhow::Result<String> {
+    pub fn generate_access_token(
+        &self,
+        user_id: Uuid,
+        email: &str,
+        role: &str,
+        session_id: Uuid,
+    ) -> anyhow::Result<String> {
         self.generate_token_internal(
-            user_id,
-            email,
+            TokenSubject {
+                user_id,
+                email,
+                role,
+            },
+            Some(session_id),
+            None,
             TokenType::Access,
-            self.access_token_expiration_hours,
+            Uuid::now_v7(),
+            Duration::hours(self.access_token_expiration_hours),
         )
     }
 
-    pub fn generate_refresh_token(&self, user_id: Uuid, email: &str) -> anyhow::Result<String> {
+    pub fn generate_refresh_token(
+        &self,
+        user_id: Uuid,
+        email: &str,
+        role: &str,
+        session_id: Uuid,
+        jti: Uuid,
+    ) -> anyhow::Result<String> {
         self.generate_token_internal(
-            user_id,
-            email,
+            TokenSubject {
+                user_id,
+                email,
+                role,
+            },
+            Some(session_id),
+            None,
             TokenType::Refresh,
-            self.refresh_token_expiration_hours,
+            jti,
+            Duration::hours(self.refresh_token_expiration_hours),
         )
     }
 
-    fn generate_token_internal(
+    /// Signed token proving ownership of an email address
+    pub fn generate_email_verification_token(
         &self,
         user_id: Uuid,
         email: &str,
-        token_type: TokenType,
         expiration_hours: i64,
+    ) -> anyhow::Result<String> {
+        self.generate_token_internal(
+            TokenSubject {
+                user_id,
+                email,
+                role: "",
+            },
+            None,
+            None,
+            TokenType::EmailVerification,
+            Uuid::now_v7(),
+            Duration::hours(expiration_hours),
+        )
+    }
+
+    /// Short-lived token issued after a correct password, exchanged for full tokens
+    /// with a valid second factor
+    pub fn generate_mfa_pending_token(
+        &self,
+        user_id: Uuid,
+        email: &str,
+        expiration_minutes: i64,
+    ) -> anyhow::Result<String> {
+        self.generate_token_internal(
+            TokenSubject {
+                user_id,
+                email,
+                role: "",
+            },
+            None,
+            None,
+            TokenType::MfaPending,
+            Uuid::now_v7(),
+            Duration::minutes(expiration_minutes),
+        )
+    }
+
+    /// Short-lived access token for an admin acting as another user.
+    /// The admin is named in the `act` claim; no refresh token goes with it.
+    pub fn generate_impersonation_token(
+        &self,
+        user_id: Uuid,
+        email: &str,
+        role: &str,
+        session_id: Uuid,
+        admin_id: Uuid,
+        expiration_minutes: i64,
+    ) -> anyhow::Result<String> {
+        self.generate_token_internal(
+            TokenSubject {
+                user_id,
+                email,
+                role,
+            },
+            Some(session_id),
+            Some(admin_id),
+            TokenType::Access,
+            Uuid::now_v7(),
+            Duration::minutes(expiration_minutes),
+        )
+    }
+
+    pub fn refresh_token_expiration_hours(&self) -> i64 {
+        self.refresh_token_expiration_hours
+    }
+
+    fn generate_token_internal(
+        &self,
+        subject: TokenSubject<'_>,
+        session_id: Option<Uuid>,
+        actor_id: Option<Uuid>,
+        token_type: TokenType,
+        jti: Uuid,
+        lifetime: Duration,
     ) -> anyhow::Result<String> {
         let now = Utc::now();
-        let expiration = now + Duration::hours(expiration_hours);
+        let expiration = now + lifetime;
+
+        let audience = self.audience_for(&token_type).to_string();
 
         let claims = Claims {
-            sub: user_id.to_string(),
+            sub: subject.user_id.to_string(),
             exp: expiration.timestamp() as usize,
             iat: now.timestamp() as usize,
-            email: email.to_string(),
+            email: subject.email.to_string(),
             token_type,
+            jti: jti.to_string(),
+            sid: session_id.map(|id| id.to_string()).unwrap_or_default(),
+            role: subject.role.to_string(),
+            act: actor_id.map(|id| Actor { sub: id.to_string() }),
+            iss: self.issuer.clone(),
+            aud: audience,
         };
 
-        let token = encode(&Header::default(), &claims, &self.encoding_key)
-            .context("Failed to generate token")?;
+        let mut header = Header::new(self.signing_algorithm);
+        header.kid = self.signing_kid.clone();
+
+        let token =
+            encode(&header, &claims, &self.encoding_key).context("Failed to generate token")?;
         Ok(token)
     }
 
-    pub fn verify_token(&self, token: &str) -> anyhow::Result<Claims> {
-        let token_data = decode::<Claims>(token, &self.decoding_key, &Validation::default())
+    // Only access tokens are meant for other services
+    fn audience_for(&self, token_type: &TokenType) -> &str {
+        match token_type {
+            TokenType::Access => &self.audience,
+            _ => &self.issuer,
+        }
+    }
+
+    /// Verify a token, accepting only the audience its expected type is issued for
+    pub fn verify_token(&self, token: &str, token_type: TokenType) -> anyhow::Result<Claims> {
+        let header = decode_header(token).context("Failed to decode token")?;
+
+        // Pick the key named in the header, pinning its algorithm
+        let key = self
+            .verification_keys
+            .get(&header.kid)
+            .context("Token signed with an unknown key")?;
+        if key.is_expired(Utc::now()) {
+            bail!("Token signed with a retired key");
+        }
+
+        let mut validation = Validation::new(key.algorithm);
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_audience(&[self.audience_for(&token_type)]);
+
+        let token_data = decode::<Claims>(token, &key.decoding_key, &validation)
             .context("Failed to decode token")?;
 
         Ok(token_data.claims)
     }
+
+    /// Public keys currently accepted for verification, as a JWK set
+    pub fn jwks(&self) -> JwkSet {
+        let now = Utc::now();
+        let mut keys: Vec<Jwk> = self
+            .verification_keys
+            .values()
+            .filter(|key| !key.is_expired(now))
+            .filter_map(|key| key.jwk.clone())
+            .collect();
+        keys.sort_by(|a, b| a.common.key_id.cmp(&b.common.key_id));
+
+        JwkSet { keys }
+    }
+}
+
+fn algorithm_for(algorithm: JwtAlgorithm) -> Algorithm {
+    match algorithm {
+        JwtAlgorithm::Rs256 => Algorithm::RS256,
+        JwtAlgorithm::EdDsa => Algorithm::EdDSA,
+    }
+}
+
+// Read a private key file and derive its verification key and public JWK
+fn load_key(kid: &str, key: &JwtKey) -> anyhow::Result<(EncodingKey, DecodingKey, Jwk)> {
+    let pem = std::fs::read_to_string(&key.private_key_file)
+        .with_context(|| format!("Failed to read {}", key.private_key_file))?;
+
+    let (encoding_key, mut jwk) = match key.algorithm {
+        JwtAlgorithm::Rs256 => {
+            let encoding_key = EncodingKey::from_rsa_pem(pem.as_bytes())?;
+            let jwk = Jwk::from_encoding_key(&encoding_key, Algorithm::RS256)?;
+            (encoding_key, jwk)
+        }
+        JwtAlgorithm::EdDsa => {
+            let encoding_key = EncodingKey::from_ed_pem(pem.as_bytes())?;
+            let signing_key = ed25519_dalek::SigningKey::from_pkcs8_pem(&pem)
+                .map_err(|e| anyhow::anyhow!("Invalid Ed25519 key: {}", e))?;
+            let jwk = Jwk {
+                common: CommonParameters {
+                    key_algorithm: Some(KeyAlgorithm::EdDSA),
+                    ..Default::default()
+                },
+                algorithm: AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                    key_type: OctetKeyPairType::OctetKeyPair,
+                    curve: EllipticCurve::Ed25519,
+                    x: URL_SAFE_NO_PAD.encode(signing_key.verifying_key().as_bytes()),
+                }),
+            };
+            (encoding_key, jwk)
+        }
+    };
+    jwk.common.key_id = Some(kid.to_string());
+    jwk.common.public_key_use = Some(PublicKeyUse::Signature);
+
+    let decoding_key = DecodingKey::from_jwk(&jwk)?;
+    Ok((encoding_key, decoding_key, jwk))
 }
@@ -0,0 +1,346 @@
+use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset};
+use sea_orm::sea_query::{Expr, OnConflict};
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Set};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::entities::login_attempts::{self, Entity as LoginAttemptsEntity};
+use crate::settings::{LoginAttemptStoreKind, LoginProtection as LoginProtectionSettings};
+
+/// Failed login attempts counted for one key (an account or a client IP)
+#[derive(Debug, Clone)]
+pub struct LoginAttempts {
+    pub key: String,
+    pub failures: u32,
+    pub last_failure: DateTime<FixedOffset>,
+    pub locked_until: Option<DateTime<FixedOffset>>,
+}
+
+/// Storage for failed login counters
+#[async_trait]
+pub trait LoginAttemptStore: Send + Sync {
+    /// Current counters of a key
+    async fn get(&self, key: &str) -> anyhow::Result<Option<LoginAttempts>>;
+
+    /// Count one failure, starting over when the last failure happened before `window_start`
+    async fn record_failure(
+        &self,
+        key: &str,
+        now: DateTime<FixedOffset>,
+        window_start: DateTime<FixedOffset>,
+    ) -> anyhow::Result<LoginAttempts>;
+
+    /// Lock a key until the given time
+    async fn lock(&self, key: &str, until: DateTime<FixedOffset>) -> anyhow::Result<()>;
+
+    /// Forget every failure of a key
+    async fn clear(&self, key: &str) -> anyhow::Result<()>;
+
+    /// Keys starting with `prefix` that are still locked at `now`
+    async fn locked(
+        &self,
+        prefix: &str,
+        now: DateTime<FixedOffset>,
+    ) -> anyhow::Result<Vec<LoginAttempts>>;
+}
+
+// ============= Store implementations =============
+
+/// Keeps the counters in process memory, for a single instance or for tests
+#[derive(Default)]
+pub struct MemoryLoginAttemptStore {
+    attempts: Mutex<HashMap<String, LoginAttempts>>,
+}
+
+impl MemoryLoginAttemptStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl LoginAttemptStore for MemoryLoginAttemptStore {
+    async fn get(&self, key: &str) -> anyhow::Result<Option<LoginAttempts>> {
+        Ok(self.attempts.lock().unwrap().get(key).cloned())
+    }
+
+    async fn record_failure(
+        &self,
+        key: &str,
+        now: DateTime<FixedOffset>,
+        window_start: DateTime<FixedOffset>,
+    ) -> anyhow::Result<LoginAttempts> {
+        let mut attempts = self.attempts.lock().unwrap();
+
+        // Drop stale entries so the map does not grow with every client IP
+        attempts.retain(|_, entry| {
+            entry.last_failure >= window_start || entry.locked_until.is_some_and(|t| t > now)
+        });
+
+        let entry = attempts
+            .entry(key.to_string())
+            .or_insert_with(|| LoginAttempts {
+                key: key.to_string(),
+                failures: 0,
+                last_failure: now,
+                locked_until: None,
+            });
+
+        if entry.last_failure < window_start {
+            entry.failures = 0;
+        }
+        entry.failures += 1;
+        entry.last_failure = now;
+
+        Ok(entry.clone())
+    }
+
+    async fn lock(&self, key: &str, until: DateTime<FixedOffset>) -> anyhow::Result<()> {
+        if let Some(entry) = self.attempts.lock().unwrap().get_mut(key) {
+            entry.locked_until = Some(until);
+        }
+        Ok(())
+    }
+
+    async fn clear(&self, key: &str) -> anyhow::Result<()> {
+        self.attempts.lock().unwrap().remove(key);
+        Ok(())
+    }
+
+    async fn locked(
+        &self,
+        prefix: &str,
+        now: DateTime<FixedOffset>,
+    ) -> anyhow::Result<Vec<LoginAttempts>> {
+        Ok(self
+            .attempts
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|entry| entry.key.starts_with(prefix))
+            .filter(|entry| entry.locked_until.is_some_and(|t| t > now))
+            .cloned()
+            .collect())
+    }
+}
+
+/// Keeps the counters in the `login_attempts` table, shared by every instance
+pub struct PostgresLoginAttemptStore {
+    db: DatabaseConnection,
+}
+
+impl PostgresLoginAttemptStore {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl LoginAttemptStore for PostgresLoginAttemptStore {
+    async fn get(&self, key: &str) -> anyhow::Result<Option<LoginAttempts>> {
+        let model = LoginAttemptsEntity::find_by_id(key.to_string())
+            .one(&self.db)
+            .await?;
+        Ok(model.map(LoginAttempts::from))
+    }
+
+    async fn record_failure(
+        &self,
+        key: &str,
+        now: DateTime<FixedOffset>,
+        window_start: DateTime<FixedOffset>,
+    ) -> anyhow::Result<LoginAttempts> {
+        let failures = (LoginAttemptsEntity, login_attempts::Column::Failures);
+        let last_failure = (LoginAttemptsEntity, login_attempts::Column::LastFailure);
+
+        // Increment in a single upsert so concurrent failures are all counted
+        let model = login_attempts::ActiveModel {
+            key: Set(key.to_string()),
+            failures: Set(1),
+            last_failure: Set(now),
+            locked_until: Set(None),
+        };
+
+        let model = LoginAttemptsEntity::insert(model)
+            .on_conflict(
+                OnConflict::column(login_attempts::Column::Key)
+                    .value(
+                        login_attempts::Column::Failures,
+                        Expr::case(Expr::col(last_failure).lt(window_start), 1)
+                            .finally(Expr::col(failures).add(1)),
+                    )
+                    .value(login_attempts::Column::LastFailure, now)
+                    .to_owned(),
+            )
+            .exec_with_returning(&self.db)
+            .await?;
+
+        Ok(model.into())
+    }
+
+    async fn lock(&self, key: &str, until: DateTime<FixedOffset>) -> anyhow::Result<()> {
+        LoginAttemptsEntity::update_many()
+            .col_expr(login_attempts::Column::LockedUntil, Expr::value(until))
+            .filter(login_attempts::Column::Key.eq(key))
+            .exec(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    async fn clear(&self, key: &str) -> anyhow::Result<()> {
+        LoginAttemptsEntity::delete_by_id(key.to_string())
+            .exec(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    async fn locked(
+        &self,
+        prefix: &str,
+        now: DateTime<FixedOffset>,
+    ) -> anyhow::Result<Vec<LoginAttempts>> {
+        let models = LoginAttemptsEntity::find()
+            .filter(login_attempts::Column::Key.starts_with(prefix))
+            .filter(login_attempts::Column::LockedUntil.gt(now))
+            .all(&self.db)
+            .await?;
+        Ok(models.into_iter().map(LoginAttempts::from).collect())
+    }
+}
+
+impl From<login_attempts::Model> for LoginAttempts {
+    fn from(model: login_attempts::Model) -> Self {
+        Self {
+            key: model.key,
+            failures: model.failures.max(0) as u32,
+            last_failure: model.last_failure,
+            locked_until: model.locked_until,
+        }
+    }
+}
+
+// Build the store selected in the settings
+pub fn build_login_attempt_store(
+    settings: &LoginProtectionSettings,
+    db: DatabaseConnection,
+) -> Arc<dyn LoginAttemptStore> {
+    match settings.store {
+        LoginAttemptStoreKind::Memory => Arc::new(MemoryLoginAttemptStore::new()),
+        LoginAttemptStoreKind::Postgres => Arc::new(PostgresLoginAttemptStore::new(db)),
+    }
+}
+
+// ============= Login throttle =============
+
+/// What a failed login is counted against
+#[derive(Debug, Clone, Copy)]
+pub enum ThrottleKey<'a> {
+    Account(uuid::Uuid),
+    Ip(&'a str),
+}
+
+impl ThrottleKey<'_> {
+    const ACCOUNT_PREFIX: &'static str = "account:";
+    const IP_PREFIX: &'static str = "ip:";
+
+    fn to_key(self) -> String {
+        match self {
+            ThrottleKey::Account(user_id) => format!("{}{}", Self::ACCOUNT_PREFIX, user_id),
+            ThrottleKey::Ip(ip) => format!("{}{}", Self::IP_PREFIX, ip),
+        }
+    }
+}
+
+/// Locks out accounts and client IPs after repeated failed logins
+///
+/// Once a key reaches its failure threshold it is locked for `lockout_base_seconds`,
+/// and the lockout doubles with every further failure up to `lockout_max_minutes`.
+#[derive(Clone)]
+pub struct LoginThrottle {
+    store: Arc<dyn LoginAttemptStore>,
+    settings: LoginProtectionSettings,
+}
+
+impl LoginThrottle {
+    pub fn new(store: Arc<dyn LoginAttemptStore>, settings: LoginProtectionSettings) -> Self {
+        Self { store, settings }
+    }
+
+    /// Seconds until the key is unlocked, or `None` when it is not locked
+    pub async fn locked_for(&self, key: ThrottleKey<'_>) -> anyhow::Result<Option<i64>> {
+        let now = now();
+        let attempts = self.store.get(&key.to_key()).await?;
+
+        Ok(attempts
+            .and_then(|attempts| attempts.locked_until)
+            .filter(|until| *until > now)
+            .map(|until| (until - now).num_seconds().max(1)))
+    }
+
+    /// Count a failed login, locking the key once it reaches its threshold
+    pub async fn record_failure(&self, key: ThrottleKey<'_>) -> anyhow::Result<()> {
+        let now = now();
+        let window_start = now - chrono::Duration::minutes(self.settings.failure_window_minutes);
+        let attempts = self
+            .store
+            .record_failure(&key.to_key(), now, window_start)
+            .await?;
+
+        let max_failures = match key {
+            ThrottleKey::Account(_) => self.settings.max_failures_per_account,
+            ThrottleKey::Ip(_) => self.settings.max_failures_per_ip,
+        };
+
+        if attempts.failures >= max_failures {
+            let lockout = self.lockout_duration(attempts.failures - max_failures);
+            tracing::warn!(
+                "Locking {} for {} seconds after {} failed logins",
+                attempts.key,
+                lockout.num_seconds(),
+                attempts.failures
+            );
+            self.store.lock(&attempts.key, now + lockout).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Forget the failures of a key, e.g. after a successful login
+    pub async fn clear(&self, key: ThrottleKey<'_>) -> anyhow::Result<()> {
+        self.store.clear(&key.to_key()).await
+    }
+
+    /// Accounts that are currently locked, with their counters
+    pub async fn locked_accounts(&self) -> anyhow::Result<Vec<(uuid::Uuid, LoginAttempts)>> {
+        let attempts = self
+            .store
+            .locked(ThrottleKey::ACCOUNT_PREFIX, now())
+            .await?;
+
+        Ok(attempts
+            .into_iter()
+            .filter_map(|attempts| {
+                let user_id = attempts
+                    .key
+                    .strip_prefix(ThrottleKey::ACCOUNT_PREFIX)?
+                    .parse()
+                    .ok()?;
+                Some((user_id, attempts))
+            })
+            .collect())
+    }
+
+    fn lockout_duration(&self, failures_past_threshold: u32) -> chrono::Duration {
+        let max_seconds = self.settings.lockout_max_minutes * 60;
+        let seconds = self
+            .settings
+            .lockout_base_seconds
+            .saturating_mul(1 << failures_past_threshold.min(20));
+        chrono::Duration::seconds(seconds.min(max_seconds))
+    }
+}
+
+fn now() -> DateTime<FixedOffset> {
+    chrono::Utc::now().with_timezone(&chrono::FixedOffset::east_opt(0).unwrap())
+}
@@ -1,3 +1,12 @@
+pub mod cache;
+pub mod client_info;
+pub mod cookies;
+pub mod email;
 pub mod jwt;
+pub mod login_throttle;
 pub mod password;
+pub mod password_policy;
+pub mod rate_limit;
+pub mod token;
+pub mod totp;
 pub mod tracing;
@@ -1,23 +1,154 @@
+use anyhow::Context;
 use argon2::password_hash::rand_core::OsRng;
 use argon2::password_hash::{PasswordHash, SaltString};
-use argon2::{Argon2, PasswordHasher, PasswordVerifier};
-
-// Hash a password using Argon2
-pub fn hash_password(password: &str) -> anyhow::Result<String> {
-    let salt = SaltString::generate(&mut OsRng);
-    let argon2 = Argon2::default();
-    let hash = argon2
-        .hash_password(password.as_bytes(), &salt)
-        .map_err(|e| anyhow::anyhow!("Password hashing failed: {}", e))?;
-    Ok(hash.to_string())
+use argon2::{
+    Algorithm, Argon2, KeyId, Params, ParamsBuilder, PasswordHasher, PasswordVerifier, Version,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::settings::Password as PasswordSettings;
+use crate::utils::password_policy::PasswordPolicy;
+
+/// Outcome of checking a password against a stored hash
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PasswordVerification {
+    pub valid: bool,
+    /// The hash was made with other parameters or pepper than the current ones,
+    /// and should be replaced by a fresh hash of the password
+    pub needs_rehash: bool,
+}
+
+/// Hashes and verifies passwords with Argon2id using the configured parameters
+///
+/// When a pepper is configured it is used as the Argon2 secret, and its id is
+/// recorded in the hash so that hashes made before a pepper change still verify.
+#[derive(Clone)]
+pub struct PasswordUtil {
+    params: Params,
+    pepper: Option<Arc<str>>,
+    peppers: Arc<HashMap<Vec<u8>, String>>,
+    policy: PasswordPolicy,
+    /// Hash of a random password, checked when there is no account to take the time
+    /// a real check would
+    dummy_hash: Arc<str>,
+}
+
+impl PasswordUtil {
+    pub fn from_settings(settings: &PasswordSettings) -> anyhow::Result<Self> {
+        let mut builder = ParamsBuilder::new();
+        builder
+            .m_cost(settings.memory_cost_kib)
+            .t_cost(settings.time_cost)
+            .p_cost(settings.parallelism);
+
+        // Peppers are looked up by the id stored in each hash
+        let mut peppers = HashMap::new();
+        for (id, pepper) in &settings.retired_peppers {
+            peppers.insert(pepper_key_id(id)?.as_bytes().to_vec(), pepper.clone());
+        }
+        if let Some(pepper) = &settings.pepper {
+            let key_id = pepper_key_id(&settings.pepper_id)?;
+            builder.keyid(key_id);
+            peppers.insert(key_id.as_bytes().to_vec(), pepper.clone());
+        }
+
+        let params = builder
+            .build()
+            .map_err(|e| anyhow::anyhow!("Invalid Argon2 parameters: {}", e))?;
+
+        let mut password_util = Self {
+            params,
+            pepper: settings.pepper.as_deref().map(Arc::from),
+            peppers: Arc::new(peppers),
+            policy: PasswordPolicy::new(settings.policy.clone()),
+            dummy_hash: Arc::from(""),
+        };
+        let dummy_password = SaltString::generate(&mut OsRng);
+        password_util.dummy_hash = password_util.hash_password(dummy_password.as_str())?.into();
+
+        Ok(password_util)
+    }
+
+    /// Rules new passwords must follow
+    pub fn policy(&self) -> &PasswordPolicy {
+        &self.policy
+    }
+
+    // Hash a password using Argon2
+    pub fn hash_password(&self, password: &str) -> anyhow::Result<String> {
+        let salt = SaltString::generate(&mut OsRng);
+        let argon2 = self.argon2(self.pepper.as_deref())?;
+        let hash = argon2
+            .hash_password(password.as_bytes(), &salt)
+            .map_err(|e| anyhow::anyhow!("Password hashing failed: {}", e))?;
+        Ok(hash.to_string())
+    }
+
+    // Verify a password using Argon2, reporting hashes made with outdated parameters
+    pub fn verify_password(
+        &self,
+        password: &str,
+        hash: &str,
+    ) -> anyhow::Result<PasswordVerification> {
+        let parsed_hash =
+            PasswordHash::new(hash).map_err(|e| anyhow::anyhow!("Invalid password hash: {}", e))?;
+        let hash_params = Params::try_from(&parsed_hash)
+            .map_err(|e| anyhow::anyhow!("Invalid password hash parameters: {}", e))?;
+
+        // Hashes made without a pepper carry no key id
+        let pepper = match hash_params.keyid() {
+            [] => None,
+            key_id => Some(
+                self.peppers
+                    .get(key_id)
+                    .context("Password hash uses an unknown pepper")?
+                    .as_str(),
+            ),
+        };
+
+        let valid = self
+            .argon2(pepper)?
+            .verify_password(password.as_bytes(), &parsed_hash)
+            .is_ok();
+
+        let needs_rehash = parsed_hash.algorithm != Algorithm::Argon2id.ident()
+            || parsed_hash.version != Some(Version::V0x13.into())
+            || hash_params.m_cost() != self.params.m_cost()
+            || hash_params.t_cost() != self.params.t_cost()
+            || hash_params.p_cost() != self.params.p_cost()
+            || hash_params.keyid() != self.params.keyid();
+
+        Ok(PasswordVerification {
+            valid,
+            needs_rehash: valid && needs_rehash,
+        })
+    }
+
+    // Check a password against the dummy hash, so that a login for an unknown account
+    // takes as long as one with a wrong password
+    pub fn verify_dummy_password(&self, password: &str) {
+        let _ = self.verify_password(password, &self.dummy_hash);
+    }
+
+    fn argon2<'a>(&self, pepper: Option<&'a str>) -> anyhow::Result<Argon2<'a>> {
+        match pepper {
+            Some(pepper) => Argon2::new_with_secret(
+                pepper.as_bytes(),
+                Algorithm::Argon2id,
+                Version::V0x13,
+                self.params.clone(),
+            )
+            .map_err(|e| anyhow::anyhow!("Invalid password pepper: {}", e)),
+            None => Ok(Argon2::new(
+                Algorithm::Argon2id,
+                Version::V0x13,
+                self.params.clone(),
+            )),
+        }
+    }
 }
 
-// Verify a password using Argon2
-pub fn verify_password(password: &str, hash: &str) -> anyhow::Result<bool> {
-    let parsed_hash =
-        PasswordHash::new(hash).map_err(|e| anyhow::anyhow!("Invalid password hash: {}", e))?;
-    let argon2 = Argon2::default();
-    Ok(argon2
-        .verify_password(password.as_bytes(), &parsed_hash)
-        .is_ok())
+fn pepper_key_id(id: &str) -> anyhow::Result<KeyId> {
+    KeyId::new(id.as_bytes()).map_err(|_| anyhow::anyhow!("Pepper ids must be at most 8 bytes"))
 }
@@ -0,0 +1,292 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::{Arc, LazyLock};
+
+use crate::settings::PasswordPolicy as PasswordPolicySettings;
+
+// Bundled list of common and breached passwords
+static COMMON_PASSWORDS: LazyLock<HashSet<String>> = LazyLock::new(|| {
+    include_str!("data/common_passwords.txt")
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_lowercase)
+        .collect()
+});
+
+// Shorter usernames and email names are too likely to appear in a password by chance
+const MIN_PERSONAL_INFO_LENGTH: usize = 3;
+
+/// Rule of the password policy
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PasswordRule {
+    MinLength,
+    MaxLength,
+    Lowercase,
+    Uppercase,
+    Digit,
+    Symbol,
+    PersonalInfo,
+    Common,
+}
+
+/// A rule a password breaks, with a message that can be shown to the user
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PasswordPolicyViolation {
+    pub rule: PasswordRule,
+    pub message: String,
+}
+
+/// Account details a password must not contain
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PersonalInfo<'a> {
+    pub username: Option<&'a str>,
+    pub email: Option<&'a str>,
+}
+
+/// Checks new passwords against the configured rules
+#[derive(Debug, Clone)]
+pub struct PasswordPolicy {
+    settings: Arc<PasswordPolicySettings>,
+}
+
+impl PasswordPolicy {
+    pub fn new(settings: PasswordPolicySettings) -> Self {
+        Self {
+            settings: Arc::new(settings),
+        }
+    }
+
+    /// Check a password, returning every rule it breaks
+    pub fn check(
+        &self,
+        password: &str,
+        personal_info: PersonalInfo<'_>,
+    ) -> Result<(), Vec<PasswordPolicyViolation>> {
+        let settings = &self.settings;
+        let mut violations = Vec::new();
+        let mut violation =
+            |rule, message: String| violations.push(PasswordPolicyViolation { rule, message });
+
+        let length = password.chars().count();
+        if length < settings.min_length {
+            violation(
+                PasswordRule::MinLength,
+                format!(
+                    "Password must be at least {} characters",
+                    settings.min_length
+                ),
+            );
+        }
+        if length > settings.max_length {
+            violation(
+                PasswordRule::MaxLength,
+                format!(
+                    "Password must be at most {} characters",
+                    settings.max_length
+                ),
+            );
+        }
+
+        if settings.require_lowercase && !password.chars().any(char::is_lowercase) {
+            violation(
+                PasswordRule::Lowercase,
+                "Password must contain a lowercase letter".to_string(),
+            );
+        }
+        if settings.require_uppercase && !password.chars().any(char::is_uppercase) {
+            violation(
+                PasswordRule::Uppercase,
+                "Password must contain an uppercase letter".to_string(),
+            );
+        }
+        if settings.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+            violation(
+                PasswordRule::Digit,
+                "Password must contain a digit".to_string(),
+            );
+        }
+        if settings.require_symbol && password.chars().all(char::is_alphanumeric) {
+            violation(
+                PasswordRule::Symbol,
+                "Password must contain a symbol".to_string(),
+            );
+        }
+
+        let lowercase_password = password.to_lowercase();
+
+        if settings.reject_personal_info {
+            // The name part of the email is checked too, since it is often the user's name
+            let email_name = personal_info
+                .email
+                .map(|email| email.split('@').next().unwrap_or(email));
+            let contains_personal_info = [personal_info.username, personal_info.email, email_name]
+                .into_iter()
+                .flatten()
+                .map(str::to_lowercase)
+                .filter(|value| value.chars().count() >= MIN_PERSONAL_INFO_LENGTH)
+                .any(|value| lowercase_password.contains(&value));
+
+            if contains_personal_info {
+                violation(
+                    PasswordRule::PersonalInfo,
+                    "Password must not contain your username or email".to_string(),
+                );
+            }
+        }
+
+        if settings.reject_common && COMMON_PASSWORDS.contains(&lowercase_password) {
+            violation(
+                PasswordRule::Common,
+                "Password is too common; choose one that is harder to guess".to_string(),
+            );
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy_with(configure: impl FnOnce(&mut PasswordPolicySettings)) -> PasswordPolicy {
+        let mut settings = PasswordPolicySettings::default();
+        configure(&mut settings);
+        PasswordPolicy::new(settings)
+    }
+
+    fn broken_rules(
+        policy: &PasswordPolicy,
+        password: &str,
+        info: PersonalInfo,
+    ) -> Vec<PasswordRule> {
+        match policy.check(password, info) {
+            Ok(()) => Vec::new(),
+            Err(violations) => violations.into_iter().map(|v| v.rule).collect(),
+        }
+    }
+
+    #[test]
+    fn accepts_password_meeting_default_policy() {
+        let policy = policy_with(|_| {});
+        assert_eq!(
+            policy.check("correct horse battery", PersonalInfo::default()),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn rejects_passwords_outside_length_bounds() {
+        let policy = policy_with(|s| {
+            s.min_length = 10;
+            s.max_length = 12;
+        });
+        assert_eq!(
+            broken_rules(&policy, "short", PersonalInfo::default()),
+            vec![PasswordRule::MinLength]
+        );
+        assert_eq!(
+            broken_rules(&policy, "much too long here", PersonalInfo::default()),
+            vec![PasswordRule::MaxLength]
+        );
+    }
+
+    #[test]
+    fn counts_length_in_characters() {
+        let policy = policy_with(|s| s.min_length = 8);
+        // Eight characters, but more than eight bytes
+        assert_eq!(
+            broken_rules(&policy, "ééééé123", PersonalInfo::default()),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn reports_every_missing_character_class() {
+        let policy = policy_with(|s| {
+            s.require_lowercase = true;
+            s.require_uppercase = true;
+            s.require_digit = true;
+            s.require_symbol = true;
+        });
+        assert_eq!(
+            broken_rules(&policy, "abcdefghij", PersonalInfo::default()),
+            vec![
+                PasswordRule::Uppercase,
+                PasswordRule::Digit,
+                PasswordRule::Symbol
+            ]
+        );
+        assert_eq!(
+            broken_rules(&policy, "ABCDEFGHIJ", PersonalInfo::default()),
+            vec![
+                PasswordRule::Lowercase,
+                PasswordRule::Digit,
+                PasswordRule::Symbol
+            ]
+        );
+        assert_eq!(
+            broken_rules(&policy, "Abcdefgh1!", PersonalInfo::default()),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn rejects_username_and_email_name_case_insensitively() {
+        let policy = policy_with(|_| {});
+        let info = PersonalInfo {
+            username: Some("Gandalf"),
+            email: Some("mithrandir@example.com"),
+        };
+        assert_eq!(
+            broken_rules(&policy, "xxgandalfxx", info),
+            vec![PasswordRule::PersonalInfo]
+        );
+        assert_eq!(
+            broken_rules(&policy, "MITHRANDIR-rules", info),
+            vec![PasswordRule::PersonalInfo]
+        );
+        assert_eq!(broken_rules(&policy, "you shall not pass", info), vec![]);
+    }
+
+    #[test]
+    fn ignores_personal_info_too_short_to_matter() {
+        let policy = policy_with(|_| {});
+        let info = PersonalInfo {
+            username: Some("al"),
+            email: Some("al@example.com"),
+        };
+        assert_eq!(broken_rules(&policy, "totally alright", info), vec![]);
+    }
+
+    #[test]
+    fn personal_info_rule_can_be_disabled() {
+        let policy = policy_with(|s| s.reject_personal_info = false);
+        let info = PersonalInfo {
+            username: Some("gandalf"),
+            email: None,
+        };
+        assert_eq!(broken_rules(&policy, "gandalf the grey", info), vec![]);
+    }
+
+    #[test]
+    fn rejects_common_passwords_case_insensitively() {
+        let policy = policy_with(|_| {});
+        assert_eq!(
+            broken_rules(&policy, "Password1", PersonalInfo::default()),
+            vec![PasswordRule::Common]
+        );
+
+        let policy = policy_with(|s| s.reject_common = false);
+        assert_eq!(
+            broken_rules(&policy, "Password1", PersonalInfo::default()),
+            vec![]
+        );
+    }
+}
@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::settings::{RateLimit as RateLimitSettings, RateLimitBudget};
+
+// At most this many clients are tracked at once
+const MAX_TRACKED_BUCKETS: usize = 10_000;
+
+// Buckets kept when making room, so the next eviction is many new clients away
+const BUCKETS_KEPT_ON_EVICTION: usize = MAX_TRACKED_BUCKETS * 9 / 10;
+
+/// Group of routes sharing a request budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RateLimitScope {
+    Auth,
+    Admin,
+    Learning,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// In-memory token bucket rate limiter with one bucket per scope and client
+pub struct RateLimiter {
+    settings: RateLimitSettings,
+    buckets: Mutex<HashMap<(RateLimitScope, String), Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(settings: RateLimitSettings) -> Self {
+        Self {
+            settings,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take one token from the client's bucket, or return how long to wait for the next one
+    pub fn check(&self, scope: RateLimitScope, client_key: &str) -> Result<(), Duration> {
+        if !self.settings.enabled {
+            return Ok(());
+        }
+
+        let budget = budget_for(&self.settings, scope);
+        let capacity = f64::from(budget.capacity);
+        let refill_per_second = f64::from(budget.refill_per_minute) / 60.0;
+        let now = Instant::now();
+
+        let mut buckets = self.buckets.lock().unwrap();
+
+        let key = (scope, client_key.to_string());
+        if buckets.len() >= MAX_TRACKED_BUCKETS && !buckets.contains_key(&key) {
+            self.evict(&mut buckets, now);
+        }
+
+        let bucket = buckets.entry(key).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+
+        // Refill for the time elapsed since the last request
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * refill_per_second).min(capacity);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+
+        if refill_per_second <= 0.0 {
+            return Err(Duration::from_secs(60));
+        }
+
+        Err(Duration::from_secs_f64(
+            (1.0 - bucket.tokens) / refill_per_second,
+        ))
+    }
+
+    /// Make room for new clients. Buckets that have refilled are dropped first, since a
+    /// new bucket starts full anyway, then the least recently used ones.
+    fn evict(&self, buckets: &mut HashMap<(RateLimitScope, String), Bucket>, now: Instant) {
+        let settings = &self.settings;
+        buckets.retain(|(scope, _), bucket| {
+            let budget = budget_for(settings, *scope);
+            let refilled = bucket.tokens
+                + now.duration_since(bucket.updated).as_secs_f64()
+                    * f64::from(budget.refill_per_minute)
+                    / 60.0;
+            refilled < f64::from(budget.capacity)
+        });
+
+        if buckets.len() <= BUCKETS_KEPT_ON_EVICTION {
+            return;
+        }
+
+        let excess = buckets.len() - BUCKETS_KEPT_ON_EVICTION;
+        let mut last_used: Vec<Instant> = buckets.values().map(|bucket| bucket.updated).collect();
+        let (_, &mut cutoff, _) = last_used.select_nth_unstable(excess - 1);
+        buckets.retain(|_, bucket| bucket.updated > cutoff);
+    }
+}
+
+fn budget_for(settings: &RateLimitSettings, scope: RateLimitScope) -> &RateLimitBudget {
+    match scope {
+        RateLimitScope::Auth => &settings.auth,
+        RateLimitScope::Admin => &settings.admin,
+        RateLimitScope::Learning => &settings.learning,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(capacity: u32, refill_per_minute: u32) -> RateLimiter {
+        let budget = RateLimitBudget {
+            capacity,
+            refill_per_minute,
+        };
+        RateLimiter::new(RateLimitSettings {
+            enabled: true,
+            auth: budget.clone(),
+            admin: budget.clone(),
+            learning: budget,
+        })
+    }
+
+    // Pretend the client's last request was `ago` in the past
+    fn rewind(limiter: &RateLimiter, client_key: &str, ago: Duration) {
+        let mut buckets = limiter.buckets.lock().unwrap();
+        let bucket = buckets
+            .get_mut(&(RateLimitScope::Auth, client_key.to_string()))
+            .unwrap();
+        bucket.updated -= ago;
+    }
+
+    // Track a client with an empty bucket last used `ago` in the past
+    fn track_empty(limiter: &RateLimiter, client_key: String, ago: Duration) {
+        limiter.buckets.lock().unwrap().insert(
+            (RateLimitScope::Auth, client_key),
+            Bucket {
+                tokens: 0.0,
+                updated: Instant::now() - ago,
+            },
+        );
+    }
+
+    fn is_tracked(limiter: &RateLimiter, client_key: &str) -> bool {
+        limiter
+            .buckets
+            .lock()
+            .unwrap()
+            .contains_key(&(RateLimitScope::Auth, client_key.to_string()))
+    }
+
+    #[test]
+    fn allows_a_burst_up_to_capacity() {
+        let limiter = limiter(3, 60);
+        for _ in 0..3 {
+            assert!(limiter.check(RateLimitScope::Auth, "client").is_ok());
+        }
+
+        let wait = limiter.check(RateLimitScope::Auth, "client").unwrap_err();
+        assert!(wait > Duration::ZERO && wait <= Duration::from_secs(1));
+    }
+
+    #[test]
+    fn refills_tokens_over_time() {
+        let limiter = limiter(3, 60);
+        for _ in 0..3 {
+            limiter.check(RateLimitScope::Auth, "client").unwrap();
+        }
+
+        rewind(&limiter, "client", Duration::from_secs(2));
+        assert!(limiter.check(RateLimitScope::Auth, "client").is_ok());
+        assert!(limiter.check(RateLimitScope::Auth, "client").is_ok());
+        assert!(limiter.check(RateLimitScope::Auth, "client").is_err());
+    }
+
+    #[test]
+    fn refill_stops_at_capacity() {
+        let limiter = limiter(3, 60);
+        limiter.check(RateLimitScope::Auth, "client").unwrap();
+
+        rewind(&limiter, "client", Duration::from_secs(3600));
+        for _ in 0..3 {
+            assert!(limiter.check(RateLimitScope::Auth, "client").is_ok());
+        }
+        assert!(limiter.check(RateLimitScope::Auth, "client").is_err());
+    }
+
+    #[test]
+    fn keeps_separate_buckets_per_scope_and_client() {
+        let limiter = limiter(1, 60);
+        assert!(limiter.check(RateLimitScope::Auth, "client").is_ok());
+        assert!(limiter.check(RateLimitScope::Auth, "client").is_err());
+        assert!(limiter.check(RateLimitScope::Admin, "client").is_ok());
+        assert!(limiter.check(RateLimitScope::Auth, "other").is_ok());
+    }
+
+    #[test]
+    fn without_refill_asks_to_wait_a_minute() {
+        let limiter = limiter(1, 0);
+        limiter.check(RateLimitScope::Auth, "client").unwrap();
+        assert_eq!(
+            limiter.check(RateLimitScope::Auth, "client"),
+            Err(Duration::from_secs(60))
+        );
+    }
+
+    #[test]
+    fn disabled_limiter_allows_everything() {
+        let mut limiter = limiter(1, 0);
+        limiter.settings.enabled = false;
+        for _ in 0..10 {
+            assert!(limiter.check(RateLimitScope::Auth, "client").is_ok());
+        }
+    }
+
+    #[test]
+    fn eviction_drops_refilled_buckets_first() {
+        let limiter = limiter(3, 60);
+        for i in 0..MAX_TRACKED_BUCKETS {
+            // Even clients have been idle long enough to refill
+            let ago = if i % 2 == 0 {
+                Duration::from_secs(3600)
+            } else {
+                Duration::ZERO
+            };
+            track_empty(&limiter, format!("client-{i}"), ago);
+        }
+
+        limiter.check(RateLimitScope::Auth, "new").unwrap();
+
+        assert_eq!(
+            limiter.buckets.lock().unwrap().len(),
+            MAX_TRACKED_BUCKETS / 2 + 1
+        );
+        assert!(!is_tracked(&limiter, "client-0"));
+        assert!(is_tracked(&limiter, "client-1"));
+        assert!(is_tracked(&limiter, "new"));
+    }
+
+    #[test]
+    fn eviction_drops_least_recently_used_buckets() {
+        let limiter = limiter(3, 0);
+        for i in 0..MAX_TRACKED_BUCKETS {
+            // Higher numbers were used longer ago
+            track_empty(
+                &limiter,
+                format!("client-{i}"),
+                Duration::from_millis(i as u64),
+            );
+        }
+
+        limiter.check(RateLimitScope::Auth, "new").unwrap();
+
+        assert_eq!(
+            limiter.buckets.lock().unwrap().len(),
+            BUCKETS_KEPT_ON_EVICTION + 1
+        );
+        assert!(is_tracked(&limiter, "client-0"));
+        assert!(is_tracked(
+            &limiter,
+            &format!("client-{}", BUCKETS_KEPT_ON_EVICTION - 1)
+        ));
+        assert!(!is_tracked(
+            &limiter,
+            &format!("client-{BUCKETS_KEPT_ON_EVICTION}")
+        ));
+        assert!(!is_tracked(
+            &limiter,
+            &format!("client-{}", MAX_TRACKED_BUCKETS - 1)
+        ));
+    }
+
+    #[test]
+    fn tracked_clients_are_not_evicted_for_their_own_requests() {
+        let limiter = limiter(3, 0);
+        for i in 0..MAX_TRACKED_BUCKETS {
+            track_empty(
+                &limiter,
+                format!("client-{i}"),
+                Duration::from_millis(i as u64),
+            );
+        }
+
+        let _ = limiter.check(RateLimitScope::Auth, "client-0");
+
+        assert_eq!(limiter.buckets.lock().unwrap().len(), MAX_TRACKED_BUCKETS);
+    }
+}
@@ -0,0 +1,81 @@
+use subtle::ConstantTimeEq;
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use crate::utils::token::{generate_token, hash_token};
+
+// RFC 6238 defaults understood by every authenticator app
+const TOTP_DIGITS: usize = 6;
+const TOTP_STEP_SECONDS: u64 = 30;
+
+// Codes from the previous and next time step are accepted to allow for clock drift
+const TOTP_SKEW_STEPS: u64 = 1;
+
+const RECOVERY_CODE_LENGTH: usize = 10;
+
+// Generate a new random TOTP secret, base32 encoded
+pub fn generate_secret() -> String {
+    Secret::generate_secret().to_encoded().to_string()
+}
+
+// Build the otpauth:// URI that authenticator apps import (usually as a QR code)
+pub fn otpauth_uri(secret: &str, issuer: &str, account_name: &str) -> anyhow::Result<String> {
+    Ok(build_totp(secret, Some(issuer), account_name)?.get_url())
+}
+
+// Check a code against the secret at a unix time.
+// Returns the time step the code belongs to, so callers can reject replays of the same step.
+pub fn verify_code(secret: &str, code: &str, unix_time: u64) -> anyhow::Result<Option<u64>> {
+    let code = code.trim();
+    if code.len() != TOTP_DIGITS || !code.chars().all(|c| c.is_ascii_digit()) {
+        return Ok(None);
+    }
+
+    let totp = build_totp(secret, None, "")?;
+    let current_step = unix_time / TOTP_STEP_SECONDS;
+
+    let step = (current_step.saturating_sub(TOTP_SKEW_STEPS)..=current_step + TOTP_SKEW_STEPS)
+        .find(|step| {
+            let expected = totp.generate(step * TOTP_STEP_SECONDS);
+            expected.as_bytes().ct_eq(code.as_bytes()).into()
+        });
+
+    Ok(step)
+}
+
+// Generate one-time recovery codes, formatted as `xxxxx-xxxxx`
+pub fn generate_recovery_codes(count: usize) -> Vec<String> {
+    (0..count)
+        .map(|_| {
+            let code = generate_token(RECOVERY_CODE_LENGTH).to_lowercase();
+            let (first, second) = code.split_at(RECOVERY_CODE_LENGTH / 2);
+            format!("{}-{}", first, second)
+        })
+        .collect()
+}
+
+// Hash a recovery code, ignoring case and separators so codes can be typed loosely
+pub fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    hash_token(&normalized)
+}
+
+fn build_totp(secret: &str, issuer: Option<&str>, account_name: &str) -> anyhow::Result<TOTP> {
+    let secret = Secret::Encoded(secret.to_string())
+        .to_bytes()
+        .map_err(|e| anyhow::anyhow!("Invalid TOTP secret: {:?}", e))?;
+
+    TOTP::new(
+        Algorithm::SHA1,
+        TOTP_DIGITS,
+        TOTP_SKEW_STEPS as u8,
+        TOTP_STEP_SECONDS,
+        secret,
+        issuer.map(|issuer| issuer.to_string()),
+        account_name.to_string(),
+    )
+    .map_err(|e| anyhow::anyhow!("Failed to create TOTP: {:?}", e))
+}
@@ -0,0 +1,240 @@
+//! Social login against the mock OpenID Connect provider
+
+#[path = "../examples/mock_oidc/provider.rs"]
+mod provider;
+mod support;
+
+use provider::ProviderConfig;
+use reqwest::Url;
+use serde_json::{json, Value};
+use support::{client, json, start_backend, unique_email, Backend};
+
+const CLIENT_ID: &str = "mock-client";
+const CLIENT_SECRET: &str = "mock-secret";
+const REDIRECT_URI: &str = "http://localhost:5173/oidc/callback";
+
+// Password of accounts registered by the tests
+const PASSWORD: &str = "Sturdy-pass-91";
+
+/// Backend with a mock provider, named `mock`, that signs in one user
+struct Setup {
+    backend: Backend,
+    http: reqwest::Client,
+}
+
+impl Setup {
+    async fn start(email: &str, email_verified: bool, link_verified_email: bool) -> Option<Self> {
+        let config = ProviderConfig {
+            client_id: CLIENT_ID.to_string(),
+            client_secret: CLIENT_SECRET.to_string(),
+            subject: uuid::Uuid::now_v7().to_string(),
+            email: email.to_string(),
+            email_verified,
+            name: "Mock Student".to_string(),
+        };
+        let (issuer, _server) = provider::start(0, config).await.unwrap();
+
+        let link_verified_email = link_verified_email.to_string();
+        let backend = start_backend(&[
+            ("APP__OIDC__PROVIDERS__MOCK__ISSUER", issuer.as_str()),
+            ("APP__OIDC__PROVIDERS__MOCK__CLIENT_ID", CLIENT_ID),
+            ("APP__OIDC__PROVIDERS__MOCK__CLIENT_SECRET", CLIENT_SECRET),
+            ("APP__OIDC__PROVIDERS__MOCK__REDIRECT_URI", REDIRECT_URI),
+            (
+                "APP__OIDC__PROVIDERS__MOCK__LINK_VERIFIED_EMAIL",
+                link_verified_email.as_str(),
+            ),
+        ])
+        .await?;
+
+        Some(Self {
+            backend,
+            http: client(),
+        })
+    }
+
+    /// Start a login at the backend, returning the provider URL to open
+    async fn authorization_url(&self) -> Url {
+        let response = self
+            .http
+            .get(format!("{}/auth/oidc/mock/authorize", self.backend.url))
+            .send()
+            .await
+            .unwrap();
+        let (status, body) = json(response).await;
+        assert_eq!(status, 200, "{}", body);
+
+        Url::parse(body["data"]["authorization_url"].as_str().unwrap()).unwrap()
+    }
+
+    /// Open a provider URL, returning the `code` and `state` it redirects back with
+    async fn approve(&self, url: Url) -> (String, String) {
+        let response = self.http.get(url).send().await.unwrap();
+        assert_eq!(response.status().as_u16(), 302);
+
+        let location = response.headers()["location"].to_str().unwrap();
+        let location = Url::parse(location).unwrap();
+        let param = |name: &str| {
+            location
+                .query_pairs()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.into_owned())
+                .unwrap()
+        };
+
+        (param("code"), param("state"))
+    }
+
+    async fn callback(&self, code: &str, state: &str) -> (u16, Value) {
+        let response = self
+            .http
+            .post(format!("{}/auth/oidc/mock/callback", self.backend.url))
+            .json(&json!({ "code": code, "state": state }))
+            .send()
+            .await
+            .unwrap();
+        json(response).await
+    }
+
+    /// Go through the whole login
+    async fn login(&self) -> (u16, Value) {
+        let url = self.authorization_url().await;
+        let (code, state) = self.approve(url).await;
+        self.callback(&code, &state).await
+    }
+
+    /// Register an account with a password, returning its ID
+    async fn register(&self, email: &str) -> Value {
+        let response = self
+            .http
+            .post(format!("{}/auth/register", self.backend.url))
+            .json(&json!({ "email": email, "password": PASSWORD }))
+            .send()
+            .await
+            .unwrap();
+        let (status, body) = json(response).await;
+        assert_eq!(status, 200, "{}", body);
+
+        body["data"]["user"]["id"].clone()
+    }
+}
+
+#[tokio::test]
+async fn authorize_and_callback_sign_in() {
+    let email = unique_email("oidc");
+    let Some(setup) = Setup::start(&email, true, false).await else {
+        return;
+    };
+
+    let (status, body) = setup.login().await;
+    assert_eq!(status, 200, "{}", body);
+    assert!(body["data"]["access_token"]
+        .as_str()
+        .is_some_and(|token| !token.is_empty()));
+    assert_eq!(body["data"]["user"]["email"], email);
+
+    // Returning users are recognised by their subject
+    let (status, again) = setup.login().await;
+    assert_eq!(status, 200, "{}", again);
+    assert_eq!(again["data"]["user"]["id"], body["data"]["user"]["id"]);
+}
+
+#[tokio::test]
+async fn unknown_or_reused_state_is_rejected() {
+    let Some(setup) = Setup::start(&unique_email("state"), true, false).await else {
+        return;
+    };
+
+    let (code, state) = setup.approve(setup.authorization_url().await).await;
+    let (status, body) = setup.callback(&code, "not-a-login-state").await;
+    assert_eq!(status, 401, "{}", body);
+
+    let (status, body) = setup.callback(&code, &state).await;
+    assert_eq!(status, 200, "{}", body);
+
+    // The state is consumed by the first callback
+    let (code, _) = setup.approve(setup.authorization_url().await).await;
+    let (status, body) = setup.callback(&code, &state).await;
+    assert_eq!(status, 401, "{}", body);
+}
+
+#[tokio::test]
+async fn code_from_another_login_fails_pkce() {
+    let Some(setup) = Setup::start(&unique_email("pkce"), true, false).await else {
+        return;
+    };
+
+    // The code was issued for the first login's challenge, but is redeemed with the
+    // verifier of the second
+    let (code, _) = setup.approve(setup.authorization_url().await).await;
+    let (_, state) = setup.approve(setup.authorization_url().await).await;
+
+    let (status, body) = setup.callback(&code, &state).await;
+    assert_eq!(status, 401, "{}", body);
+    assert_eq!(
+        body["error"],
+        "Authorization code was rejected by the provider"
+    );
+}
+
+#[tokio::test]
+async fn id_token_for_another_nonce_is_rejected() {
+    let Some(setup) = Setup::start(&unique_email("nonce"), true, false).await else {
+        return;
+    };
+
+    let mut url = setup.authorization_url().await;
+    let params: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(key, value)| match key.as_ref() {
+            "nonce" => (key.into_owned(), "another-nonce".to_string()),
+            _ => (key.into_owned(), value.into_owned()),
+        })
+        .collect();
+    url.query_pairs_mut().clear().extend_pairs(params);
+
+    let (code, state) = setup.approve(url).await;
+    let (status, body) = setup.callback(&code, &state).await;
+    assert_eq!(status, 401, "{}", body);
+    assert_eq!(body["error"], "ID token does not belong to this login");
+}
+
+#[tokio::test]
+async fn unverified_email_does_not_link_existing_account() {
+    let email = unique_email("unverified");
+    let Some(setup) = Setup::start(&email, false, true).await else {
+        return;
+    };
+    let user_id = setup.register(&email).await;
+
+    // A separate account is created, without the unverified address
+    let (status, body) = setup.login().await;
+    assert_eq!(status, 200, "{}", body);
+    assert_ne!(body["data"]["user"]["id"], user_id);
+    assert_eq!(body["data"]["user"]["email"], "");
+}
+
+#[tokio::test]
+async fn verified_email_links_existing_account() {
+    let email = unique_email("verified");
+    let Some(setup) = Setup::start(&email, true, true).await else {
+        return;
+    };
+    let user_id = setup.register(&email).await;
+
+    let (status, body) = setup.login().await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["data"]["user"]["id"], user_id);
+}
+
+#[tokio::test]
+async fn verified_email_conflicts_without_linking() {
+    let email = unique_email("conflict");
+    let Some(setup) = Setup::start(&email, true, false).await else {
+        return;
+    };
+    setup.register(&email).await;
+
+    let (status, body) = setup.login().await;
+    assert_eq!(status, 409, "{}", body);
+}
@@ -0,0 +1,103 @@
+//! Helpers for tests that run the backend binary against a real database.
+//!
+//! Set `DATABASE_URL_TEST` (see `.env.example`) to a Postgres database with the
+//! migrations applied; without it the tests are skipped.
+
+use serde_json::Value;
+use std::net::TcpListener;
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+
+// Time the backend gets to connect to the database and start listening
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Backends are started one at a time, so the admin user is seeded only once
+static STARTUP: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// Backend process listening on a free local port, stopped when dropped
+pub struct Backend {
+    pub url: String,
+    process: Child,
+}
+
+impl Drop for Backend {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+/// Start the backend with extra `APP__*` settings.
+/// None when no test database is configured.
+pub async fn start_backend(settings: &[(&str, &str)]) -> Option<Backend> {
+    let Ok(database_url) = std::env::var("DATABASE_URL_TEST") else {
+        eprintln!("DATABASE_URL_TEST is not set, skipping");
+        return None;
+    };
+
+    let _startup = STARTUP.lock().await;
+
+    let port = TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("no free port")
+        .port();
+
+    // Only the settings given here, not those of the environment or `.env` file
+    let process = Command::new(env!("CARGO_BIN_EXE_chill-labs-backend"))
+        .current_dir(std::env::temp_dir())
+        .env_clear()
+        .env("APP__DATABASE__URL", database_url)
+        .env("APP__DATABASE__MIGRATE_ON_STARTUP", "false")
+        .env("APP__LOGGING__LOG_LEVEL", "warn")
+        .env("APP__LOGGING__LOG_FORMAT", "json")
+        .env("APP__SERVER__ENV", "dev")
+        .env("APP__SERVER__HOST", "127.0.0.1")
+        .env("APP__SERVER__PORT", port.to_string())
+        .env("APP__JWT__SECRET", "test-secret")
+        .env("APP__JWT__ACCESS_TOKEN_EXPIRATION_HOURS", "1")
+        .env("APP__JWT__REFRESH_TOKEN_EXPIRATION_HOURS", "24")
+        .env("APP__ADMIN__EMAIL", "admin@example.com")
+        .env("APP__ADMIN__PASSWORD", "adminpass1")
+        .envs(settings.iter().copied())
+        .stdout(Stdio::null())
+        .spawn()
+        .expect("failed to start the backend");
+
+    let backend = Backend {
+        url: format!("http://127.0.0.1:{}", port),
+        process,
+    };
+
+    // Wait until the server answers
+    let deadline = tokio::time::Instant::now() + STARTUP_TIMEOUT;
+    let healthcheck = format!("{}/healthcheck", backend.url);
+    while reqwest::get(&healthcheck).await.is_err() {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "backend did not start in time"
+        );
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    Some(backend)
+}
+
+/// HTTP client that leaves redirects to the test
+pub fn client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap()
+}
+
+/// Status code and JSON body of a response
+pub async fn json(response: reqwest::Response) -> (u16, Value) {
+    let status = response.status().as_u16();
+    let body = response.json().await.unwrap_or(Value::Null);
+    (status, body)
+}
+
+/// Email address no other test uses
+pub fn unique_email(prefix: &str) -> String {
+    format!("{}-{}@example.com", prefix, uuid::Uuid::now_v7())
+}